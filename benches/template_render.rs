@@ -3,7 +3,7 @@ use std::{hint::black_box, time::Duration};
 use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
 use libspy::{
     entry::Entry,
-    template::{Template, context_value},
+    template::{Settings, Template, context_value},
 };
 use once_cell::sync::Lazy;
 
@@ -15,12 +15,20 @@ static ENTRY: Lazy<Entry> = Lazy::new(|| {
         .expect("fixture entry JSON should deserialize into Entry")
 });
 
-static SIMPLE_TEMPLATE: Lazy<Template> = Lazy::new(|| Template::new("{{ title }}".to_owned()));
-static COMPLEX_TEMPLATE: Lazy<Template> =
-    Lazy::new(|| Template::new(COMPLEX_TEMPLATE_FIXTURE.to_owned()));
+static SIMPLE_TEMPLATE_SOURCE: &str = "{{ title }}";
+
+static SIMPLE_TEMPLATE: Lazy<Template> = Lazy::new(|| {
+    Template::new(SIMPLE_TEMPLATE_SOURCE.to_owned(), Settings::default())
+        .expect("title template compiles")
+});
+static COMPLEX_TEMPLATE: Lazy<Template> = Lazy::new(|| {
+    Template::new(COMPLEX_TEMPLATE_FIXTURE.to_owned(), Settings::default())
+        .expect("full template compiles")
+});
 
 struct Scenario {
     id: &'static str,
+    source: &'static str,
     template: &'static Template,
     bytes_out: usize,
 }
@@ -31,7 +39,8 @@ static SCENARIOS: Lazy<[Scenario; 2]> = Lazy::new(|| {
     [
         Scenario {
             id: "title_only",
-            template: &*SIMPLE_TEMPLATE,
+            source: SIMPLE_TEMPLATE_SOURCE,
+            template: &SIMPLE_TEMPLATE,
             bytes_out: SIMPLE_TEMPLATE
                 .render(entry)
                 .expect("title template renders for sizing")
@@ -39,7 +48,8 @@ static SCENARIOS: Lazy<[Scenario; 2]> = Lazy::new(|| {
         },
         Scenario {
             id: "full_document",
-            template: &*COMPLEX_TEMPLATE,
+            source: COMPLEX_TEMPLATE_FIXTURE,
+            template: &COMPLEX_TEMPLATE,
             bytes_out: COMPLEX_TEMPLATE
                 .render(entry)
                 .expect("full template renders for sizing")
@@ -78,6 +88,53 @@ fn render_templates(c: &mut Criterion) {
     group.finish();
 }
 
+/// Compiling for every entry, as rendering did before templates were
+/// compiled once up front, against reusing one compiled template.
+fn compile_once_vs_per_render(c: &mut Criterion) {
+    let entry = &*ENTRY;
+    let mut group = c.benchmark_group("template_compile");
+
+    for scenario in SCENARIOS.iter() {
+        group.throughput(Throughput::Bytes(scenario.bytes_out as u64));
+        group.bench_with_input(
+            BenchmarkId::new("per_render", scenario.id),
+            scenario,
+            |b, scenario| {
+                b.iter_with_large_drop(|| {
+                    Template::new(black_box(scenario.source).to_owned(), Settings::default())
+                        .expect("template compiles")
+                        .render(black_box(entry))
+                        .expect("template renders")
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("compiled_once", scenario.id),
+            scenario,
+            |b, scenario| {
+                b.iter_with_large_drop(|| {
+                    scenario
+                        .template
+                        .render(black_box(entry))
+                        .expect("template renders")
+                });
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("compile_only", scenario.id),
+            scenario,
+            |b, scenario| {
+                b.iter_with_large_drop(|| {
+                    Template::new(black_box(scenario.source).to_owned(), Settings::default())
+                        .expect("template compiles")
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
 fn build_context_value(c: &mut Criterion) {
     let entry = &*ENTRY;
     c.bench_function("template_context/value_from_entry", |b| {
@@ -95,6 +152,6 @@ fn configure_criterion() -> Criterion {
 criterion_group! {
     name = benches;
     config = configure_criterion();
-    targets = render_templates, build_context_value
+    targets = render_templates, compile_once_vs_per_render, build_context_value
 }
 criterion_main!(benches);
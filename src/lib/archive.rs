@@ -0,0 +1,244 @@
+use std::borrow::Cow;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use crate::fetch::{Fetcher, Method};
+
+/// The Internet Archive's Wayback Machine TimeGate.
+pub const WAYBACK_TIMEGATE: &str = "https://web.archive.org/web/";
+
+/// TimeGate redirects followed before giving up.
+const MAX_HOPS: usize = 5;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to query archive at {url}: {error}")]
+    Request { url: Url, error: Box<ureq::Error> },
+    #[error("No archived copy of {0}")]
+    NotArchived(Url),
+    #[error("Archive returned no snapshot for {0}")]
+    NoMemento(Url),
+}
+
+/// An archived copy of a page.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Where the archived copy can be fetched.
+    pub url: Url,
+    /// When the archive captured the page.
+    pub captured: DateTime<Utc>,
+}
+
+impl Snapshot {
+    /// Undo the archive's rewriting of the links in this snapshot, so they
+    /// point at the original site again. Archives such as the Wayback
+    /// Machine prefix each link with the snapshot's path up to the original
+    /// URL (`/web/20200102030405/`), perhaps with their own origin before it
+    /// and a modifier such as `im_` after the timestamp.
+    pub fn restore_links<'b>(&self, body: &'b str) -> Cow<'b, str> {
+        let path = self.url.path();
+        let Some(at) = path.find("/http://").or_else(|| path.find("/https://")) else {
+            return Cow::Borrowed(body);
+        };
+        let (directory, stamp) = path[..at].rsplit_once('/').unwrap_or_default();
+        let stamp = if !stamp.is_empty() && stamp.bytes().all(|b| b.is_ascii_digit()) {
+            r"\d+".to_owned()
+        } else {
+            regex::escape(stamp)
+        };
+        let origin = self
+            .url
+            .host_str()
+            .map(|host| format!(r"(?:(?:https?:)?//{}(?::\d+)?)?", regex::escape(host)))
+            .unwrap_or_default();
+        let pattern = format!(
+            r"{origin}{}/{stamp}(?:[a-z]{{2}}_)?/(https?:)",
+            regex::escape(directory)
+        );
+        match Regex::new(&pattern) {
+            Ok(prefix) => prefix.replace_all(body, "$1"),
+            Err(_) => Cow::Borrowed(body),
+        }
+    }
+}
+
+/// A web archive that can look up earlier copies of a page.
+pub trait ArchiveProvider {
+    /// The snapshot of `url` closest to `at`, or the most recent one,
+    /// looked up through `fetcher`.
+    fn closest(
+        &self,
+        fetcher: &dyn Fetcher,
+        url: &Url,
+        at: Option<DateTime<Utc>>,
+    ) -> Result<Snapshot, Error>;
+}
+
+/// A Memento (RFC 7089) TimeGate, such as the Wayback Machine's.
+#[derive(Debug, Clone)]
+pub struct Memento {
+    timegate: Url,
+}
+
+impl Memento {
+    /// A TimeGate that takes the original URL appended to `timegate`.
+    pub fn new(timegate: Url) -> Self {
+        Memento { timegate }
+    }
+}
+
+impl Default for Memento {
+    fn default() -> Self {
+        Memento::new(Url::parse(WAYBACK_TIMEGATE).expect("Wayback TimeGate URL is valid"))
+    }
+}
+
+impl ArchiveProvider for Memento {
+    /// Negotiate with the TimeGate via `Accept-Datetime`, following its
+    /// redirects until a response carries `Memento-Datetime`.
+    fn closest(
+        &self,
+        fetcher: &dyn Fetcher,
+        url: &Url,
+        at: Option<DateTime<Utc>>,
+    ) -> Result<Snapshot, Error> {
+        let mut current = Url::parse(&format!("{}{}", self.timegate, url))
+            .map_err(|_| Error::NotArchived(url.clone()))?;
+        let accept = at
+            .unwrap_or_else(Utc::now)
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string();
+        for _ in 0..MAX_HOPS {
+            let response = fetcher
+                .send(Method::Head, &current, &[("accept-datetime", &accept)], 0)
+                .map_err(|error| Error::Request {
+                    url: current.clone(),
+                    error: Box::new(error),
+                })?;
+            let header = |name: &str| response.header(name).map(str::to_owned);
+            if let Some(captured) = header("memento-datetime").and_then(|d| parse_http_date(&d)) {
+                // A memento may name its own URL when served without a redirect.
+                let url = header("content-location")
+                    .and_then(|l| current.join(&l).ok())
+                    .unwrap_or(current);
+                return Ok(Snapshot { url, captured });
+            }
+            match (response.status, header("location")) {
+                (300..=399, Some(location)) => {
+                    current = current
+                        .join(&location)
+                        .map_err(|_| Error::NoMemento(url.clone()))?;
+                }
+                (404, _) => return Err(Error::NotArchived(url.clone())),
+                _ => return Err(Error::NoMemento(url.clone())),
+            }
+        }
+        Err(Error::NoMemento(url.clone()))
+    }
+}
+
+/// Parse an HTTP date (`Sun, 06 Nov 1994 08:49:37 GMT`).
+fn parse_http_date(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(&raw.trim().replace("GMT", "+0000"))
+        .ok()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+    use crate::fetch::Live;
+
+    /// A stand-in TimeGate that redirects to a memento which reports its
+    /// capture time, recording the `Accept-Datetime` it was sent.
+    fn serve_timegate() -> (Url, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 302 Found\r\nLocation: /web/20200102030405/https://example.com/\r\n",
+                "HTTP/1.1 200 OK\r\nMemento-Datetime: Thu, 02 Jan 2020 03:04:05 GMT\r\n",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                requests.push(head);
+                write!(
+                    stream,
+                    "{response}Content-Length: 0\r\nConnection: close\r\n\r\n"
+                )
+                .unwrap();
+            }
+            requests
+        });
+        (Url::parse(&format!("{base}/web/")).unwrap(), handle)
+    }
+
+    #[test]
+    fn resolves_snapshot_through_timegate() {
+        let (timegate, server) = serve_timegate();
+        let url = Url::parse("https://example.com/").unwrap();
+        let at = DateTime::from_timestamp(1_577_934_245, 0);
+        let snapshot = Memento::new(timegate.clone())
+            .closest(&Live, &url, at)
+            .unwrap();
+        assert_eq!(
+            snapshot.url,
+            timegate
+                .join("/web/20200102030405/https://example.com/")
+                .unwrap()
+        );
+        assert_eq!(snapshot.captured, at.unwrap());
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("HEAD /web/https://example.com/ "));
+        assert!(requests[0].contains("Thu, 02 Jan 2020 03:04:05 GMT"));
+    }
+
+    #[test]
+    fn restores_rewritten_links() {
+        let snapshot = Snapshot {
+            url: Url::parse("https://web.archive.org/web/20200102030405/https://example.com/post")
+                .unwrap(),
+            captured: DateTime::from_timestamp(1_577_934_245, 0).unwrap(),
+        };
+        let body = r#"<link rel="canonical" href="https://web.archive.org/web/20200102030405/https://example.com/post">
+<img src="/web/20200102030405im_/https://example.com/a.png">
+<a href="//web.archive.org/web/20191231000000/http://other.example/">
+<a href="/web/notes">"#;
+        assert_eq!(
+            snapshot.restore_links(body),
+            r#"<link rel="canonical" href="https://example.com/post">
+<img src="https://example.com/a.png">
+<a href="http://other.example/">
+<a href="/web/notes">"#
+        );
+    }
+
+    #[test]
+    fn parses_http_dates() {
+        assert_eq!(
+            parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"),
+            DateTime::from_timestamp(784_111_777, 0)
+        );
+        assert_eq!(parse_http_date("yesterday"), None);
+    }
+}
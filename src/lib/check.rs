@@ -0,0 +1,444 @@
+use std::{collections::HashMap, fmt::Write, io};
+
+use clap::ValueEnum;
+use scraper::Html;
+use serde::Serialize;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    entry::{Entry, first_text},
+    fetch::{Fetcher, MAX_BODY, Method},
+    text::words,
+};
+
+/// Redirects followed before giving up on a link.
+const MAX_HOPS: usize = 10;
+/// Share of title words a page must have in common with the site's error page
+/// to count as a soft 404.
+const SOFT_404_SIMILARITY: f64 = 0.8;
+
+/// How a link check report is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ReportFormat {
+    /// One aligned line per entry.
+    #[default]
+    Table,
+    /// One JSON object per line.
+    Json,
+}
+
+/// What became of a bookmarked link.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    /// Temporarily redirected; `location` is where the chain ended.
+    Redirected {
+        location: Url,
+    },
+    /// Every redirect in the chain was permanent (301 or 308).
+    MovedPermanently {
+        location: Url,
+    },
+    NotFound,
+    Gone,
+    /// Any other non-success status.
+    Http {
+        code: u16,
+    },
+    /// The page loads, but looks like the site's "not found" page.
+    Soft404,
+    DnsFailure,
+    TlsError {
+        message: String,
+    },
+    /// The connection failed or timed out.
+    Unreachable {
+        message: String,
+    },
+}
+
+impl Status {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Status::Ok | Status::Redirected { .. })
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Status::Ok => "ok",
+            Status::Redirected { .. } => "redirected",
+            Status::MovedPermanently { .. } => "moved",
+            Status::NotFound => "404",
+            Status::Gone => "410",
+            Status::Http { .. } => "http-error",
+            Status::Soft404 => "soft-404",
+            Status::DnsFailure => "dns",
+            Status::TlsError { .. } => "tls",
+            Status::Unreachable { .. } => "unreachable",
+        }
+    }
+
+    fn detail(&self) -> String {
+        match self {
+            Status::Redirected { location } | Status::MovedPermanently { location } => {
+                location.to_string()
+            }
+            Status::Http { code } => code.to_string(),
+            Status::TlsError { message } | Status::Unreachable { message } => message.clone(),
+            _ => String::new(),
+        }
+    }
+}
+
+/// The result of checking one entry.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub id: &'a Uuid,
+    pub url: &'a Url,
+    #[serde(flatten)]
+    pub status: Status,
+}
+
+/// One response in a redirect chain.
+#[derive(Debug)]
+struct Hop {
+    status: u16,
+    location: Option<Url>,
+}
+
+/// Checks links, remembering each host's error page for soft-404 detection.
+pub struct Checker<'f> {
+    fetcher: &'f dyn Fetcher,
+    /// Whether to fetch pages that load and compare them with the host's
+    /// error page, at the cost of a GET per page and one per host.
+    soft_404: bool,
+    /// Title of the page each host serves for a path that cannot exist, or
+    /// `None` if it answers such paths with an error status.
+    error_titles: HashMap<String, Option<String>>,
+}
+
+impl<'f> Checker<'f> {
+    pub fn new(fetcher: &'f dyn Fetcher, soft_404: bool) -> Self {
+        Checker {
+            fetcher,
+            soft_404,
+            error_titles: HashMap::new(),
+        }
+    }
+
+    pub fn check<'a>(&mut self, entry: &'a Entry) -> Report<'a> {
+        Report {
+            id: entry.id(),
+            url: entry.url(),
+            status: self.status(entry.url()),
+        }
+    }
+
+    fn status(&mut self, url: &Url) -> Status {
+        let hops = match follow(self.fetcher, url) {
+            Ok(hops) => hops,
+            Err(error) => return failure(&error),
+        };
+        let status = classify(url, &hops);
+        if self.soft_404 && (status.is_ok() || matches!(status, Status::MovedPermanently { .. })) {
+            let last = hops.iter().rev().find_map(|h| h.location.as_ref());
+            if self.is_soft_404(last.unwrap_or(url)) {
+                return Status::Soft404;
+            }
+        }
+        status
+    }
+
+    /// Compare the page's title with the one the host gives a made-up path.
+    fn is_soft_404(&mut self, url: &Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return false;
+        };
+        let Some(title) = page_title(self.fetcher, url) else {
+            return false;
+        };
+        let error_title = self.error_titles.entry(host.to_owned()).or_insert_with(|| {
+            url.join(&format!("/{}", Uuid::new_v4().simple()))
+                .ok()
+                .and_then(|probe| page_title(self.fetcher, &probe))
+        });
+        error_title
+            .as_deref()
+            .is_some_and(|error| similarity(&title, error) >= SOFT_404_SIMILARITY)
+    }
+}
+
+/// Issue HEAD requests along the redirect chain, falling back to GET for
+/// servers that do not support HEAD.
+fn follow(fetcher: &dyn Fetcher, url: &Url) -> Result<Vec<Hop>, ureq::Error> {
+    let mut hops = Vec::new();
+    let mut current = url.clone();
+    while hops.len() < MAX_HOPS {
+        let mut response = fetcher.send(Method::Head, &current, &[], 0)?;
+        if matches!(response.status, 405 | 501) {
+            response = fetcher.send(Method::Get, &current, &[], MAX_BODY)?;
+        }
+        let status = response.status;
+        let location = response
+            .header("location")
+            .and_then(|l| current.join(l).ok())
+            .filter(|_| (300..=399).contains(&status));
+        let next = location.clone();
+        hops.push(Hop { status, location });
+        match next {
+            Some(next) => current = next,
+            None => break,
+        }
+    }
+    Ok(hops)
+}
+
+/// Classify a redirect chain by its final status, then by its redirects.
+fn classify(url: &Url, hops: &[Hop]) -> Status {
+    let Some(last) = hops.last() else {
+        return Status::Ok;
+    };
+    match last.status {
+        404 => return Status::NotFound,
+        410 => return Status::Gone,
+        200..=299 => {}
+        // Includes redirects still going after MAX_HOPS.
+        code => return Status::Http { code },
+    }
+    let Some(location) = hops.iter().rev().find_map(|h| h.location.clone()) else {
+        return Status::Ok;
+    };
+    if location == *url {
+        Status::Ok
+    } else if hops
+        .iter()
+        .filter(|h| h.location.is_some())
+        .all(|h| matches!(h.status, 301 | 308))
+    {
+        Status::MovedPermanently { location }
+    } else {
+        Status::Redirected { location }
+    }
+}
+
+fn failure(error: &ureq::Error) -> Status {
+    match error {
+        ureq::Error::HostNotFound | ureq::Error::Timeout(ureq::Timeout::Resolve) => {
+            Status::DnsFailure
+        }
+        ureq::Error::Io(e) if is_resolver_error(e) => Status::DnsFailure,
+        ureq::Error::Tls(message) => Status::TlsError {
+            message: (*message).to_owned(),
+        },
+        ureq::Error::Rustls(e) => Status::TlsError {
+            message: e.to_string(),
+        },
+        e => Status::Unreachable {
+            message: e.to_string(),
+        },
+    }
+}
+
+/// Whether an I/O error came from resolving the host rather than from
+/// the connection. The system resolver's failures carry no OS error code
+/// (apart from `EAI_SYSTEM`), while failed connections always do.
+fn is_resolver_error(error: &io::Error) -> bool {
+    use io::ErrorKind::*;
+    error.raw_os_error().is_none()
+        && !matches!(
+            error.kind(),
+            ConnectionRefused
+                | ConnectionReset
+                | ConnectionAborted
+                | NotConnected
+                | HostUnreachable
+                | NetworkUnreachable
+                | AddrNotAvailable
+                | TimedOut
+                | UnexpectedEof
+                | InvalidData
+        )
+}
+
+/// The `<title>` of a page fetched with redirects followed, if it loads.
+fn page_title(fetcher: &dyn Fetcher, url: &Url) -> Option<String> {
+    let body = fetcher.get(url).ok()?;
+    first_text(&Html::parse_document(&body), "head > title")
+}
+
+/// Jaccard similarity of the two titles' word sets.
+fn similarity(a: &str, b: &str) -> f64 {
+    let a: std::collections::HashSet<String> = words(a).into_iter().collect();
+    let b: std::collections::HashSet<String> = words(b).into_iter().collect();
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / a.union(&b).count() as f64
+}
+
+/// Write reports in the chosen format.
+pub fn render(format: ReportFormat, reports: &[Report]) -> Result<String, serde_json::Error> {
+    match format {
+        ReportFormat::Json => Ok(reports
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n")),
+        ReportFormat::Table => {
+            let mut out = String::new();
+            for report in reports {
+                let status = report.status.label();
+                let detail = report.status.detail();
+                let _ = write!(out, "{status:<12} {}", report.url);
+                if !detail.is_empty() {
+                    let _ = write!(out, "  {detail}");
+                }
+                out.push('\n');
+            }
+            out.pop();
+            Ok(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::fetch::Response;
+
+    /// A site that answers every path with the same "not found" page.
+    #[derive(Default)]
+    struct SoftErrors {
+        requests: RefCell<Vec<Method>>,
+    }
+
+    impl Fetcher for SoftErrors {
+        fn send(
+            &self,
+            method: Method,
+            _: &Url,
+            _: &[(&str, &str)],
+            _: usize,
+        ) -> Result<Response, ureq::Error> {
+            self.requests.borrow_mut().push(method);
+            Ok(Response {
+                status: 200,
+                headers: vec![("content-type".into(), "text/html".into())],
+                body: b"<head><title>Page not found</title></head>".to_vec(),
+            })
+        }
+    }
+
+    fn hop(status: u16, location: Option<&str>) -> Hop {
+        Hop {
+            status,
+            location: location.map(|l| Url::parse(l).unwrap()),
+        }
+    }
+
+    #[test]
+    fn classifies_redirect_chains() {
+        let url = Url::parse("http://example.com/a").unwrap();
+        let moved = [
+            hop(301, Some("https://example.com/a")),
+            hop(308, Some("https://example.com/b")),
+            hop(200, None),
+        ];
+        assert_eq!(
+            classify(&url, &moved),
+            Status::MovedPermanently {
+                location: Url::parse("https://example.com/b").unwrap()
+            }
+        );
+        let temporary = [
+            hop(301, Some("https://example.com/a")),
+            hop(302, Some("https://example.com/login")),
+            hop(200, None),
+        ];
+        assert!(matches!(
+            classify(&url, &temporary),
+            Status::Redirected { .. }
+        ));
+        let dead = [hop(301, Some("https://example.com/a")), hop(404, None)];
+        assert_eq!(classify(&url, &dead), Status::NotFound);
+        assert_eq!(classify(&url, &[hop(410, None)]), Status::Gone);
+        assert_eq!(
+            classify(&url, &[hop(503, None)]),
+            Status::Http { code: 503 }
+        );
+        assert_eq!(classify(&url, &[hop(204, None)]), Status::Ok);
+    }
+
+    #[test]
+    fn soft_404s_are_checked_only_when_asked_for() {
+        let url = Url::parse("https://example.com/a").unwrap();
+        let site = SoftErrors::default();
+        assert_eq!(Checker::new(&site, false).status(&url), Status::Ok);
+        assert_eq!(*site.requests.borrow(), [Method::Head]);
+        assert_eq!(Checker::new(&site, true).status(&url), Status::Soft404);
+    }
+
+    #[test]
+    fn failures_are_classified_by_kind() {
+        let resolver = io::Error::other("failed to lookup address information");
+        assert_eq!(failure(&ureq::Error::Io(resolver)), Status::DnsFailure);
+        assert_eq!(failure(&ureq::Error::HostNotFound), Status::DnsFailure);
+        assert_eq!(
+            failure(&ureq::Error::Timeout(ureq::Timeout::Resolve)),
+            Status::DnsFailure
+        );
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert!(matches!(
+            failure(&ureq::Error::Io(refused)),
+            Status::Unreachable { .. }
+        ));
+        let os = io::Error::from_raw_os_error(111);
+        assert!(matches!(
+            failure(&ureq::Error::Io(os)),
+            Status::Unreachable { .. }
+        ));
+    }
+
+    #[test]
+    fn similar_titles_score_high() {
+        assert!(
+            similarity("Page not found | Example", "Page Not Found - Example")
+                >= SOFT_404_SIMILARITY
+        );
+        assert!(
+            similarity("Rust in the kernel | LWN", "Page not found | LWN") < SOFT_404_SIMILARITY
+        );
+        assert_eq!(similarity("", "anything"), 0.0);
+    }
+
+    #[test]
+    fn renders_json_and_table() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let id = Uuid::nil();
+        let reports = [
+            Report {
+                id: &id,
+                url: &url,
+                status: Status::Ok,
+            },
+            Report {
+                id: &id,
+                url: &url,
+                status: Status::Http { code: 500 },
+            },
+        ];
+        let json = render(ReportFormat::Json, &reports).unwrap();
+        assert!(
+            json.lines()
+                .nth(1)
+                .unwrap()
+                .contains(r#""status":"http","code":500"#)
+        );
+        let table = render(ReportFormat::Table, &reports).unwrap();
+        assert_eq!(table.lines().count(), 2);
+        assert!(table.ends_with("https://example.com/  500"));
+    }
+}
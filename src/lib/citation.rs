@@ -0,0 +1,174 @@
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::entry::{add_authors, collapse_ws, first_attr};
+
+/// Bibliographic metadata exposed by scholarly pages through Highwire Press
+/// (`citation_*`) meta tags, as indexed by Google Scholar.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Citation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub journal: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conference: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue: Option<String>,
+    /// Page range, either a single page or `first-last`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pages: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pdf_url: Option<String>,
+}
+
+impl Citation {
+    /// Collect the bibliographic fields from a document, if it carries any.
+    pub(crate) fn from_document(doc: &Html) -> Option<Self> {
+        let first_page = citation_meta(doc, "citation_firstpage");
+        let last_page = citation_meta(doc, "citation_lastpage");
+        let pages = match (first_page, last_page) {
+            (Some(first), Some(last)) if first != last => Some(format!("{first}-{last}")),
+            (Some(first), _) => Some(first),
+            (None, last) => last,
+        };
+        let citation = Citation {
+            doi: citation_meta(doc, "citation_doi").map(|d| normalise_doi(&d)),
+            journal: citation_meta(doc, "citation_journal_title"),
+            conference: citation_meta(doc, "citation_conference_title"),
+            publisher: citation_meta(doc, "citation_publisher"),
+            volume: citation_meta(doc, "citation_volume"),
+            issue: citation_meta(doc, "citation_issue"),
+            pages,
+            issn: citation_meta(doc, "citation_issn"),
+            pdf_url: citation_meta(doc, "citation_pdf_url"),
+        };
+        if citation == Citation::default() {
+            None
+        } else {
+            Some(citation)
+        }
+    }
+}
+
+fn citation_meta(doc: &Html, name: &str) -> Option<String> {
+    first_attr(doc, &format!(r#"meta[name="{name}"]"#), "content")
+}
+
+/// Title from `citation_title`.
+pub(crate) fn citation_title(doc: &Html) -> Option<String> {
+    citation_meta(doc, "citation_title")
+}
+
+/// Authors from repeated `citation_author` tags, falling back to the legacy
+/// semicolon-separated `citation_authors` tag.
+pub(crate) fn citation_authors(doc: &Html) -> Option<Vec<String>> {
+    let sel = Selector::parse(r#"meta[name="citation_author"]"#).ok()?;
+    let mut out = Vec::new();
+    add_authors(
+        &mut out,
+        doc.select(&sel)
+            .filter_map(|m| m.value().attr("content"))
+            .map(collapse_ws)
+            .filter(|s| !s.is_empty()),
+    );
+    if out.is_empty()
+        && let Some(all) = citation_meta(doc, "citation_authors")
+    {
+        add_authors(
+            &mut out,
+            all.split(';').map(collapse_ws).filter(|s| !s.is_empty()),
+        );
+    }
+    if out.is_empty() { None } else { Some(out) }
+}
+
+/// Raw publication date, preferring `citation_publication_date` over the
+/// older `citation_date` and `citation_online_date` tags.
+pub(crate) fn citation_date(doc: &Html) -> Option<String> {
+    citation_meta(doc, "citation_publication_date")
+        .or_else(|| citation_meta(doc, "citation_date"))
+        .or_else(|| citation_meta(doc, "citation_online_date"))
+}
+
+/// Strip resolver prefixes so only the bare `10.xxxx/...` identifier remains.
+fn normalise_doi(raw: &str) -> String {
+    let trimmed = raw.trim();
+    let lower = trimmed.to_ascii_lowercase();
+    for prefix in [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "http://dx.doi.org/",
+        "doi:",
+    ] {
+        if lower.starts_with(prefix) {
+            return trimmed[prefix.len()..].trim().to_owned();
+        }
+    }
+    trimmed.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHOLARLY_PAGE: &str = r#"<html><head>
+        <title>Journal Home | Example Press</title>
+        <meta name="citation_title" content="On   Computable Numbers">
+        <meta name="citation_author" content="Turing, Alan">
+        <meta name="citation_author" content="Church, Alonzo">
+        <meta name="citation_publication_date" content="1936/11/12">
+        <meta name="citation_journal_title" content="Proceedings of the London Mathematical Society">
+        <meta name="citation_volume" content="s2-42">
+        <meta name="citation_issue" content="1">
+        <meta name="citation_firstpage" content="230">
+        <meta name="citation_lastpage" content="265">
+        <meta name="citation_doi" content="doi:10.1112/plms/s2-42.1.230">
+        <meta name="citation_pdf_url" content="https://example.com/turing.pdf">
+        </head><body></body></html>"#;
+
+    #[test]
+    fn extracts_highwire_fields() {
+        let doc = Html::parse_document(SCHOLARLY_PAGE);
+        assert_eq!(
+            citation_title(&doc).as_deref(),
+            Some("On Computable Numbers")
+        );
+        let authors = citation_authors(&doc).unwrap();
+        assert_eq!(authors, ["Turing, Alan", "Church, Alonzo"]);
+        assert_eq!(citation_date(&doc).as_deref(), Some("1936/11/12"));
+
+        let citation = Citation::from_document(&doc).unwrap();
+        assert_eq!(citation.doi.as_deref(), Some("10.1112/plms/s2-42.1.230"));
+        assert_eq!(citation.volume.as_deref(), Some("s2-42"));
+        assert_eq!(citation.issue.as_deref(), Some("1"));
+        assert_eq!(citation.pages.as_deref(), Some("230-265"));
+        assert_eq!(
+            citation.pdf_url.as_deref(),
+            Some("https://example.com/turing.pdf")
+        );
+    }
+
+    #[test]
+    fn non_scholarly_page_has_no_citation() {
+        let doc = Html::parse_document("<html><head><title>Blog</title></head></html>");
+        assert!(Citation::from_document(&doc).is_none());
+        assert!(citation_authors(&doc).is_none());
+    }
+
+    #[test]
+    fn legacy_semicolon_authors_are_split() {
+        let doc = Html::parse_document(
+            r#"<head><meta name="citation_authors" content="Hopper, Grace; Lovelace, Ada"></head>"#,
+        );
+        let authors = citation_authors(&doc).unwrap();
+        assert_eq!(authors, ["Hopper, Grace", "Lovelace, Ada"]);
+    }
+}
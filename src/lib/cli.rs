@@ -1,16 +1,555 @@
-use clap::Parser;
+use std::{io::IsTerminal, path::PathBuf};
+
+use chrono::TimeDelta;
+use std::ffi::OsString;
+
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
 use url::Url;
+
+use crate::{
+    archive::WAYBACK_TIMEGATE,
+    check::ReportFormat,
+    dedup::OnDuplicate,
+    entry::Extraction,
+    export::{Format, Naming, Options, feed::FeedFormat},
+    filter::{Filter, Sort},
+    import::Source,
+    snapshot::DEFAULT_BUDGET,
+    template::{self, Context, Escape, Settings, Template, Undefined},
+};
+
 #[derive(Parser, Debug)]
 #[command(
     version,
     name = "spy",
-    about = "Fetch information on a webpage on the command line"
+    about = "Fetch information on a webpage on the command line",
+    subcommand_negates_reqs = true,
+    override_usage = "spy [OPTIONS] <URL>\n       spy [OPTIONS] <COMMAND>"
 )]
 pub struct Cli {
     /// The bookmark to the URL.
-    pub url: Url,
+    #[arg(required = true)]
+    pub url: Option<Url>,
+
+    #[command(flatten)]
+    pub output: Output,
+
+    #[command(flatten)]
+    pub archive: Archive,
+
+    #[arg(long, global = true, value_name = "PATH")]
+    /// Bookmark store to use. Defaults to `$XDG_DATA_HOME/spy/entries.jsonl`.
+    pub store: Option<PathBuf>,
+
+    #[arg(long, global = true, value_name = "FILE")]
+    /// Append every HTTP exchange made while fetching pages to this WARC file.
+    pub warc: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// Parse arguments, which may put global options before or after the
+    /// subcommand. A URL is fetched only when there is no subcommand.
+    pub fn try_parse_args<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let cli = Self::try_parse_from(args)?;
+        if let (Some(url), Some(_)) = (&cli.url, &cli.command) {
+            return Err(Self::command().error(
+                ErrorKind::ArgumentConflict,
+                format!("the URL {url} cannot be used with a subcommand"),
+            ));
+        }
+        Ok(cli)
+    }
+}
 
-    #[arg(short = 't', long)]
+/// How entries are written to stdout.
+#[derive(Args, Debug)]
+pub struct Output {
+    #[arg(short = 't', long, global = true)]
     /// Optional template string. If none is passed in, it will be serialised as JSON.
+    /// Besides the entry's fields, templates can use the filters `slugify`,
+    /// `truncate_words`, `strip_html`, `markdown`, `date`, `domain`, `urlencode`,
+    /// `shell_quote`, `json`, `yaml` and `wrap`, and the function `now()`.
     pub template: Option<String>,
+
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "template")]
+    /// Read the template from a file.
+    pub template_file: Option<PathBuf>,
+
+    #[arg(
+        short = 'T',
+        long,
+        global = true,
+        value_name = "NAME",
+        conflicts_with_all = ["template", "template_file"]
+    )]
+    /// Use a template from the templates directory by name, with or without
+    /// its extension, e.g. `-T org-capture`.
+    pub template_name: Option<String>,
+
+    #[arg(long, global = true)]
+    /// Render the template once for all the entries, as `entries` with
+    /// `count`, `sites` and `date_range`, rather than once per entry.
+    pub collection: bool,
+
+    #[arg(long, global = true, value_name = "DIR", env = "SPY_TEMPLATES")]
+    /// Directory of named templates, which templates can also `include` and
+    /// `extend`. Defaults to `$XDG_CONFIG_HOME/spy/templates`.
+    pub templates_dir: Option<PathBuf>,
+
+    #[arg(long, global = true, value_enum, default_value_t)]
+    /// What templates do with variables that are not defined, such as an
+    /// entry's missing `description`.
+    pub undefined: Undefined,
+
+    #[arg(long, global = true, value_enum, default_value_t)]
+    /// How values printed by templates are escaped.
+    pub escape: Escape,
+
+    #[arg(
+        short = 'f',
+        long,
+        value_enum,
+        default_value_t,
+        global = true,
+        conflicts_with_all = ["template", "template_file", "template_name"]
+    )]
+    /// Built-in output format, used when no template is given.
+    pub format: Format,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "DIR",
+        conflicts_with_all = ["template", "template_file", "template_name"]
+    )]
+    /// Write each entry to its own file in this directory instead of stdout.
+    pub output_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        global = true,
+        requires = "output_dir"
+    )]
+    /// How files written to `--output-dir` are named.
+    pub naming: Naming,
+
+    #[arg(long, global = true, value_delimiter = ',', value_name = "FIELD,...")]
+    /// Fields to output, e.g. `title,site,author,url,published`. Applies to
+    /// CSV, TSV and JSON; selecting fields writes JSON with the same field
+    /// names as templates.
+    pub fields: Vec<String>,
+
+    #[arg(long, global = true, value_delimiter = ',', value_name = "FIELD,...")]
+    /// Fields to leave out of CSV, TSV and JSON output.
+    pub exclude: Vec<String>,
+
+    #[arg(long, global = true, conflicts_with = "jsonl")]
+    /// Indent JSON output. The default when writing to a terminal.
+    pub pretty: bool,
+
+    #[arg(long, global = true)]
+    /// Write JSON as one compact object per line, even to a terminal.
+    pub jsonl: bool,
+
+    #[arg(long, global = true)]
+    /// Leave out the article text, and skip extracting it when fetching.
+    /// Entries saved by `spy add` are stored without their text, and so
+    /// cannot be matched as duplicates by it, until `spy refresh`.
+    pub no_full_text: bool,
+
+    #[arg(long, global = true)]
+    /// Keep each fetched page's `<meta>` tags, JSON-LD and microdata as
+    /// written, in a `meta` template variable, and write stored entries'
+    /// `meta` to CSV, TSV and JSON output. Naming `meta` in `--fields` also
+    /// writes it.
+    pub include_raw_meta: bool,
+
+    #[arg(long, global = true, default_value = "; ", value_name = "TEXT")]
+    /// Joins list values such as authors and tags within a column.
+    pub list_separator: String,
+
+    #[arg(long, global = true)]
+    /// Leave out the header row of CSV and TSV output.
+    pub no_header: bool,
+}
+
+impl Output {
+    /// What templates are rendered with.
+    pub fn context(&self) -> Context {
+        if self.collection {
+            Context::Collection
+        } else {
+            Context::Entry
+        }
+    }
+
+    /// How templates handle undefined variables and escaping.
+    pub fn settings(&self) -> Settings {
+        Settings {
+            undefined: self.undefined,
+            escape: self.escape,
+        }
+    }
+
+    /// The template given in any of the three ways, if one was.
+    pub fn template(&self) -> Result<Option<Template>, template::Error> {
+        Ok(
+            match (&self.template, &self.template_file, &self.template_name) {
+                (Some(string), _, _) => Some(Template::new(string.clone(), self.settings())?),
+                (_, Some(path), _) => Some(Template::from_file(path, self.settings())?),
+                (_, _, Some(name)) => Some(Template::named(name.clone(), self.settings())?),
+                _ => None,
+            },
+        )
+    }
+
+    /// Settings for the built-in formats.
+    pub fn options(&self) -> Options {
+        Options {
+            fields: self.fields.clone(),
+            exclude: self.exclude.clone(),
+            separator: self.list_separator.clone(),
+            header: !self.no_header,
+            pretty: self.pretty || (!self.jsonl && std::io::stdout().is_terminal()),
+            raw_meta: self.include_raw_meta,
+            full_text: !self.no_full_text,
+        }
+    }
+
+    /// How much of each fetched page to extract.
+    pub fn extraction(&self) -> Extraction {
+        Extraction {
+            full_text: !self.no_full_text,
+            raw_meta: self.include_raw_meta,
+        }
+    }
+}
+
+/// When and where to fall back to archived copies of pages.
+#[derive(Args, Debug)]
+pub struct Archive {
+    #[arg(long, global = true)]
+    /// Extract from the closest archived snapshot instead of the live page.
+    pub archived: bool,
+
+    #[arg(long, global = true, conflicts_with = "archived")]
+    /// Try the closest archived snapshot when the live page cannot be
+    /// fetched. Entries extracted from one record it in `archive`.
+    pub archive_fallback: bool,
+
+    #[arg(
+        long,
+        global = true,
+        env = "SPY_TIMEGATE",
+        value_name = "URL",
+        default_value = WAYBACK_TIMEGATE
+    )]
+    /// Memento TimeGate to look snapshots up in; the page URL is appended to it.
+    pub timegate: Url,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Import bookmarks exported by another application.
+    Import {
+        /// Format of the file to import.
+        #[arg(value_enum)]
+        source: Source,
+
+        /// File to import.
+        file: PathBuf,
+
+        #[arg(long)]
+        /// Fetch each bookmark and run the normal extraction on it.
+        enrich: bool,
+    },
+    /// Fetch a URL and save it to the store.
+    Add {
+        url: Url,
+
+        #[arg(long)]
+        /// Title to use instead of the page's own.
+        title: Option<String>,
+
+        #[arg(long = "tag", value_name = "TAG")]
+        /// Tag to attach; may be repeated.
+        tags: Vec<String>,
+
+        #[arg(long)]
+        /// Note to keep with the entry.
+        note: Option<String>,
+
+        #[arg(long, value_enum, default_value_t)]
+        /// What to do when the URL, its canonical URL or its text is already stored.
+        on_duplicate: OnDuplicate,
+    },
+    /// Fetch every URL listed in a file, one per line, and output them
+    /// together. Blank lines and lines starting with `#` are skipped.
+    Batch {
+        /// File of URLs (`-` for stdin).
+        file: PathBuf,
+    },
+    /// List stored entries, optionally filtered and sorted.
+    List {
+        #[arg(long = "where", value_name = "EXPR")]
+        /// Only list entries matching the expression, e.g.
+        /// `site = "lwn.net" and published > 2025-01-01 and tag:rust`.
+        filter: Option<Filter>,
+
+        #[arg(long, value_name = "FIELD[:desc]")]
+        /// Sort by a field, e.g. `published:desc`.
+        sort: Option<Sort>,
+
+        #[arg(short = 'n', long)]
+        /// Show at most this many entries.
+        limit: Option<usize>,
+
+        #[arg(long, value_name = "FILE")]
+        /// Read JSONL entries from a file (`-` for stdin) instead of the store.
+        input: Option<PathBuf>,
+    },
+    /// Show a stored entry.
+    Show {
+        /// Entry ID (or a unique prefix of it) or URL.
+        key: String,
+    },
+    /// Search stored entries, best matches first.
+    Search {
+        #[arg(required = true)]
+        /// Words, "quoted phrases" and author:, site: or tag: filters.
+        query: Vec<String>,
+
+        #[arg(short = 'n', long)]
+        /// Show at most this many results.
+        limit: Option<usize>,
+    },
+    /// Re-fetch stored entries and extract them again, keeping their IDs,
+    /// custom titles, tags and notes.
+    #[command(group(ArgGroup::new("which").required(true).args(["key", "all", "older_than"])))]
+    Refresh {
+        /// Entry ID (or a unique prefix of it) or URL.
+        key: Option<String>,
+
+        #[arg(long)]
+        /// Refresh every stored entry.
+        all: bool,
+
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        /// Refresh entries last fetched longer ago than this, e.g. `30d` or `12h`.
+        older_than: Option<TimeDelta>,
+    },
+    /// Check stored (or piped) entries for dead links.
+    Check {
+        #[arg(long, value_name = "FILE")]
+        /// Read JSONL entries from a file (`-` for stdin) instead of the store.
+        input: Option<PathBuf>,
+
+        #[arg(long, value_enum, default_value_t)]
+        /// How to write the report.
+        report: ReportFormat,
+
+        #[arg(long)]
+        /// Also fetch pages that load and compare their titles with the
+        /// page each host serves for a made-up path, to catch "not found"
+        /// pages served as successes. Costs a GET per page and one per host.
+        soft_404: bool,
+    },
+    /// Save a page as a single self-contained HTML file.
+    Snapshot {
+        url: Url,
+
+        #[arg(short = 'o', long, value_name = "FILE")]
+        /// Where to write the snapshot. Defaults to stdout.
+        output: Option<PathBuf>,
+
+        #[arg(long, value_name = "FILE")]
+        /// Also write a reader-mode snapshot of the extracted article text.
+        reader: Option<PathBuf>,
+
+        #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_BUDGET)]
+        /// Stop inlining images and stylesheets once this many bytes are embedded.
+        max_size: usize,
+    },
+    /// Extract entries from a WARC file without touching the network.
+    Replay {
+        /// WARC file written with `--warc`.
+        file: PathBuf,
+
+        /// Pages to extract. Defaults to every HTML page captured in the file.
+        urls: Vec<Url>,
+    },
+    /// Publish stored (or piped) entries as an Atom, RSS or JSON feed.
+    Feed {
+        #[arg(long = "type", value_enum, default_value_t)]
+        /// Feed format.
+        kind: FeedFormat,
+
+        #[arg(long, env = "SPY_FEED_TITLE", default_value = "spy")]
+        /// Feed title.
+        title: String,
+
+        #[arg(long, env = "SPY_FEED_LINK", value_name = "URL")]
+        /// The website the feed belongs to.
+        link: Url,
+
+        #[arg(long, env = "SPY_FEED_URL", value_name = "URL")]
+        /// Where the feed will be published; also used as the Atom feed ID.
+        feed_url: Option<Url>,
+
+        #[arg(long, env = "SPY_FEED_DESCRIPTION")]
+        /// What the feed is about.
+        description: Option<String>,
+
+        #[arg(long, env = "SPY_FEED_AUTHOR")]
+        /// Author of the feed, also used for entries without one.
+        author: Option<String>,
+
+        #[arg(long)]
+        /// Include each entry's article text, not only its description.
+        full_text: bool,
+
+        #[arg(long = "where", value_name = "EXPR")]
+        /// Only include entries matching the expression.
+        filter: Option<Filter>,
+
+        #[arg(long, value_name = "FIELD[:desc]", default_value = "added:desc")]
+        /// Order of the feed's items.
+        sort: Sort,
+
+        #[arg(short = 'n', long)]
+        /// Include at most this many entries.
+        limit: Option<usize>,
+
+        #[arg(long, value_name = "FILE")]
+        /// Read JSONL entries from a file (`-` for stdin) instead of the store.
+        input: Option<PathBuf>,
+
+        #[arg(short = 'o', long, value_name = "FILE")]
+        /// Where to write the feed. Defaults to stdout.
+        output: Option<PathBuf>,
+    },
+    /// Bundle stored (or piped) entries into an EPUB 3 book.
+    Epub {
+        /// Entry IDs (or unique prefixes) or URLs, in reading order. Defaults
+        /// to every entry matching `--where`.
+        keys: Vec<String>,
+
+        #[arg(short = 'o', long, value_name = "FILE")]
+        /// Where to write the book.
+        output: PathBuf,
+
+        #[arg(long = "where", value_name = "EXPR", conflicts_with = "keys")]
+        /// Only include entries matching the expression.
+        filter: Option<Filter>,
+
+        #[arg(long, value_name = "FILE", conflicts_with = "keys")]
+        /// Read JSONL entries from a file (`-` for stdin) instead of the store.
+        input: Option<PathBuf>,
+
+        #[arg(long)]
+        /// Book title. Defaults to the entry's title, or a generic one for several entries.
+        title: Option<String>,
+
+        #[arg(long, default_value = "en", value_name = "TAG")]
+        /// Language of the book.
+        language: String,
+
+        #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_BUDGET)]
+        /// Stop embedding images once this many bytes are embedded.
+        max_size: usize,
+    },
+    /// Remove a stored entry.
+    Rm {
+        /// Entry ID (or a unique prefix of it) or URL.
+        key: String,
+    },
+    /// Work with templates without fetching anything.
+    Template {
+        #[command(subcommand)]
+        command: TemplateCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommand {
+    /// Compile a template and list the variables it uses, warning about any
+    /// that entries don't have and any unknown filters or tests. Exits with
+    /// status 1 if there are warnings.
+    Check {
+        /// Template file to check.
+        file: PathBuf,
+    },
+}
+
+/// Parse an age such as `90s`, `15m`, `12h`, `30d` or `2w`.
+fn parse_age(s: &str) -> Result<TimeDelta, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (n, unit) = s.split_at(split);
+    let n: i64 = n
+        .parse()
+        .map_err(|_| format!("{s:?} does not start with a number"))?;
+    let delta = match unit {
+        "s" => TimeDelta::try_seconds(n),
+        "m" => TimeDelta::try_minutes(n),
+        "h" => TimeDelta::try_hours(n),
+        "d" | "" => TimeDelta::try_days(n),
+        "w" => TimeDelta::try_weeks(n),
+        _ => return Err(format!("unknown unit {unit:?}; expected s, m, h, d or w")),
+    };
+    delta.ok_or_else(|| format!("{s:?} is out of range"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Cli, clap::Error> {
+        Cli::try_parse_args(std::iter::once("spy").chain(args.split_whitespace()))
+    }
+
+    #[test]
+    fn global_options_go_before_or_after_the_subcommand() {
+        for args in [
+            "--store s.jsonl list",
+            "list --store s.jsonl",
+            "--format csv list",
+            "--store s.jsonl search q",
+        ] {
+            let cli = parse(args).unwrap_or_else(|e| panic!("{args}: {e}"));
+            assert!(cli.command.is_some() && cli.url.is_none(), "{args}");
+        }
+        for args in [
+            "--format csv https://example.com/",
+            "https://example.com/ --format csv",
+        ] {
+            let cli = parse(args).unwrap_or_else(|e| panic!("{args}: {e}"));
+            assert!(cli.command.is_none(), "{args}");
+            assert_eq!(cli.url.unwrap().as_str(), "https://example.com/");
+            assert_eq!(cli.output.format, Format::Csv);
+        }
+        assert_eq!(
+            parse("https://example.com/ list").unwrap_err().kind(),
+            ErrorKind::ArgumentConflict
+        );
+        assert!(parse("--store s.jsonl").is_err());
+    }
+
+    #[test]
+    fn parses_ages() {
+        assert_eq!(parse_age("30d"), Ok(TimeDelta::days(30)));
+        assert_eq!(parse_age("12h"), Ok(TimeDelta::hours(12)));
+        assert_eq!(parse_age("7"), Ok(TimeDelta::days(7)));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+    }
 }
@@ -0,0 +1,270 @@
+use std::fmt::Display;
+
+use clap::ValueEnum;
+
+use crate::{entry::Entry, store::normalise_url, text::words};
+
+/// Fingerprints further apart than this many bits are different documents.
+const MAX_DISTANCE: u32 = 3;
+/// Texts shorter than this many words are too short to fingerprint reliably.
+const MIN_WORDS: usize = 20;
+/// Words per shingle fed into the fingerprint.
+const SHINGLE: usize = 3;
+
+/// What to do when an added entry duplicates a stored one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OnDuplicate {
+    /// Keep the stored entry and discard the new one.
+    #[default]
+    Skip,
+    /// Replace the stored entry's content, keeping its ID, tags and folder.
+    Update,
+    /// Store the new entry alongside the existing one.
+    KeepBoth,
+}
+
+impl OnDuplicate {
+    /// The entry to store for `entry`, given the stored entry it duplicates
+    /// if any, or `None` to keep the stored one as it is.
+    pub fn resolve(self, entry: Entry, existing: Option<&Entry>) -> Option<Entry> {
+        match (existing, self) {
+            (Some(_), OnDuplicate::Skip) => None,
+            (Some(existing), OnDuplicate::Update) => Some(entry.replacing(existing)),
+            (Some(_), OnDuplicate::KeepBoth) | (None, _) => Some(entry),
+        }
+    }
+}
+
+/// Why two entries were considered the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason {
+    /// Same URL after normalisation.
+    Url,
+    /// One entry's canonical URL matches the other's URL or canonical URL.
+    Canonical,
+    /// The article text is near-identical; `distance` is the number of
+    /// differing fingerprint bits.
+    Content { distance: u32 },
+}
+
+impl Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::Url => f.write_str("same normalised URL"),
+            Reason::Canonical => f.write_str("same canonical URL"),
+            Reason::Content { distance } => {
+                write!(f, "near-identical text, {distance} bits apart")
+            }
+        }
+    }
+}
+
+/// A stored entry that a candidate duplicates.
+#[derive(Debug)]
+pub struct Duplicate<'a> {
+    pub existing: &'a Entry,
+    pub reason: Reason,
+}
+
+/// Find the first stored entry that the candidate duplicates, checking URLs
+/// before falling back to comparing text fingerprints.
+pub fn find<'a>(stored: &'a [Entry], candidate: &Entry) -> Option<Duplicate<'a>> {
+    let url = normalise_url(candidate.url());
+    let canonical = candidate.canonical().map(normalise_url);
+    if let Some(existing) = stored.iter().find(|e| normalise_url(e.url()) == url) {
+        return Some(Duplicate {
+            existing,
+            reason: Reason::Url,
+        });
+    }
+    if let Some(existing) = stored.iter().find(|e| {
+        let other_url = normalise_url(e.url());
+        let other_canonical = e.canonical().map(normalise_url);
+        canonical.as_ref() == Some(&other_url)
+            || other_canonical.as_ref() == Some(&url)
+            || (canonical.is_some() && canonical == other_canonical)
+    }) {
+        return Some(Duplicate {
+            existing,
+            reason: Reason::Canonical,
+        });
+    }
+    let fingerprint = simhash(candidate.full_text())?;
+    stored.iter().find_map(|e| {
+        let distance = (simhash(e.full_text())? ^ fingerprint).count_ones();
+        (distance <= MAX_DISTANCE).then_some(Duplicate {
+            existing: e,
+            reason: Reason::Content { distance },
+        })
+    })
+}
+
+/// A 64-bit SimHash over word shingles of the text, ignoring markup. Similar
+/// texts produce fingerprints that differ in few bits.
+pub fn simhash(text: &str) -> Option<u64> {
+    let words = words(text);
+    if words.len() < MIN_WORDS {
+        return None;
+    }
+    let mut weights = [0i32; 64];
+    for shingle in words.windows(SHINGLE) {
+        let h = fnv1a(shingle);
+        for (bit, w) in weights.iter_mut().enumerate() {
+            if h & (1 << bit) != 0 {
+                *w += 1;
+            } else {
+                *w -= 1;
+            }
+        }
+    }
+    Some(
+        weights
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| **w > 0)
+            .fold(0u64, |acc, (bit, _)| acc | (1 << bit)),
+    )
+}
+
+fn fnv1a(words: &[String]) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for w in words {
+        for b in w.bytes().chain(std::iter::once(b' ')) {
+            h ^= u64::from(b);
+            h = h.wrapping_mul(0x100000001b3);
+        }
+    }
+    h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entry::Extraction, fetch::Live, tag::Tag};
+    use url::Url;
+
+    const ARTICLE: &str = "<p>The kernel community met in Paris this week to discuss the \
+        future of memory safety, the role of Rust in drivers, and how maintainers can \
+        share review load without burning out. Several talks covered new tooling.</p>";
+
+    fn entry(url: &str) -> Entry {
+        Entry::unfetched(&Url::parse(url).unwrap(), None)
+    }
+
+    fn page(url: &str, head: &str, body: &str) -> Entry {
+        let html =
+            format!("<html><head>{head}</head><body><article>{body}</article></body></html>");
+        Entry::extract(
+            &Live,
+            &Url::parse(url).unwrap(),
+            &html,
+            None,
+            Extraction::FULL,
+        )
+    }
+
+    #[test]
+    fn near_identical_text_has_close_fingerprints() {
+        let edited = ARTICLE.replace("this week", "this month");
+        let a = simhash(ARTICLE).unwrap();
+        let b = simhash(&format!("<div>{edited}</div>")).unwrap();
+        let unrelated = simhash(
+            "A recipe for sourdough bread needs flour, water, salt and a lively starter \
+             that has been fed the night before, plus patience while the dough proofs slowly.",
+        )
+        .unwrap();
+        assert!((a ^ b).count_ones() < (a ^ unrelated).count_ones());
+        assert_eq!(simhash("too short"), None);
+    }
+
+    #[test]
+    fn url_matches_take_priority() {
+        let stored = vec![
+            entry("https://lwn.net/Articles/1/"),
+            entry("https://lwn.net/"),
+        ];
+        let dup = find(&stored, &entry("https://lwn.net/Articles/1?utm_source=x")).unwrap();
+        assert_eq!(dup.existing.id(), stored[0].id());
+        assert_eq!(dup.reason, Reason::Url);
+        assert!(find(&stored, &entry("https://lwn.net/Articles/2")).is_none());
+    }
+
+    #[test]
+    fn canonical_urls_match_either_way() {
+        let canonical = r#"<link rel="canonical" href="https://example.com/post">"#;
+        let stored = vec![
+            entry("https://example.com/post"),
+            entry("https://example.com/"),
+        ];
+        let mirror = page("https://mirror.example/post", canonical, "");
+        let dup = find(&stored, &mirror).unwrap();
+        assert_eq!(dup.existing.id(), stored[0].id());
+        assert_eq!(dup.reason, Reason::Canonical);
+
+        let stored = vec![page("https://amp.example.com/post", canonical, "")];
+        let dup = find(&stored, &entry("https://example.com/post?utm_medium=rss")).unwrap();
+        assert_eq!(dup.reason, Reason::Canonical);
+        let sibling = page("https://m.example.com/post", canonical, "");
+        assert_eq!(find(&stored, &sibling).unwrap().reason, Reason::Canonical);
+    }
+
+    #[test]
+    fn lightly_edited_text_is_a_duplicate() {
+        let article = ARTICLE.repeat(3);
+        let stored = vec![
+            page("https://example.com/", "", "<p>Nothing to see.</p>"),
+            page("https://example.com/original", "", &article),
+        ];
+        let edited = article.replacen("this week", "this month", 1);
+        let copy = page("https://elsewhere.example/copy", "", &edited);
+        let dup = find(&stored, &copy).unwrap();
+        assert_eq!(dup.existing.id(), stored[1].id());
+        assert!(matches!(dup.reason, Reason::Content { distance } if distance <= MAX_DISTANCE));
+
+        let unrelated = page(
+            "https://elsewhere.example/bread",
+            "",
+            &"<p>A recipe for sourdough bread needs flour, water, salt and a lively starter \
+              that has been fed the night before, plus patience while the dough proofs.</p>"
+                .repeat(3),
+        );
+        assert!(find(&stored, &unrelated).is_none());
+    }
+
+    #[test]
+    fn duplicates_are_skipped_updated_or_kept() {
+        let existing = entry("https://example.com/post")
+            .with_tags([Tag::try_from("rust").unwrap()])
+            .with_notes(Some("read later".into()));
+        let fresh = || entry("https://example.com/post").with_description(Some("New".into()));
+
+        assert!(
+            OnDuplicate::Skip
+                .resolve(fresh(), Some(&existing))
+                .is_none()
+        );
+
+        let updated = OnDuplicate::Update
+            .resolve(fresh(), Some(&existing))
+            .unwrap();
+        assert_eq!(updated.id(), existing.id());
+        assert_eq!(updated.tags(), existing.tags());
+        assert_eq!(updated.description(), Some("New"));
+
+        let both = OnDuplicate::KeepBoth
+            .resolve(fresh(), Some(&existing))
+            .unwrap();
+        assert_ne!(both.id(), existing.id());
+        assert!(both.tags().is_empty());
+
+        for on_duplicate in [
+            OnDuplicate::Skip,
+            OnDuplicate::Update,
+            OnDuplicate::KeepBoth,
+        ] {
+            let new = fresh();
+            let id = *new.id();
+            assert_eq!(on_duplicate.resolve(new, None).map(|e| *e.id()), Some(id));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{collections::HashSet, fmt::Display};
 
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
 use serde_json::Value;
 
 use scraper::{Html, Selector};
@@ -8,7 +9,13 @@ use thiserror::Error;
 use url::Url;
 use uuid::Uuid;
 
-use crate::AGENT;
+use crate::{
+    archive::{ArchiveProvider, Snapshot},
+    citation::{Citation, citation_authors, citation_date, citation_title},
+    fetch::{Fetcher, Live},
+    meta::RawMeta,
+    tag::Tag,
+};
 
 #[cfg(test)]
 use proptest::strategy::BoxedStrategy;
@@ -22,44 +29,171 @@ pub struct Entry {
     url: Url,
     page_title: String,
     site_title: String,
-    authors: HashSet<String>,
+    authors: Vec<String>,
+    #[serde(default)]
     full_text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     thumbnail: Option<Url>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<DateTime<Utc>>,
+    /// How much of the publication date the page gave; `published` holds
+    /// the start of that period.
+    #[serde(default, skip_serializing_if = "Precision::is_day")]
+    published_precision: Precision,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    citation: Option<Citation>,
+    /// The page's preferred URL, when it differs from the one fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical: Option<Url>,
+    #[serde(default, skip_serializing_if = "HashSet::is_empty")]
+    tags: HashSet<Tag>,
+    /// When the bookmark was created, if known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    added: Option<DateTime<Utc>>,
+    /// Folder path the bookmark was filed under, outermost first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    folder: Vec<String>,
+    /// Whether the title was supplied by the user rather than extracted.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    custom_title: bool,
+    /// Free-form notes kept with the bookmark.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    /// When the entry was last re-fetched, if ever.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    refreshed: Option<DateTime<Utc>>,
+    /// The archived copy the entry was extracted from, if the live page was not used.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<Snapshot>,
+    /// The page's structured data as written, if asked for when extracting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    meta: Option<RawMeta>,
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Failed to fetch URL {url}")]
     FetchError { error: ureq::Error, url: Url },
-    #[error("Failed to read to string: {url}")]
-    ReadToStringError { error: ureq::Error, url: Url },
+    #[error("Failed to find an archived copy of {url}")]
+    ArchiveError {
+        error: crate::archive::Error,
+        url: Url,
+    },
+}
+
+/// How much of a page to extract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extraction {
+    /// Extract the article text with readability, which dominates
+    /// extraction time.
+    pub full_text: bool,
+    /// Keep the page's `<meta>` tags, JSON-LD and microdata as written.
+    pub raw_meta: bool,
+}
+
+impl Extraction {
+    /// Metadata and the article text.
+    pub const FULL: Self = Self {
+        full_text: true,
+        raw_meta: false,
+    };
+    /// Metadata only.
+    pub const METADATA_ONLY: Self = Self {
+        full_text: false,
+        raw_meta: false,
+    };
+}
+
+impl Default for Extraction {
+    fn default() -> Self {
+        Self::FULL
+    }
 }
 
 impl Entry {
     /// Construct a new Entry from a Url, and optionally, a user-defined title.
     pub fn new(url: &Url, page_title: Option<String>) -> Result<Self, Box<Error>> {
-        let body = AGENT
-            .get(url.as_str())
-            .call()
-            .map_err(|e| Error::FetchError {
-                error: e,
-                url: url.clone(),
-            })?
-            .body_mut()
-            .read_to_string()
-            .map_err(|e| Error::ReadToStringError {
-                error: e,
-                url: url.clone(),
-            })?;
-        let doc = Html::parse_document(&body);
-        let mut bytes = body.as_bytes();
-        let full_text = readability::extractor::extract(&mut bytes, url)
-            .map(|p| p.content)
-            .unwrap_or_default();
+        Self::fetch(&Live, url, page_title)
+    }
+
+    /// Construct a new Entry, making every request through `fetcher`.
+    pub fn fetch(
+        fetcher: &dyn Fetcher,
+        url: &Url,
+        page_title: Option<String>,
+    ) -> Result<Self, Box<Error>> {
+        Self::fetch_with(fetcher, url, page_title, Extraction::FULL)
+    }
+
+    /// Like [`Entry::fetch`], extracting only as much as `extraction` asks for.
+    pub fn fetch_with(
+        fetcher: &dyn Fetcher,
+        url: &Url,
+        page_title: Option<String>,
+        extraction: Extraction,
+    ) -> Result<Self, Box<Error>> {
+        let body = fetcher.get(url)?;
+        Ok(Self::extract(fetcher, url, &body, page_title, extraction))
+    }
+
+    /// Construct an Entry from the closest archived copy of a Url rather than
+    /// the live page, recording which snapshot was used. The archive's links
+    /// are restored and resolved against the original Url, so the entry's
+    /// links point at the original site.
+    pub fn archived(
+        url: &Url,
+        page_title: Option<String>,
+        provider: &dyn ArchiveProvider,
+        fetcher: &dyn Fetcher,
+        extraction: Extraction,
+    ) -> Result<Self, Box<Error>> {
+        let snapshot =
+            provider
+                .closest(fetcher, url, None)
+                .map_err(|error| Error::ArchiveError {
+                    error,
+                    url: url.clone(),
+                })?;
+        let body = fetcher.get(&snapshot.url)?;
+        let body = snapshot.restore_links(&body);
+        let mut entry = Self::extract(fetcher, url, &body, page_title, extraction);
+        entry.archive = Some(snapshot);
+        Ok(entry)
+    }
+
+    /// Run extraction over an already fetched page. Sub-requests go through
+    /// `fetcher`.
+    pub fn from_html(
+        fetcher: &dyn Fetcher,
+        url: &Url,
+        body: &str,
+        page_title: Option<String>,
+    ) -> Self {
+        Self::extract(fetcher, url, body, page_title, Extraction::FULL)
+    }
+
+    /// Like [`Entry::from_html`], extracting only as much as `extraction` asks for.
+    pub fn extract(
+        fetcher: &dyn Fetcher,
+        url: &Url,
+        body: &str,
+        page_title: Option<String>,
+        extraction: Extraction,
+    ) -> Self {
+        let custom_title = page_title.is_some();
+        let doc = Html::parse_document(body);
+        let full_text = if extraction.full_text {
+            let mut bytes = body.as_bytes();
+            readability::extractor::extract(&mut bytes, url)
+                .map(|p| p.content)
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
         let page_title = page_title
+            .or_else(|| citation_title(&doc))
             .or_else(|| first_text(&doc, "head > title"))
             .or_else(|| first_attr(&doc, r#"head meta[property="og:title"]"#, "content"))
             .or_else(|| first_attr(&doc, r#"head meta[name="twitter:title"]"#, "content"))
@@ -87,14 +221,15 @@ impl Entry {
             .unwrap_or_default();
 
         let site_title = og_site_name(&doc)
-            .or_else(|| manifest_site_name(url, &doc))
+            .or_else(|| manifest_site_name(fetcher, url, &doc))
             .or_else(|| schema_site_name(&doc))
             .or_else(|| microformats_site_name(&doc))
             .or_else(|| meta_application_name(&doc))
             .or_else(|| url.host_str().map(str::to_string))
             .unwrap_or_default();
 
-        let authors = meta_author(&doc)
+        let authors = citation_authors(&doc)
+            .or_else(|| meta_author(&doc))
             .or_else(|| link_rel_author(&doc))
             .or_else(|| json_ld_authors(&doc))
             .or_else(|| microdata_authors(&doc))
@@ -112,7 +247,7 @@ impl Entry {
             .or_else(|| schema_description_microdata_rdfa(&doc))
             .or_else(|| microformats_summary(&doc))
             .or_else(|| dublin_core_description(&doc))
-            .or_else(|| manifest_description(url, &doc));
+            .or_else(|| manifest_description(fetcher, url, &doc));
         let thumbnail = og_image(url, &doc)
             .or_else(|| twitter_image(url, &doc))
             .or_else(|| schema_primary_image_jsonld(url, &doc))
@@ -120,13 +255,30 @@ impl Entry {
             .or_else(|| schema_image_jsonld(url, &doc))
             .or_else(|| schema_image_microdata_rdfa(url, &doc))
             .or_else(|| microformats_image(url, &doc))
-            .or_else(|| oembed_thumbnail(url, &doc))
+            .or_else(|| oembed_thumbnail(fetcher, url, &doc))
             .or_else(|| amp_story_poster(url, &doc))
             .or_else(|| rel_image_src(url, &doc))
             .and_then(|s| Url::parse(&s).ok());
+        let (published, published_precision) = citation_date(&doc)
+            .and_then(|s| parse_published(&s))
+            .or_else(|| og_published_time(&doc))
+            .or_else(|| schema_date_published_jsonld(&doc))
+            .or_else(|| schema_date_published_microdata_rdfa(&doc))
+            .or_else(|| microformats_published(&doc))
+            .or_else(|| dublin_core_date(&doc))
+            .unzip();
+        let citation = Citation::from_document(&doc);
+        let canonical = link_canonical(url, &doc)
+            .or_else(|| og_url(url, &doc))
+            .and_then(|s| Url::parse(&s).ok())
+            .filter(|c| c != url);
+        let meta = extraction
+            .raw_meta
+            .then(|| RawMeta::from_document(url, &doc))
+            .flatten();
 
         let id = Uuid::new_v4();
-        Ok(Entry {
+        Entry {
             id,
             url: url.clone(),
             page_title,
@@ -135,11 +287,216 @@ impl Entry {
             description,
             full_text,
             thumbnail,
-        })
+            published,
+            published_precision: published_precision.unwrap_or_default(),
+            citation,
+            canonical,
+            tags: HashSet::new(),
+            added: None,
+            folder: Vec::new(),
+            custom_title,
+            notes: None,
+            refreshed: None,
+            archive: None,
+            meta,
+        }
+    }
+
+    /// Construct an Entry for a Url without fetching it, e.g. for imported
+    /// bookmarks. The site title falls back to the Url's host.
+    pub fn unfetched(url: &Url, page_title: Option<String>) -> Self {
+        Entry {
+            id: Uuid::new_v4(),
+            url: url.clone(),
+            custom_title: page_title.is_some(),
+            page_title: page_title.unwrap_or_default(),
+            site_title: url.host_str().map(str::to_string).unwrap_or_default(),
+            authors: Vec::new(),
+            full_text: String::new(),
+            description: None,
+            thumbnail: None,
+            published: None,
+            published_precision: Precision::Day,
+            citation: None,
+            canonical: None,
+            tags: HashSet::new(),
+            added: None,
+            folder: Vec::new(),
+            notes: None,
+            refreshed: None,
+            archive: None,
+            meta: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        if description.is_some() {
+            self.description = description;
+        }
+        self
+    }
+
+    pub fn with_tags(mut self, tags: impl IntoIterator<Item = Tag>) -> Self {
+        self.tags.extend(tags);
+        self
+    }
+
+    pub fn with_added(mut self, added: Option<DateTime<Utc>>) -> Self {
+        self.added = added;
+        self
+    }
+
+    pub fn with_folder(mut self, folder: Vec<String>) -> Self {
+        self.folder = folder;
+        self
+    }
+
+    pub fn with_notes(mut self, notes: Option<String>) -> Self {
+        self.notes = notes;
+        self
+    }
+
+    /// Take over the identity and user-assigned data of an existing entry,
+    /// so that this freshly extracted entry can replace it: the ID, the date it
+    /// was added, its folder and notes are kept, and tags are merged.
+    pub fn replacing(mut self, existing: &Entry) -> Self {
+        self.id = existing.id;
+        self.added = existing.added.or(self.added);
+        if self.folder.is_empty() {
+            self.folder = existing.folder.clone();
+        }
+        if self.notes.is_none() {
+            self.notes = existing.notes.clone();
+        }
+        self.tags.extend(existing.tags.iter().cloned());
+        self
+    }
+
+    /// Re-fetch the page and run extraction again. The ID and user-assigned
+    /// data (a custom title, tags, notes, folder and added date) are kept.
+    /// Returns the refreshed entry and the names of the fields that changed.
+    pub fn refresh(&self, fetcher: &dyn Fetcher) -> Result<(Entry, Vec<&'static str>), Box<Error>> {
+        let title = self.custom_title.then(|| self.page_title.clone());
+        let extraction = Extraction {
+            raw_meta: self.meta.is_some(),
+            ..Extraction::FULL
+        };
+        let mut fresh = Entry::fetch_with(fetcher, &self.url, title, extraction)?.replacing(self);
+        fresh.refreshed = Some(Utc::now());
+        let changed = self.changes(&fresh);
+        Ok((fresh, changed))
+    }
+
+    /// Names of the extracted fields that differ between two entries.
+    pub fn changes(&self, other: &Entry) -> Vec<&'static str> {
+        [
+            ("title", self.page_title != other.page_title),
+            ("site", self.site_title != other.site_title),
+            ("authors", self.authors != other.authors),
+            ("description", self.description != other.description),
+            ("thumbnail", self.thumbnail != other.thumbnail),
+            (
+                "published",
+                (self.published, self.published_precision)
+                    != (other.published, other.published_precision),
+            ),
+            ("citation", self.citation != other.citation),
+            ("canonical", self.canonical != other.canonical),
+            ("full_text", self.full_text != other.full_text),
+        ]
+        .into_iter()
+        .filter_map(|(name, changed)| changed.then_some(name))
+        .collect()
+    }
+}
+
+impl Entry {
+    pub fn id(&self) -> &Uuid {
+        &self.id
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn title(&self) -> &str {
+        &self.page_title
+    }
+
+    pub fn site(&self) -> &str {
+        &self.site_title
+    }
+
+    /// Authors in the order the page lists them.
+    pub fn authors(&self) -> Vec<&str> {
+        self.authors.iter().map(String::as_str).collect()
+    }
+
+    pub fn full_text(&self) -> &str {
+        &self.full_text
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn thumbnail(&self) -> Option<&Url> {
+        self.thumbnail.as_ref()
+    }
+
+    /// The start of the publication date; see [`Entry::published_date`]
+    /// for the date as precisely as the page gave it.
+    pub fn published(&self) -> Option<&DateTime<Utc>> {
+        self.published.as_ref()
+    }
+
+    /// The publication date with only the parts the page gave.
+    pub fn published_date(&self) -> Option<PartialDate> {
+        self.published
+            .map(|d| PartialDate::new(d.date_naive(), self.published_precision))
+    }
+
+    pub fn citation(&self) -> Option<&Citation> {
+        self.citation.as_ref()
+    }
+
+    pub fn canonical(&self) -> Option<&Url> {
+        self.canonical.as_ref()
+    }
+
+    /// Tags sorted lexicographically.
+    pub fn tags(&self) -> Vec<&Tag> {
+        let mut tags: Vec<&Tag> = self.tags.iter().collect();
+        tags.sort_unstable();
+        tags
+    }
+
+    pub fn added(&self) -> Option<&DateTime<Utc>> {
+        self.added.as_ref()
+    }
+
+    pub fn folder(&self) -> &[String] {
+        &self.folder
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        self.notes.as_deref()
+    }
+
+    pub fn refreshed(&self) -> Option<&DateTime<Utc>> {
+        self.refreshed.as_ref()
+    }
+
+    pub fn archive(&self) -> Option<&Snapshot> {
+        self.archive.as_ref()
+    }
+
+    pub fn meta(&self) -> Option<&RawMeta> {
+        self.meta.as_ref()
     }
 }
 
-fn first_text(doc: &Html, css: &str) -> Option<String> {
+pub(crate) fn first_text(doc: &Html, css: &str) -> Option<String> {
     let sel = Selector::parse(css).ok()?;
     doc.select(&sel)
         .next()
@@ -148,7 +505,7 @@ fn first_text(doc: &Html, css: &str) -> Option<String> {
         .filter(|s| !s.is_empty())
 }
 
-fn first_attr(doc: &Html, css: &str, attr: &str) -> Option<String> {
+pub(crate) fn first_attr(doc: &Html, css: &str, attr: &str) -> Option<String> {
     let sel = Selector::parse(css).ok()?;
     doc.select(&sel)
         .filter_map(|e| e.value().attr(attr))
@@ -216,7 +573,7 @@ fn dublin_core_meta(doc: &Html) -> Option<String> {
     None
 }
 
-fn collapse_ws(s: &str) -> String {
+pub(crate) fn collapse_ws(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
     let mut was_space = false;
     for ch in s.chars() {
@@ -237,15 +594,14 @@ fn og_site_name(doc: &Html) -> Option<String> {
     first_attr(doc, r#"head meta[property="og:site_name"]"#, "content")
 }
 
-fn manifest_site_name(base: &Url, doc: &Html) -> Option<String> {
+fn manifest_site_name(fetcher: &dyn Fetcher, base: &Url, doc: &Html) -> Option<String> {
     let sel = Selector::parse(r#"link[rel~="manifest"]"#).ok()?;
     let href = doc
         .select(&sel)
         .filter_map(|l| l.value().attr("href"))
         .next()?;
     let manifest_url = base.join(href).ok()?;
-    let resp = AGENT.get(manifest_url.as_str()).call().ok()?;
-    let text = resp.into_body().read_to_string().ok()?;
+    let text = fetcher.get(&manifest_url).ok()?;
     let v: Value = serde_json::from_str(&text).ok()?;
     v.get("name")
         .and_then(Value::as_str)
@@ -348,22 +704,22 @@ fn meta_application_name(doc: &Html) -> Option<String> {
     first_attr(doc, r#"head meta[name="application-name"]"#, "content")
 }
 
-fn meta_author(doc: &Html) -> Option<HashSet<String>> {
+fn meta_author(doc: &Html) -> Option<Vec<String>> {
     first_attr_all(doc, r#"head meta[name="author"]"#, "content")
 }
 
-fn link_rel_author(doc: &Html) -> Option<HashSet<String>> {
-    let mut out = HashSet::new();
+fn link_rel_author(doc: &Html) -> Option<Vec<String>> {
+    let mut out = Vec::new();
     for css in [r#"a[rel~="author"]"#, r#"link[rel~="author"]"#] {
         let sel = Selector::parse(css).ok()?;
         for el in doc.select(&sel) {
             let text = collapse_ws(&el.text().collect::<String>());
             if !text.is_empty() {
-                out.insert(text);
+                add_author(&mut out, text);
             } else if let Some(title) = el.value().attr("title") {
                 let s = collapse_ws(title);
                 if !s.is_empty() {
-                    out.insert(s);
+                    add_author(&mut out, s);
                 }
             }
         }
@@ -371,10 +727,10 @@ fn link_rel_author(doc: &Html) -> Option<HashSet<String>> {
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn json_ld_authors(doc: &Html) -> Option<HashSet<String>> {
+fn json_ld_authors(doc: &Html) -> Option<Vec<String>> {
     use serde_json::Value;
     let sel = Selector::parse(r#"script[type="application/ld+json"]"#).ok()?;
-    let mut out = HashSet::new();
+    let mut out = Vec::new();
     for node in doc.select(&sel) {
         let raw = node.text().collect::<String>();
         if let Ok(val) = serde_json::from_str::<Value>(&raw) {
@@ -384,27 +740,27 @@ fn json_ld_authors(doc: &Html) -> Option<HashSet<String>> {
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn microdata_authors(doc: &Html) -> Option<HashSet<String>> {
-    let mut out = HashSet::new();
+fn microdata_authors(doc: &Html) -> Option<Vec<String>> {
+    let mut out = Vec::new();
 
     if let Some(vals) = first_attr_all(doc, r#"[itemprop="author"]"#, "content") {
-        out.extend(vals);
+        add_authors(&mut out, vals);
     }
 
     let sel = Selector::parse(r#"[itemprop="author"]"#).ok()?;
     for el in doc.select(&sel) {
         let text = collapse_ws(&el.text().collect::<String>());
         if !text.is_empty() {
-            out.insert(text);
+            add_author(&mut out, text);
         }
         if let Some(names) = first_attr_all_in(&el, r#"[itemprop="name"]"#, "content") {
-            out.extend(names);
+            add_authors(&mut out, names);
         }
         let sel_name = Selector::parse(r#"[itemprop="name"]"#).unwrap();
         for child in el.select(&sel_name) {
             let t = collapse_ws(&child.text().collect::<String>());
             if !t.is_empty() {
-                out.insert(t);
+                add_author(&mut out, t);
             }
         }
     }
@@ -412,11 +768,11 @@ fn microdata_authors(doc: &Html) -> Option<HashSet<String>> {
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn rdfa_authors(doc: &Html) -> Option<HashSet<String>> {
-    let mut out = HashSet::new();
+fn rdfa_authors(doc: &Html) -> Option<Vec<String>> {
+    let mut out = Vec::new();
 
     if let Some(vals) = first_attr_all(doc, r#"[property="schema:author"]"#, "content") {
-        out.extend(vals);
+        add_authors(&mut out, vals);
     }
     for css in [
         r#"[property="schema:author"]"#,
@@ -426,7 +782,7 @@ fn rdfa_authors(doc: &Html) -> Option<HashSet<String>> {
         for el in doc.select(&sel) {
             let t = collapse_ws(&el.text().collect::<String>());
             if !t.is_empty() {
-                out.insert(t);
+                add_author(&mut out, t);
             }
         }
     }
@@ -434,8 +790,8 @@ fn rdfa_authors(doc: &Html) -> Option<HashSet<String>> {
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn microformats_authors(doc: &Html) -> Option<HashSet<String>> {
-    let mut out = HashSet::new();
+fn microformats_authors(doc: &Html) -> Option<Vec<String>> {
+    let mut out = Vec::new();
     for css in [
         ".h-entry .p-author",
         ".p-author",
@@ -445,28 +801,31 @@ fn microformats_authors(doc: &Html) -> Option<HashSet<String>> {
         let sel = Selector::parse(css).ok()?;
         for el in doc.select(&sel) {
             if let Some(n) = first_text_in(&el, ".p-name") {
-                out.insert(n);
+                add_author(&mut out, n);
             }
             let t = collapse_ws(&el.text().collect::<String>());
             if !t.is_empty() {
-                out.insert(t);
+                add_author(&mut out, t);
             }
         }
     }
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn og_article_authors(doc: &Html) -> Option<HashSet<String>> {
+fn og_article_authors(doc: &Html) -> Option<Vec<String>> {
     first_attr_all(doc, r#"head meta[property="article:author"]"#, "content")
 }
 
-fn twitter_creator(doc: &Html) -> Option<HashSet<String>> {
-    first_attr_all(doc, r#"head meta[name="twitter:creator"]"#, "content")
-        .map(|set| set.into_iter().map(|s| trim_at(&s)).collect())
+fn twitter_creator(doc: &Html) -> Option<Vec<String>> {
+    first_attr_all(doc, r#"head meta[name="twitter:creator"]"#, "content").map(|names| {
+        let mut out = Vec::new();
+        add_authors(&mut out, names.iter().map(|s| trim_at(s)));
+        out
+    })
 }
 
-fn dublin_core_creators(doc: &Html) -> Option<HashSet<String>> {
-    let mut out = HashSet::new();
+fn dublin_core_creators(doc: &Html) -> Option<Vec<String>> {
+    let mut out = Vec::new();
     let sel = Selector::parse("head meta").ok()?;
     for m in doc.select(&sel) {
         if let Some(name) = m.value().attr("name")
@@ -476,22 +835,22 @@ fn dublin_core_creators(doc: &Html) -> Option<HashSet<String>> {
         {
             let s = collapse_ws(val);
             if !s.is_empty() {
-                out.insert(s);
+                add_author(&mut out, s);
             }
         }
     }
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn address_authors(doc: &Html) -> Option<HashSet<String>> {
-    let mut out = HashSet::new();
+fn address_authors(doc: &Html) -> Option<Vec<String>> {
+    let mut out = Vec::new();
 
     for css in ["article address", "footer address", "address"] {
         let sel = Selector::parse(css).ok()?;
         for el in doc.select(&sel) {
             let t = collapse_ws(&el.text().collect::<String>());
             if !t.is_empty() {
-                out.insert(t);
+                add_author(&mut out, t);
             }
         }
         if !out.is_empty() {
@@ -501,14 +860,14 @@ fn address_authors(doc: &Html) -> Option<HashSet<String>> {
     if out.is_empty() { None } else { Some(out) }
 }
 
-fn first_attr_all(doc: &Html, css: &str, attr: &str) -> Option<HashSet<String>> {
+pub(crate) fn first_attr_all(doc: &Html, css: &str, attr: &str) -> Option<Vec<String>> {
     let sel = Selector::parse(css).ok()?;
-    let mut out = HashSet::new();
+    let mut out = Vec::new();
     for e in doc.select(&sel) {
         if let Some(v) = e.value().attr(attr) {
             let s = collapse_ws(v);
             if !s.is_empty() {
-                out.insert(s);
+                add_author(&mut out, s);
             }
         }
     }
@@ -519,14 +878,14 @@ fn first_attr_all_in(
     el: &scraper::element_ref::ElementRef,
     css: &str,
     attr: &str,
-) -> Option<HashSet<String>> {
+) -> Option<Vec<String>> {
     let sel = Selector::parse(css).ok()?;
-    let mut out = HashSet::new();
+    let mut out = Vec::new();
     for c in el.select(&sel) {
         if let Some(v) = c.value().attr(attr) {
             let s = collapse_ws(v);
             if !s.is_empty() {
-                out.insert(s);
+                add_author(&mut out, s);
             }
         }
     }
@@ -545,7 +904,21 @@ fn trim_at(s: &str) -> String {
     s.trim().trim_start_matches('@').to_string()
 }
 
-pub fn collect_schema_authors(v: &Value, out: &mut HashSet<String>) {
+/// Add a name unless it is already listed, keeping the order names appear in
+/// on the page.
+pub(crate) fn add_author(out: &mut Vec<String>, name: String) {
+    if !out.contains(&name) {
+        out.push(name);
+    }
+}
+
+pub(crate) fn add_authors(out: &mut Vec<String>, names: impl IntoIterator<Item = String>) {
+    for name in names {
+        add_author(out, name);
+    }
+}
+
+pub fn collect_schema_authors(v: &Value, out: &mut Vec<String>) {
     match v {
         Value::Object(m) => {
             if let Some(a) = m.get("author") {
@@ -572,19 +945,19 @@ pub fn collect_schema_authors(v: &Value, out: &mut HashSet<String>) {
     }
 }
 
-fn extract_author_node(node: &Value, out: &mut HashSet<String>) {
+fn extract_author_node(node: &Value, out: &mut Vec<String>) {
     match node {
         Value::String(s) => {
             let s = s.trim();
             if !s.is_empty() && !looks_like_url(s) {
-                out.insert(s.to_owned());
+                add_author(out, s.to_owned());
             }
         }
         Value::Object(m) => {
             if let Some(Value::String(n)) = m.get("name") {
                 let n = n.trim();
                 if !n.is_empty() {
-                    out.insert(n.to_owned());
+                    add_author(out, n.to_owned());
                     return;
                 }
             }
@@ -610,7 +983,7 @@ fn extract_author_node(node: &Value, out: &mut HashSet<String>) {
                     full.push_str(fn_);
                 }
                 if !full.is_empty() {
-                    out.insert(full);
+                    add_author(out, full);
                 }
             }
         }
@@ -700,7 +1073,7 @@ fn dublin_core_description(doc: &Html) -> Option<String> {
     None
 }
 
-fn manifest_description(base: &Url, doc: &Html) -> Option<String> {
+fn manifest_description(fetcher: &dyn Fetcher, base: &Url, doc: &Html) -> Option<String> {
     let sel = Selector::parse(r#"link[rel~="manifest"]"#).ok()?;
     let href = doc
         .select(&sel)
@@ -708,13 +1081,7 @@ fn manifest_description(base: &Url, doc: &Html) -> Option<String> {
         .next()?;
     let manifest_url = base.join(href).ok()?;
 
-    let text = crate::AGENT
-        .get(manifest_url.as_str())
-        .call()
-        .ok()?
-        .into_body()
-        .read_to_string()
-        .ok()?;
+    let text = fetcher.get(&manifest_url).ok()?;
     let v: Value = serde_json::from_str(&text).ok()?;
     v.get("description")
         .and_then(Value::as_str)
@@ -849,7 +1216,7 @@ fn microformats_image(base: &Url, doc: &Html) -> Option<String> {
     None
 }
 
-fn oembed_thumbnail(base: &Url, doc: &Html) -> Option<String> {
+fn oembed_thumbnail(fetcher: &dyn Fetcher, base: &Url, doc: &Html) -> Option<String> {
     let sel = Selector::parse(r#"link[rel~="alternate"]"#).ok()?;
     // Find an oEmbed endpoint advertised in <head>.
     let href = doc.select(&sel).find_map(|l| {
@@ -862,13 +1229,7 @@ fn oembed_thumbnail(base: &Url, doc: &Html) -> Option<String> {
     })?;
     // Fetch JSON oEmbed only (keep simple). If XML, you could parse with quick-xml.
     let oembed_url = base.join(&href).ok()?;
-    let body = crate::AGENT
-        .get(oembed_url.as_str())
-        .call()
-        .ok()?
-        .into_body()
-        .read_to_string()
-        .ok()?;
+    let body = fetcher.get(&oembed_url).ok()?;
     if href.contains("json+oembed")
         && let Ok(v) = serde_json::from_str::<Value>(&body)
         && let Some(u) = v
@@ -1029,6 +1390,197 @@ fn absolutise(base: &Url, candidate: &str) -> Option<String> {
     base.join(c).ok().map(|u| u.into())
 }
 
+fn link_canonical(base: &Url, doc: &Html) -> Option<String> {
+    let sel = Selector::parse(r#"link[rel~="canonical"]"#).ok()?;
+    doc.select(&sel)
+        .filter_map(|l| l.value().attr("href"))
+        .find_map(|u| absolutise(base, u))
+}
+
+fn og_url(base: &Url, doc: &Html) -> Option<String> {
+    first_attr(doc, r#"head meta[property="og:url"]"#, "content").and_then(|u| absolutise(base, &u))
+}
+
+fn og_published_time(doc: &Html) -> Option<(DateTime<Utc>, Precision)> {
+    first_attr(
+        doc,
+        r#"head meta[property="article:published_time"]"#,
+        "content",
+    )
+    .and_then(|s| parse_published(&s))
+}
+
+fn schema_date_published_jsonld(doc: &Html) -> Option<(DateTime<Utc>, Precision)> {
+    let sel = Selector::parse(r#"script[type="application/ld+json"]"#).ok()?;
+    let mut cands = Vec::<String>::new();
+    for node in doc.select(&sel) {
+        let raw = node.text().collect::<String>();
+        if let Ok(val) = serde_json::from_str::<Value>(&raw) {
+            collect_schema_dates(&val, &mut cands);
+        }
+    }
+    cands.into_iter().find_map(|s| parse_published(&s))
+}
+
+fn collect_schema_dates(v: &Value, out: &mut Vec<String>) {
+    match v {
+        Value::Object(m) => {
+            for key in ["datePublished", "dateCreated"] {
+                if let Some(Value::String(s)) = m.get(key) {
+                    push_clean(s, out);
+                }
+            }
+            if let Some(g) = m.get("@graph") {
+                collect_schema_dates(g, out);
+            }
+            for (_k, vv) in m {
+                collect_schema_dates(vv, out);
+            }
+        }
+        Value::Array(a) => {
+            for x in a {
+                collect_schema_dates(x, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn schema_date_published_microdata_rdfa(doc: &Html) -> Option<(DateTime<Utc>, Precision)> {
+    for css in [
+        r#"[itemprop="datePublished"]"#,
+        r#"[property="schema:datePublished"]"#,
+    ] {
+        if let Some(d) = first_attr(doc, css, "content")
+            .or_else(|| first_attr(doc, css, "datetime"))
+            .or_else(|| first_text(doc, css))
+            .and_then(|s| parse_published(&s))
+        {
+            return Some(d);
+        }
+    }
+    None
+}
+
+fn microformats_published(doc: &Html) -> Option<(DateTime<Utc>, Precision)> {
+    first_attr(doc, ".h-entry .dt-published", "datetime")
+        .or_else(|| first_attr(doc, ".dt-published", "datetime"))
+        .or_else(|| first_text(doc, ".dt-published"))
+        .and_then(|s| parse_published(&s))
+}
+
+fn dublin_core_date(doc: &Html) -> Option<(DateTime<Utc>, Precision)> {
+    let sel = Selector::parse("head meta").ok()?;
+    for m in doc.select(&sel) {
+        if let Some(name) = m.value().attr("name")
+            && let lname = name.to_ascii_lowercase()
+            && matches!(
+                lname.as_str(),
+                "dc.date" | "dc.date.issued" | "dcterms.date" | "dcterms.issued"
+            )
+            && let Some(d) = m.value().attr("content").and_then(parse_published)
+        {
+            return Some(d);
+        }
+    }
+    None
+}
+
+/// How much of a date its source gave.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    Year,
+    Month,
+    #[default]
+    Day,
+}
+
+impl Precision {
+    fn is_day(&self) -> bool {
+        *self == Precision::Day
+    }
+}
+
+/// A calendar date without the parts its source left out, e.g. `1936` or
+/// `2020-05`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialDate {
+    pub year: i32,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+}
+
+impl PartialDate {
+    fn new(date: NaiveDate, precision: Precision) -> Self {
+        PartialDate {
+            year: date.year(),
+            month: (precision != Precision::Year).then(|| date.month()),
+            day: (precision == Precision::Day).then(|| date.day()),
+        }
+    }
+}
+
+impl Display for PartialDate {
+    /// ISO 8601 with only the known parts: `1936`, `2020-05` or `2020-05-12`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{month:02}")?;
+            if let Some(day) = self.day {
+                write!(f, "-{day:02}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the date formats commonly found in page metadata: RFC 3339, RFC 2822,
+/// naive timestamps, and partial dates such as `2020/05/12`, `2020-05` or `2020`.
+/// Dates without a time resolve to midnight UTC, and partial dates to the
+/// start of their year or month.
+pub(crate) fn parse_date(raw: &str) -> Option<DateTime<Utc>> {
+    parse_published(raw).map(|(date, _)| date)
+}
+
+/// [`parse_date`], along with how much of the date was given.
+fn parse_published(raw: &str) -> Option<(DateTime<Utc>, Precision)> {
+    let s = raw.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(d) = DateTime::parse_from_rfc3339(s) {
+        return Some((d.with_timezone(&Utc), Precision::Day));
+    }
+    if let Ok(d) = DateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S%z") {
+        return Some((d.with_timezone(&Utc), Precision::Day));
+    }
+    if let Ok(d) = DateTime::parse_from_rfc2822(s) {
+        return Some((d.with_timezone(&Utc), Precision::Day));
+    }
+    for fmt in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(d) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some((d.and_utc(), Precision::Day));
+        }
+    }
+    // Partial dates: year, year-month or year-month-day with `-`, `/` or `.`.
+    let mut parts = s.split(['-', '/', '.']).map(str::parse::<u32>);
+    let year = parts.next()?.ok()?;
+    let month = parts.next().transpose().ok()?;
+    let day = parts.next().transpose().ok()?;
+    if parts.next().is_some() || !(1000..=9999).contains(&year) {
+        return None;
+    }
+    let precision = match (month, day) {
+        (None, _) => Precision::Year,
+        (Some(_), None) => Precision::Month,
+        (Some(_), Some(_)) => Precision::Day,
+    };
+    NaiveDate::from_ymd_opt(year as i32, month.unwrap_or(1), day.unwrap_or(1))
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| (d.and_utc(), precision))
+}
+
 impl Display for Entry {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let view = EntryView::from(self);
@@ -1043,7 +1595,7 @@ pub(crate) struct EntryView<'a> {
     #[serde(rename = "site")]
     site: &'a str,
 
-    // Primary author if any (deterministic: lexicographically smallest).
+    // Primary author if any: the first one the page lists.
     #[serde(rename = "author", skip_serializing_if = "Option::is_none")]
     author: Option<&'a str>,
 
@@ -1060,16 +1612,67 @@ pub(crate) struct EntryView<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     thumbnail: Option<&'a str>,
 
+    // Publication date as RFC 3339, or as much of it as the page gave.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    published: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    citation: Option<&'a Citation>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    canonical: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<&'a str>,
+
+    // Bookmark creation date as RFC 3339.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    added: Option<String>,
+
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    folder: &'a [String],
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<&'a str>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<&'a Snapshot>,
+
+    /// Raw structured data, for entries extracted with it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<&'a RawMeta>,
+
     full_text: &'a str,
 }
 
+impl EntryView<'_> {
+    /// Names of the serialised fields, in order.
+    pub(crate) const FIELDS: &'static [&'static str] = &[
+        "title",
+        "site",
+        "author",
+        "authors",
+        "url",
+        "id",
+        "description",
+        "thumbnail",
+        "published",
+        "citation",
+        "canonical",
+        "tags",
+        "added",
+        "folder",
+        "notes",
+        "archive",
+        "meta",
+        "full_text",
+    ];
+}
+
 impl<'a> From<&'a Entry> for EntryView<'a> {
     fn from(e: &'a Entry) -> Self {
-        // Authors: sort for determinism, then pick primary and build list.
-        let mut authors: Vec<&str> = e.authors.iter().map(String::as_str).collect();
-        authors.sort_unstable();
-        let author = authors.first().copied();
-        let authors_list = authors;
+        let authors_list = e.authors();
+        let author = authors_list.first().copied();
 
         EntryView {
             title: &e.page_title,
@@ -1080,6 +1683,20 @@ impl<'a> From<&'a Entry> for EntryView<'a> {
             id: &e.id,
             description: e.description.as_deref(),
             thumbnail: e.thumbnail.as_ref().map(|u| u.as_str()),
+            published: e.published.map(|d| match e.published_precision {
+                Precision::Day => d.to_rfc3339_opts(SecondsFormat::Secs, true),
+                precision => PartialDate::new(d.date_naive(), precision).to_string(),
+            }),
+            citation: e.citation.as_ref(),
+            canonical: e.canonical.as_ref().map(|u| u.as_str()),
+            tags: e.tags().into_iter().map(Tag::as_str).collect(),
+            added: e
+                .added
+                .map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            folder: &e.folder,
+            notes: e.notes.as_deref(),
+            archive: e.archive.as_ref(),
+            meta: e.meta.as_ref(),
             full_text: &e.full_text,
         }
     }
@@ -1101,17 +1718,68 @@ impl<'a> EntryTemplateContext<'a> {
     }
 }
 
+/// Context for templates that render many entries into one document, such
+/// as a digest.
+#[derive(Serialize)]
+pub(crate) struct CollectionTemplateContext<'a> {
+    entries: Vec<EntryTemplateContext<'a>>,
+    count: usize,
+    /// Distinct sites, in order of first appearance.
+    sites: Vec<&'a str>,
+    /// Earliest and latest publication (or bookmarking) dates, if any
+    /// entry has one.
+    date_range: Option<DateRange>,
+}
+
+#[derive(Serialize)]
+struct DateRange {
+    start: String,
+    end: String,
+}
+
+impl<'a> CollectionTemplateContext<'a> {
+    /// Names of the serialised fields.
+    pub(crate) const FIELDS: &'static [&'static str] = &["entries", "count", "sites", "date_range"];
+
+    pub(crate) fn new(entries: &'a [Entry]) -> Self {
+        let mut sites: Vec<&str> = Vec::new();
+        for entry in entries {
+            if !sites.contains(&entry.site()) {
+                sites.push(entry.site());
+            }
+        }
+        let dates = entries.iter().filter_map(|e| e.published().or(e.added()));
+        let date_range = dates
+            .clone()
+            .min()
+            .zip(dates.max())
+            .map(|(start, end)| DateRange {
+                start: start.to_rfc3339_opts(SecondsFormat::Secs, true),
+                end: end.to_rfc3339_opts(SecondsFormat::Secs, true),
+            });
+        Self {
+            entries: entries.iter().map(EntryTemplateContext::new).collect(),
+            count: entries.len(),
+            sites,
+            date_range,
+        }
+    }
+}
+
 #[cfg(test)]
 impl Entry {
+    #[allow(clippy::too_many_arguments)]
     pub fn from_parts(
         id: Uuid,
         url: Url,
         page_title: String,
         site_title: String,
-        authors: HashSet<String>,
+        authors: Vec<String>,
         full_text: String,
         description: Option<String>,
         thumbnail: Option<Url>,
+        published: Option<DateTime<Utc>>,
+        citation: Option<Citation>,
     ) -> Self {
         Self {
             id,
@@ -1122,6 +1790,18 @@ impl Entry {
             full_text,
             description,
             thumbnail,
+            published,
+            published_precision: Precision::Day,
+            citation,
+            canonical: None,
+            tags: HashSet::new(),
+            added: None,
+            folder: Vec::new(),
+            custom_title: false,
+            notes: None,
+            refreshed: None,
+            archive: None,
+            meta: None,
         }
     }
 }
@@ -1135,8 +1815,53 @@ prop_compose! {
     }
 }
 
+#[cfg(test)]
+prop_compose! {
+    fn arbitrary_date()(secs in 1i64..4_102_444_800) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap()
+    }
+}
+
+#[cfg(test)]
+prop_compose! {
+    fn arbitrary_citation()
+        (doi in prop::option::of(prop::string::string_regex("10\\.[0-9]{4}/[a-z0-9.]{1,12}").unwrap()),
+         journal in prop::option::of(prop::string::string_regex("[A-Za-z ]{3,32}").unwrap()),
+         volume in prop::option::of(prop::string::string_regex("[0-9]{1,3}").unwrap()),
+         pages in prop::option::of(prop::string::string_regex("[0-9]{1,3}-[0-9]{3,4}").unwrap())) -> Citation {
+        Citation { doi, journal, volume, pages, ..Citation::default() }
+    }
+}
+
 #[cfg(test)]
 pub(crate) fn entry_strategy() -> BoxedStrategy<Entry> {
+    (
+        base_entry_strategy(),
+        collection::vec(
+            prop::string::string_regex("[a-z0-9_-]{1,30}").unwrap(),
+            0..4,
+        ),
+        prop::option::of(arbitrary_date()),
+        collection::vec(
+            prop::string::string_regex("[A-Za-z]{1,8}( [A-Za-z]{1,8})?").unwrap(),
+            0..3,
+        ),
+        prop::option::of(arbitrary_url()),
+        prop::option::of(prop::string::string_regex("[A-Za-z0-9 ,.!?'-]{1,64}").unwrap()),
+    )
+        .prop_map(|(mut entry, tags, added, folder, canonical, notes)| {
+            entry.canonical = canonical;
+            entry
+                .with_tags(tags.iter().filter_map(|t| Tag::try_from(t.as_str()).ok()))
+                .with_added(added)
+                .with_folder(folder)
+                .with_notes(notes)
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+fn base_entry_strategy() -> BoxedStrategy<Entry> {
     (
         any::<[u8; 16]>(),
         arbitrary_url(),
@@ -1146,6 +1871,8 @@ pub(crate) fn entry_strategy() -> BoxedStrategy<Entry> {
         collection::vec(prop::string::string_regex("[A-Za-z]{1,12}").unwrap(), 1..24),
         prop::option::of(prop::string::string_regex("[A-Za-z0-9 ,.!?;:'-]{1,160}").unwrap()),
         prop::option::of(arbitrary_url()),
+        prop::option::of(arbitrary_date()),
+        prop::option::of(arbitrary_citation()),
     )
         .prop_map(
             |(
@@ -1157,8 +1884,11 @@ pub(crate) fn entry_strategy() -> BoxedStrategy<Entry> {
                 words,
                 description,
                 thumbnail,
+                published,
+                citation,
             )| {
-                let authors = authors_vec.into_iter().collect::<HashSet<_>>();
+                let mut authors = Vec::new();
+                add_authors(&mut authors, authors_vec);
                 let full_text = words.join(" ");
                 Entry::from_parts(
                     Uuid::from_bytes(id_bytes),
@@ -1169,6 +1899,8 @@ pub(crate) fn entry_strategy() -> BoxedStrategy<Entry> {
                     full_text,
                     description,
                     thumbnail,
+                    published,
+                    citation,
                 )
             },
         )
@@ -1178,18 +1910,13 @@ pub(crate) fn entry_strategy() -> BoxedStrategy<Entry> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fetch::{Method, Response};
     use serde_json::Value as JsonValue;
 
     fn context_json(entry: &Entry) -> JsonValue {
         serde_json::to_value(EntryTemplateContext::new(entry)).unwrap()
     }
 
-    fn sorted_authors(entry: &Entry) -> Vec<String> {
-        let mut authors: Vec<String> = entry.authors.iter().cloned().collect();
-        authors.sort_unstable();
-        authors
-    }
-
     proptest! {
         #[test]
         fn alias_fields_match_raw_view(entry in super::entry_strategy()) {
@@ -1210,9 +1937,9 @@ mod tests {
 
     proptest! {
         #[test]
-        fn authors_view_is_sorted(entry in super::entry_strategy()) {
+        fn authors_view_keeps_page_order(entry in super::entry_strategy()) {
             let json = context_json(&entry);
-            let expected = sorted_authors(&entry);
+            let expected = entry.authors.clone();
             if expected.is_empty() {
                 prop_assert!(json.get("authors").is_none());
                 prop_assert!(json.get("author").is_none());
@@ -1251,6 +1978,204 @@ mod tests {
                 }
                 None => prop_assert!(json.get("thumbnail").is_none()),
             }
+
+            match entry.published.as_ref() {
+                Some(date) => {
+                    let value = json.get("published").and_then(JsonValue::as_str).unwrap();
+                    prop_assert_eq!(parse_date(value), Some(*date));
+                }
+                None => prop_assert!(json.get("published").is_none()),
+            }
         }
     }
+
+    proptest! {
+        #[test]
+        fn entry_json_round_trips(entry in super::entry_strategy()) {
+            let json = serde_json::to_string(&entry).unwrap();
+            let back: Entry = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(back.published, entry.published);
+            prop_assert_eq!(back.citation, entry.citation);
+        }
+
+        #[test]
+        fn view_fields_are_listed_in_order(entry in super::entry_strategy()) {
+            let JsonValue::Object(view) = serde_json::to_value(EntryView::from(&entry)).unwrap() else {
+                panic!("views serialise to objects");
+            };
+            let mut fields = EntryView::FIELDS.iter();
+            for key in view.keys() {
+                prop_assert!(fields.any(|f| f == key), "{} is missing or out of order", key);
+            }
+        }
+    }
+
+    #[test]
+    fn replacing_keeps_user_data_and_reports_changes() {
+        let url = Url::parse("https://lwn.net/Articles/1").unwrap();
+        let old = Entry::unfetched(&url, Some("My title".into()))
+            .with_tags([Tag::try_from("rust").unwrap()])
+            .with_notes(Some("read later".into()));
+        let fresh = Entry::unfetched(&url, Some("My title".into()))
+            .with_description(Some("New summary".into()))
+            .replacing(&old);
+        assert_eq!(fresh.id(), old.id());
+        assert_eq!(fresh.notes(), Some("read later"));
+        assert_eq!(fresh.tags(), old.tags());
+        assert_eq!(old.changes(&fresh), ["description"]);
+    }
+
+    #[test]
+    fn metadata_only_extraction_skips_article_text() {
+        let url = Url::parse("https://example.com/post").unwrap();
+        let html = "<html><head><title>Post</title></head><body><article><p>Body text \
+            that goes on for long enough to count as an article.</p></article></body></html>";
+        let full = Entry::extract(&Live, &url, html, None, Extraction::FULL);
+        let light = Entry::extract(&Live, &url, html, None, Extraction::METADATA_ONLY);
+        assert!(full.full_text().contains("Body text"));
+        assert_eq!(light.full_text(), "");
+        assert_eq!(light.title(), "Post");
+    }
+
+    #[test]
+    fn raw_meta_is_kept_only_when_asked_for() {
+        let url = Url::parse("https://example.com/clip").unwrap();
+        let html = r#"<html><head><title>Clip</title>
+            <meta property="og:video" content="https://example.com/clip.mp4">
+            </head><body></body></html>"#;
+        let plain = Entry::extract(&Live, &url, html, None, Extraction::METADATA_ONLY);
+        assert!(plain.meta().is_none());
+        let extraction = Extraction {
+            raw_meta: true,
+            ..Extraction::METADATA_ONLY
+        };
+        let entry = Entry::extract(&Live, &url, html, None, extraction);
+        let json = serde_json::to_value(&entry).unwrap();
+        assert_eq!(
+            json["meta"]["properties"]["og:video"],
+            "https://example.com/clip.mp4"
+        );
+        let template = crate::template::Template::new(
+            r#"{{ meta.properties["og:video"] }}"#.to_owned(),
+            Default::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&entry).unwrap(),
+            "https://example.com/clip.mp4"
+        );
+        let stored: Entry = serde_json::from_value(json).unwrap();
+        assert_eq!(stored.meta(), entry.meta());
+    }
+
+    #[test]
+    fn parses_partial_and_full_dates() {
+        let day = |y, m, d| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        assert_eq!(
+            parse_published("2020/05/12"),
+            Some((day(2020, 5, 12), Precision::Day))
+        );
+        assert_eq!(
+            parse_published("2020-05"),
+            Some((day(2020, 5, 1), Precision::Month))
+        );
+        assert_eq!(
+            parse_published("1936"),
+            Some((day(1936, 1, 1), Precision::Year))
+        );
+        assert_eq!(
+            parse_date("2024-03-01T10:30:00+02:00"),
+            DateTime::from_timestamp(1_709_281_800, 0)
+        );
+        assert_eq!(parse_date("yesterday"), None);
+        assert_eq!(parse_date("12"), None);
+    }
+
+    #[test]
+    fn partial_dates_keep_their_precision() {
+        let url = Url::parse("https://example.com/paper").unwrap();
+        let page = |date: &str| {
+            let html = format!(r#"<head><meta name="dc.date" content="{date}"></head>"#);
+            Entry::extract(&Live, &url, &html, None, Extraction::METADATA_ONLY)
+        };
+        for (date, view) in [
+            ("1936", "1936"),
+            ("2020-05", "2020-05"),
+            ("2020-05-12", "2020-05-12T00:00:00Z"),
+        ] {
+            let entry = page(date);
+            assert_eq!(entry.published_date().unwrap().to_string(), date);
+            assert_eq!(context_json(&entry)["published"], view);
+            let stored: Entry =
+                serde_json::from_str(&serde_json::to_string(&entry).unwrap()).unwrap();
+            assert_eq!(stored.published_date(), entry.published_date());
+        }
+        assert_eq!(page("1936").changes(&page("1936-01-01")), ["published"]);
+    }
+
+    /// A TimeGate at `web.archive.org` with one snapshot, whose links are
+    /// rewritten the way the Wayback Machine does.
+    struct Wayback;
+
+    impl Fetcher for Wayback {
+        fn send(
+            &self,
+            method: Method,
+            url: &Url,
+            _: &[(&str, &str)],
+            _: usize,
+        ) -> Result<Response, ureq::Error> {
+            let snapshot = "/web/20200102030405/https://example.com/post";
+            let (headers, body) = match method {
+                Method::Head => (
+                    vec![
+                        ("memento-datetime", "Thu, 02 Jan 2020 03:04:05 GMT"),
+                        ("content-location", snapshot),
+                    ],
+                    "",
+                ),
+                Method::Get if url.path() == snapshot => (
+                    vec![("content-type", "text/html")],
+                    r#"<head><title>Post</title>
+                    <link rel="canonical" href="https://web.archive.org/web/20200102030405/https://example.com/post">
+                    <meta property="og:image" content="/web/20200102030405im_/https://example.com/a.png">
+                    </head>"#,
+                ),
+                Method::Get => return Err(ureq::Error::StatusCode(404)),
+            };
+            Ok(Response {
+                status: 200,
+                headers: headers
+                    .into_iter()
+                    .map(|(n, v)| (n.to_owned(), v.to_owned()))
+                    .collect(),
+                body: body.as_bytes().to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn archived_entries_point_at_the_original_site() {
+        let url = Url::parse("https://example.com/post").unwrap();
+        let provider = crate::archive::Memento::default();
+        let entry =
+            Entry::archived(&url, None, &provider, &Wayback, Extraction::METADATA_ONLY).unwrap();
+        assert_eq!(entry.url(), &url);
+        assert_eq!(entry.site(), "example.com");
+        assert_eq!(
+            entry.thumbnail().map(Url::as_str),
+            Some("https://example.com/a.png")
+        );
+        assert_eq!(entry.canonical(), None);
+        assert_eq!(
+            entry.archive().map(|s| s.url.as_str()),
+            Some("https://web.archive.org/web/20200102030405/https://example.com/post")
+        );
+    }
 }
@@ -0,0 +1,192 @@
+use std::fmt::Write;
+
+use super::{Kind, Name, citation_keys};
+use crate::entry::Entry;
+
+/// Render entries as BibLaTeX records: `@article` for journal papers,
+/// `@inproceedings` for conference papers and `@online` for everything else.
+pub fn render(entries: &[Entry]) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    for (i, (entry, key)) in entries.iter().zip(citation_keys(entries)).enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        write_record(&mut out, entry, &key)?;
+    }
+    Ok(out)
+}
+
+fn write_record(out: &mut impl Write, entry: &Entry, key: &str) -> std::fmt::Result {
+    let kind = Kind::of(entry);
+    let ty = match kind {
+        Kind::Article => "article",
+        Kind::Proceedings => "inproceedings",
+        Kind::Webpage => "online",
+    };
+    writeln!(out, "@{ty}{{{key},")?;
+    field(out, "title", Some(entry.title()))?;
+    let authors = entry
+        .authors()
+        .iter()
+        .map(|a| match Name::parse(a) {
+            Name {
+                family,
+                given: Some(given),
+            } => format!("{}, {}", escape(family), escape(given)),
+            Name {
+                family,
+                given: None,
+            } => format!("{{{}}}", escape(family)),
+        })
+        .collect::<Vec<_>>();
+    if !authors.is_empty() {
+        writeln!(out, "  author = {{{}}},", authors.join(" and "))?;
+    }
+    let citation = entry.citation();
+    match kind {
+        Kind::Article => field(out, "journal", citation.and_then(|c| c.journal.as_deref()))?,
+        Kind::Proceedings => field(
+            out,
+            "booktitle",
+            citation.and_then(|c| c.conference.as_deref()),
+        )?,
+        Kind::Webpage => field(out, "organization", Some(entry.site()))?,
+    }
+    if let Some(date) = entry.published_date() {
+        writeln!(out, "  date = {{{date}}},")?;
+        writeln!(out, "  year = {{{}}},", date.year)?;
+    }
+    if let Some(c) = citation {
+        field(out, "volume", c.volume.as_deref())?;
+        field(out, "number", c.issue.as_deref())?;
+        if let Some(pages) = c.pages.as_deref() {
+            writeln!(out, "  pages = {{{}}},", escape(pages).replace('-', "--"))?;
+        }
+        field(out, "publisher", c.publisher.as_deref())?;
+        field(out, "issn", c.issn.as_deref())?;
+        verbatim(out, "doi", c.doi.as_deref())?;
+    }
+    verbatim(out, "url", Some(entry.url().as_str()))?;
+    field(out, "abstract", entry.description())?;
+    writeln!(out, "}}")
+}
+
+fn field(out: &mut impl Write, name: &str, value: Option<&str>) -> std::fmt::Result {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => writeln!(out, "  {name} = {{{}}},", escape(v)),
+        None => Ok(()),
+    }
+}
+
+/// Fields that BibLaTeX reads verbatim (URLs, DOIs) only need their braces
+/// kept balanced.
+fn verbatim(out: &mut impl Write, name: &str, value: Option<&str>) -> std::fmt::Result {
+    match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(v) => writeln!(out, "  {name} = {{{}}},", v.replace(['{', '}'], "")),
+        None => Ok(()),
+    }
+}
+
+/// Escape the characters LaTeX treats specially and collapse line breaks,
+/// which would otherwise end up as paragraph breaks.
+pub(crate) fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut was_space = false;
+    for ch in s.chars() {
+        if ch.is_whitespace() {
+            if !was_space {
+                out.push(' ');
+            }
+            was_space = true;
+            continue;
+        }
+        was_space = false;
+        match ch {
+            '\\' => out.push_str(r"\textbackslash{}"),
+            '~' => out.push_str(r"\textasciitilde{}"),
+            '^' => out.push_str(r"\textasciicircum{}"),
+            '{' | '}' | '&' | '%' | '$' | '#' | '_' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+    out.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entry::{Extraction, entry_strategy},
+        fetch::Live,
+    };
+    use proptest::prelude::*;
+
+    #[test]
+    fn escapes_latex_specials() {
+        assert_eq!(
+            escape("50% of R&D_costs {approx} ~$5\n#1 \\o/"),
+            r"50\% of R\&D\_costs \{approx\} \textasciitilde{}\$5 \#1 \textbackslash{}o/"
+        );
+    }
+
+    #[test]
+    fn organisations_and_single_names_are_braced() {
+        let mut json = serde_json::to_value(Entry::unfetched(
+            &url::Url::parse("https://example.com/report").unwrap(),
+            Some("Annual report".into()),
+        ))
+        .unwrap();
+        json["authors"] = serde_json::json!(["Procter and Gamble", "Plato", "Ada Lovelace"]);
+        let entry: Entry = serde_json::from_value(json).unwrap();
+        let out = render(&[entry]).unwrap();
+        assert!(
+            out.contains("  author = {{Procter and Gamble} and {Plato} and Lovelace, Ada},"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn authors_keep_the_page_order() {
+        let url = url::Url::parse("https://example.com/paper").unwrap();
+        let html = r#"<head><meta name="citation_title" content="On Computable Numbers">
+            <meta name="citation_author" content="Turing, Alan">
+            <meta name="citation_author" content="Church, Alonzo">
+            <meta name="citation_author" content="Turing, Alan"></head>"#;
+        let entry = Entry::extract(&Live, &url, html, None, Extraction::METADATA_ONLY);
+        let out = render(&[entry]).unwrap();
+        let key = out.split_once('{').unwrap().1;
+        assert!(key.starts_with("turing"), "{out}");
+        assert!(
+            out.contains("  author = {Turing, Alan and Church, Alonzo},"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn partial_dates_stay_partial() {
+        let url = url::Url::parse("https://example.com/paper").unwrap();
+        let html = r#"<head><meta name="dc.date" content="1936"></head>"#;
+        let entry = Entry::extract(&Live, &url, html, None, Extraction::METADATA_ONLY);
+        let out = render(&[entry]).unwrap();
+        assert!(
+            out.contains("  date = {1936},\n  year = {1936},\n"),
+            "{out}"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn records_have_balanced_braces(entry in entry_strategy()) {
+            let out = render(std::slice::from_ref(&entry)).unwrap();
+            let unescaped = out.replace(r"\{", "").replace(r"\}", "");
+            let opens = unescaped.matches('{').count();
+            let closes = unescaped.matches('}').count();
+            prop_assert_eq!(opens, closes);
+            prop_assert!(out.starts_with('@'), "missing entry type");
+            prop_assert!(out.trim_end().ends_with('}'), "unterminated record");
+        }
+    }
+}
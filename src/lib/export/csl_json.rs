@@ -0,0 +1,157 @@
+use serde::Serialize;
+
+use super::{Error, Kind, Name, citation_keys};
+use crate::entry::Entry;
+
+/// A single CSL-JSON item. Only the variables spy can populate are modelled.
+#[derive(Serialize)]
+struct Item<'a> {
+    id: String,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    author: Vec<Person<'a>>,
+    #[serde(rename = "container-title", skip_serializing_if = "Option::is_none")]
+    container_title: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issued: Option<DateParts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    volume: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issue: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publisher: Option<&'a str>,
+    #[serde(rename = "ISSN", skip_serializing_if = "Option::is_none")]
+    issn: Option<&'a str>,
+    #[serde(rename = "DOI", skip_serializing_if = "Option::is_none")]
+    doi: Option<&'a str>,
+    #[serde(rename = "URL")]
+    url: &'a str,
+    #[serde(rename = "abstract", skip_serializing_if = "Option::is_none")]
+    abstract_: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Person<'a> {
+    Split {
+        family: &'a str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        given: Option<&'a str>,
+    },
+    Literal {
+        literal: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct DateParts {
+    #[serde(rename = "date-parts")]
+    date_parts: [Vec<i32>; 1],
+}
+
+/// Render entries as a CSL-JSON array.
+pub fn render(entries: &[Entry]) -> Result<String, Error> {
+    let items: Vec<Item> = entries
+        .iter()
+        .zip(citation_keys(entries))
+        .map(|(entry, id)| item(entry, id))
+        .collect();
+    Ok(serde_json::to_string_pretty(&items)?)
+}
+
+fn item(entry: &Entry, id: String) -> Item<'_> {
+    let kind = Kind::of(entry);
+    let citation = entry.citation();
+    let author = entry
+        .authors()
+        .into_iter()
+        .map(|a| match Name::parse(a) {
+            Name {
+                family,
+                given: Some(given),
+            } => Person::Split {
+                family,
+                given: Some(given),
+            },
+            Name { given: None, .. } => Person::Literal { literal: a.trim() },
+        })
+        .collect();
+    Item {
+        id,
+        ty: match kind {
+            Kind::Article => "article-journal",
+            Kind::Proceedings => "paper-conference",
+            Kind::Webpage => "webpage",
+        },
+        title: entry.title(),
+        author,
+        container_title: match kind {
+            Kind::Article => citation.and_then(|c| c.journal.as_deref()),
+            Kind::Proceedings => citation.and_then(|c| c.conference.as_deref()),
+            Kind::Webpage => Some(entry.site()).filter(|s| !s.is_empty()),
+        },
+        issued: entry.published_date().map(|d| DateParts {
+            date_parts: [[
+                Some(d.year),
+                d.month.map(|m| m as i32),
+                d.day.map(|d| d as i32),
+            ]
+            .into_iter()
+            .map_while(|part| part)
+            .collect()],
+        }),
+        volume: citation.and_then(|c| c.volume.as_deref()),
+        issue: citation.and_then(|c| c.issue.as_deref()),
+        page: citation.and_then(|c| c.pages.as_deref()),
+        publisher: citation.and_then(|c| c.publisher.as_deref()),
+        issn: citation.and_then(|c| c.issn.as_deref()),
+        doi: citation.and_then(|c| c.doi.as_deref()),
+        url: entry.url().as_str(),
+        abstract_: entry.description(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entry::{Extraction, entry_strategy},
+        fetch::Live,
+    };
+    use proptest::prelude::*;
+    use serde_json::{Value, json};
+
+    #[test]
+    fn issued_has_only_the_known_parts() {
+        let url = url::Url::parse("https://example.com/paper").unwrap();
+        for (date, parts) in [
+            ("1936", json!([[1936]])),
+            ("2020-05", json!([[2020, 5]])),
+            ("2020-05-12", json!([[2020, 5, 12]])),
+        ] {
+            let html = format!(r#"<head><meta name="dc.date" content="{date}"></head>"#);
+            let entry = Entry::extract(&Live, &url, &html, None, Extraction::METADATA_ONLY);
+            let out: Value = serde_json::from_str(&render(&[entry]).unwrap()).unwrap();
+            assert_eq!(out[0]["issued"]["date-parts"], parts);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn items_carry_required_variables(entries in prop::collection::vec(entry_strategy(), 0..4)) {
+            let out: Value = serde_json::from_str(&render(&entries).unwrap()).unwrap();
+            let items = out.as_array().unwrap();
+            prop_assert_eq!(items.len(), entries.len());
+            for (item, entry) in items.iter().zip(&entries) {
+                prop_assert!(item.get("id").and_then(Value::as_str).is_some());
+                prop_assert!(item.get("type").and_then(Value::as_str).is_some());
+                prop_assert_eq!(item.get("URL").and_then(Value::as_str), Some(entry.url().as_str()));
+                prop_assert_eq!(item.get("title").and_then(Value::as_str), Some(entry.title()));
+            }
+        }
+    }
+}
@@ -0,0 +1,735 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use chrono::{SecondsFormat, Utc};
+use ego_tree::NodeRef;
+use flate2::{Compression, Crc, write::DeflateEncoder};
+use scraper::{Html, Node};
+use url::Url;
+use uuid::Uuid;
+
+use super::xml_escape;
+use crate::{entry::Entry, snapshot::Resource};
+
+/// Title of a book collecting several entries, unless one is given.
+const DEFAULT_TITLE: &str = "Reading list";
+
+/// Elements dropped with their contents: they either cannot run in a reader
+/// or would need namespaces and resources the book does not carry.
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "noscript", "template", "style", "iframe", "object", "embed", "form", "input",
+    "button", "select", "textarea", "svg", "math", "canvas", "video", "audio", "link", "meta",
+    "base",
+];
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "br", "col", "hr", "img", "input", "source", "track", "wbr",
+];
+/// Attributes kept on chapter content; anything else is presentational or
+/// not valid in EPUB content documents.
+const KEPT_ATTRIBUTES: &[&str] = &[
+    "alt", "title", "lang", "dir", "colspan", "rowspan", "cite", "datetime", "start", "reversed",
+];
+/// Image types EPUB reading systems are required to support.
+const IMAGE_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLE: &str = "body{font-family:serif;line-height:1.5}\
+img{max-width:100%;height:auto}.byline,.source{color:#666;font-size:.9em}pre{white-space:pre-wrap}";
+
+/// Builds an EPUB 3 book with one chapter per entry. Images in the article
+/// text are downloaded and embedded while the budget lasts; the rest are
+/// replaced by their alt text.
+pub struct Book<F> {
+    title: Option<String>,
+    language: String,
+    budget: usize,
+    fetch: F,
+}
+
+impl<F: Fn(&Url, usize) -> Option<Resource>> Book<F> {
+    /// `fetch` is called with each image URL and the bytes left in the budget.
+    pub fn new(budget: usize, fetch: F) -> Self {
+        Book {
+            title: None,
+            language: "en".to_owned(),
+            budget,
+            fetch,
+        }
+    }
+
+    /// Title the book, rather than using the entry's title (or a generic one
+    /// for several entries).
+    pub fn with_title(mut self, title: Option<String>) -> Self {
+        self.title = title;
+        self
+    }
+
+    /// Set the book's language as a BCP 47 tag.
+    pub fn with_language(mut self, language: String) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Write the book for `entries` as a zipped EPUB container.
+    pub fn write<W: Write>(&self, entries: &[Entry], out: W) -> io::Result<W> {
+        let mut images = Images {
+            fetch: &self.fetch,
+            budget: self.budget,
+            files: Vec::new(),
+            by_url: HashMap::new(),
+        };
+        let chapters: Vec<String> = entries
+            .iter()
+            .map(|entry| self.chapter(entry, &mut images))
+            .collect();
+
+        let mut zip = Zip::new(out);
+        // The mimetype must come first and uncompressed so the container can
+        // be recognised from its leading bytes.
+        zip.add("mimetype", b"application/epub+zip", false)?;
+        zip.add("META-INF/container.xml", CONTAINER.as_bytes(), true)?;
+        zip.add(
+            "OEBPS/content.opf",
+            self.package(entries, &images.files).as_bytes(),
+            true,
+        )?;
+        zip.add("OEBPS/nav.xhtml", self.nav(entries).as_bytes(), true)?;
+        zip.add("OEBPS/style.css", STYLE.as_bytes(), true)?;
+        for (n, chapter) in chapters.iter().enumerate() {
+            zip.add(
+                &format!("OEBPS/{}", chapter_file(n)),
+                chapter.as_bytes(),
+                true,
+            )?;
+        }
+        for image in &images.files {
+            // Image formats are already compressed.
+            zip.add(
+                &format!("OEBPS/{}", image.href),
+                &image.resource.data,
+                false,
+            )?;
+        }
+        zip.finish()
+    }
+
+    fn title(&self, entries: &[Entry]) -> String {
+        match (&self.title, entries) {
+            (Some(title), _) => title.clone(),
+            (None, [entry]) => entry.title().to_owned(),
+            _ => DEFAULT_TITLE.to_owned(),
+        }
+    }
+
+    /// The package document: metadata, manifest and reading order.
+    fn package(&self, entries: &[Entry], images: &[Image]) -> String {
+        let identifier = match entries {
+            [entry] => *entry.id(),
+            _ => Uuid::new_v4(),
+        };
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" xml:lang="{lang}">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:identifier id="book-id">urn:uuid:{identifier}</dc:identifier>
+    <dc:title>{title}</dc:title>
+    <dc:language>{lang}</dc:language>
+"#,
+            lang = xml_escape(&self.language, true),
+            title = xml_escape(&self.title(entries), false),
+        );
+        let authors: BTreeSet<&str> = entries.iter().flat_map(|e| e.authors()).collect();
+        for author in authors {
+            let _ = writeln!(
+                out,
+                "    <dc:creator>{}</dc:creator>",
+                xml_escape(author, false)
+            );
+        }
+        if let [entry] = entries {
+            let _ = writeln!(
+                out,
+                "    <dc:publisher>{}</dc:publisher>",
+                xml_escape(entry.site(), false)
+            );
+            let _ = writeln!(
+                out,
+                "    <dc:source>{}</dc:source>",
+                xml_escape(entry.url().as_str(), false)
+            );
+            if let Some((published, date)) = entry.published().zip(entry.published_date()) {
+                // W3CDTF allows a bare year or year and month.
+                let date = match date.day {
+                    Some(_) => published.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    None => date.to_string(),
+                };
+                let _ = writeln!(out, "    <dc:date>{date}</dc:date>");
+            }
+            if let Some(description) = entry.description() {
+                let _ = writeln!(
+                    out,
+                    "    <dc:description>{}</dc:description>",
+                    xml_escape(description, false)
+                );
+            }
+        }
+        let subjects: BTreeSet<String> = entries
+            .iter()
+            .flat_map(|e| e.tags())
+            .map(ToString::to_string)
+            .collect();
+        for subject in subjects {
+            let _ = writeln!(
+                out,
+                "    <dc:subject>{}</dc:subject>",
+                xml_escape(&subject, false)
+            );
+        }
+        let _ = write!(
+            out,
+            r#"    <meta property="dcterms:modified">{}</meta>
+  </metadata>
+  <manifest>
+    <item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>
+    <item id="style" href="style.css" media-type="text/css"/>
+"#,
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        for n in 0..entries.len() {
+            let _ = writeln!(
+                out,
+                r#"    <item id="chapter-{}" href="{}" media-type="application/xhtml+xml"/>"#,
+                n + 1,
+                chapter_file(n)
+            );
+        }
+        for (n, image) in images.iter().enumerate() {
+            let cover = if image.cover {
+                r#" properties="cover-image""#
+            } else {
+                ""
+            };
+            let _ = writeln!(
+                out,
+                r#"    <item id="image-{}" href="{}" media-type="{}"{cover}/>"#,
+                n + 1,
+                image.href,
+                image.resource.mime
+            );
+        }
+        out.push_str("  </manifest>\n  <spine>\n");
+        for n in 0..entries.len() {
+            let _ = writeln!(out, r#"    <itemref idref="chapter-{}"/>"#, n + 1);
+        }
+        out.push_str("  </spine>\n</package>\n");
+        out
+    }
+
+    /// The navigation document, listing each chapter by title.
+    fn nav(&self, entries: &[Entry]) -> String {
+        let mut items = String::new();
+        for (n, entry) in entries.iter().enumerate() {
+            let _ = writeln!(
+                items,
+                r#"        <li><a href="{}">{}</a></li>"#,
+                chapter_file(n),
+                xml_escape(entry.title(), false)
+            );
+        }
+        self.document(
+            &self.title(entries),
+            &format!(
+                r#"    <nav epub:type="toc" id="toc">
+      <h1>Contents</h1>
+      <ol>
+{items}      </ol>
+    </nav>
+"#
+            ),
+        )
+    }
+
+    fn chapter(&self, entry: &Entry, images: &mut Images<F>) -> String {
+        let mut byline: Vec<String> = Vec::new();
+        if !entry.authors().is_empty() {
+            byline.push(entry.authors().join(", "));
+        }
+        byline.push(entry.site().to_owned());
+        if let Some(published) = entry.published_date() {
+            byline.push(published.to_string());
+        }
+        let thumbnail = entry
+            .thumbnail()
+            .and_then(|url| images.embed(url, true))
+            .map_or(String::new(), |href| {
+                format!(r#"<img class="thumbnail" src="{href}" alt=""/>"#)
+            });
+        let body = format!(
+            r#"    <section epub:type="chapter">
+      <header><h1>{title}</h1><p class="byline">{byline}</p><p class="source"><a href="{url}">{url}</a></p>{thumbnail}</header>
+{text}
+    </section>
+"#,
+            title = xml_escape(entry.title(), false),
+            byline = xml_escape(&byline.join(" · "), false),
+            url = xml_escape(entry.url().as_str(), true),
+            text = xhtml(entry.full_text(), entry.url(), images),
+        );
+        self.document(entry.title(), &body)
+    }
+
+    /// Wrap a body in an XHTML content document.
+    fn document(&self, title: &str, body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops" xml:lang="{lang}" lang="{lang}">
+  <head>
+    <title>{title}</title>
+    <link rel="stylesheet" type="text/css" href="style.css"/>
+  </head>
+  <body>
+{body}  </body>
+</html>
+"#,
+            lang = xml_escape(&self.language, true),
+            title = xml_escape(title, false),
+        )
+    }
+}
+
+fn chapter_file(n: usize) -> String {
+    format!("chapter-{:03}.xhtml", n + 1)
+}
+
+/// An image embedded in the book.
+struct Image {
+    href: String,
+    resource: Resource,
+    cover: bool,
+}
+
+/// Images fetched so far, shared across chapters so each is stored once.
+struct Images<'a, F> {
+    fetch: &'a F,
+    budget: usize,
+    files: Vec<Image>,
+    /// Path within the book (or `None` for failures) by image URL.
+    by_url: HashMap<Url, Option<String>>,
+}
+
+impl<F: Fn(&Url, usize) -> Option<Resource>> Images<'_, F> {
+    /// Embed an image, returning its path relative to the chapters. The
+    /// first thumbnail embedded becomes the book's cover.
+    fn embed(&mut self, url: &Url, thumbnail: bool) -> Option<String> {
+        if let Some(href) = self.by_url.get(url) {
+            return href.clone();
+        }
+        let href = (self.fetch)(url, self.budget).and_then(|resource| {
+            let (_, extension) = IMAGE_TYPES
+                .iter()
+                .find(|(mime, _)| *mime == resource.mime)?;
+            if resource.data.len() > self.budget {
+                return None;
+            }
+            self.budget -= resource.data.len();
+            let href = format!("images/{}.{extension}", self.files.len() + 1);
+            let cover = thumbnail && !self.files.iter().any(|i| i.cover);
+            self.files.push(Image {
+                href: href.clone(),
+                resource,
+                cover,
+            });
+            Some(href)
+        });
+        self.by_url.insert(url.clone(), href.clone());
+        href
+    }
+}
+
+/// Convert extracted article HTML to well-formed XHTML, embedding images and
+/// making links absolute unless they point at an id within the chapter.
+fn xhtml<F: Fn(&Url, usize) -> Option<Resource>>(
+    html: &str,
+    base: &Url,
+    images: &mut Images<F>,
+) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut ids = HashSet::new();
+    kept_ids(*fragment.root_element(), &mut ids);
+    let mut out = String::new();
+    for child in fragment.root_element().children() {
+        write_node(&mut out, child, base, &ids, images);
+    }
+    out
+}
+
+/// The ids [`write_node`] keeps, so links to them can stay in the chapter.
+fn kept_ids<'a>(node: NodeRef<'a, Node>, ids: &mut HashSet<&'a str>) {
+    if let Node::Element(el) = node.value() {
+        if DROPPED_ELEMENTS.contains(&el.name()) || el.name() == "img" {
+            return;
+        }
+        if let Some(id) = el.attr("id")
+            && el.name().bytes().all(|b| b.is_ascii_alphanumeric())
+            && is_xml_id(id)
+        {
+            ids.insert(id);
+        }
+    }
+    for child in node.children() {
+        kept_ids(child, ids);
+    }
+}
+
+fn write_node<F: Fn(&Url, usize) -> Option<Resource>>(
+    out: &mut String,
+    node: NodeRef<Node>,
+    base: &Url,
+    ids: &HashSet<&str>,
+    images: &mut Images<F>,
+) {
+    match node.value() {
+        Node::Text(t) => out.push_str(&xml_escape(t, false)),
+        Node::Element(el) => {
+            let name = el.name();
+            if DROPPED_ELEMENTS.contains(&name) {
+                return;
+            }
+            // Custom and unknown elements are not valid XHTML, but their
+            // text is part of the article.
+            if !name.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                for child in node.children() {
+                    write_node(out, child, base, ids, images);
+                }
+                return;
+            }
+            if name == "img" {
+                let src = el
+                    .attr("data-src")
+                    .or(el.attr("src"))
+                    .and_then(|src| base.join(src.trim()).ok());
+                match src.and_then(|src| images.embed(&src, false)) {
+                    Some(href) => {
+                        let _ = write!(
+                            out,
+                            r#"<img src="{href}" alt="{}"/>"#,
+                            xml_escape(el.attr("alt").unwrap_or_default(), true)
+                        );
+                    }
+                    None => out.push_str(&xml_escape(el.attr("alt").unwrap_or_default(), false)),
+                }
+                return;
+            }
+            let _ = write!(out, "<{name}");
+            for (attr, value) in el.attrs() {
+                if KEPT_ATTRIBUTES.contains(&attr) || attr == "id" && is_xml_id(value) {
+                    let _ = write!(out, r#" {attr}="{}""#, xml_escape(value, true));
+                }
+            }
+            if name == "a"
+                && let Some(href) = el.attr("href").map(str::trim)
+            {
+                if href.strip_prefix('#').is_some_and(|id| ids.contains(id)) {
+                    let _ = write!(out, r#" href="{}""#, xml_escape(href, true));
+                } else if let Ok(href) = base.join(href)
+                    && matches!(href.scheme(), "http" | "https" | "mailto")
+                {
+                    let _ = write!(out, r#" href="{}""#, xml_escape(href.as_str(), true));
+                }
+            }
+            if VOID_ELEMENTS.contains(&name) {
+                out.push_str("/>");
+                return;
+            }
+            out.push('>');
+            for child in node.children() {
+                write_node(out, child, base, ids, images);
+            }
+            let _ = write!(out, "</{name}>");
+        }
+        Node::Document | Node::Fragment => {
+            for child in node.children() {
+                write_node(out, child, base, ids, images);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Whether `id` can be an XML ID: a name without colons.
+fn is_xml_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// A minimal ZIP writer: each file is stored or deflated in one go, so sizes
+/// and checksums are known before its local header is written.
+struct Zip<W> {
+    out: W,
+    offset: u32,
+    central: Vec<u8>,
+    count: u16,
+}
+
+/// 1980-01-01 00:00 in MS-DOS format; timestamps are not meaningful here.
+const DOS_DATE: u16 = (1 << 5) | 1;
+/// General purpose flag marking file names as UTF-8.
+const UTF8_NAMES: u16 = 1 << 11;
+
+impl<W: Write> Zip<W> {
+    fn new(out: W) -> Self {
+        Zip {
+            out,
+            offset: 0,
+            central: Vec::new(),
+            count: 0,
+        }
+    }
+
+    fn add(&mut self, name: &str, data: &[u8], compress: bool) -> io::Result<()> {
+        let mut crc = Crc::new();
+        crc.update(data);
+        let (method, stored) = if compress {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data)?;
+            (8u16, encoder.finish()?)
+        } else {
+            (0u16, data.to_vec())
+        };
+        let size = |n: usize| {
+            u32::try_from(n).map_err(|_| io::Error::other(format!("{name} is too large")))
+        };
+        let (compressed, uncompressed) = (size(stored.len())?, size(data.len())?);
+        let name_len = u16::try_from(name.len()).map_err(io::Error::other)?;
+
+        let mut header = Vec::with_capacity(30 + name.len());
+        header.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        header.extend_from_slice(&20u16.to_le_bytes());
+        header.extend_from_slice(&UTF8_NAMES.to_le_bytes());
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&DOS_DATE.to_le_bytes());
+        header.extend_from_slice(&crc.sum().to_le_bytes());
+        header.extend_from_slice(&compressed.to_le_bytes());
+        header.extend_from_slice(&uncompressed.to_le_bytes());
+        header.extend_from_slice(&name_len.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(name.as_bytes());
+
+        let central = &mut self.central;
+        central.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        // Everything from the version needed to the extra field length
+        // matches the local header.
+        central.extend_from_slice(&header[4..30]);
+        // Comment length, disk number, internal and external attributes.
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&self.offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        self.out.write_all(&header)?;
+        self.out.write_all(&stored)?;
+        self.offset = self
+            .offset
+            .checked_add(size(header.len())? + compressed)
+            .ok_or_else(|| io::Error::other("book is too large"))?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Write the central directory and return the underlying writer.
+    fn finish(mut self) -> io::Result<W> {
+        let size = u32::try_from(self.central.len()).map_err(io::Error::other)?;
+        self.out.write_all(&self.central)?;
+        let mut end = Vec::with_capacity(22);
+        end.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        end.extend_from_slice(&[0; 4]);
+        end.extend_from_slice(&self.count.to_le_bytes());
+        end.extend_from_slice(&self.count.to_le_bytes());
+        end.extend_from_slice(&size.to_le_bytes());
+        end.extend_from_slice(&self.offset.to_le_bytes());
+        end.extend_from_slice(&0u16.to_le_bytes());
+        self.out.write_all(&end)?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::entry::entry_strategy;
+
+    fn fetch(url: &Url, limit: usize) -> Option<Resource> {
+        let (mime, data): (&str, Vec<u8>) = match url.path() {
+            "/logo.png" => ("image/png", b"png".to_vec()),
+            "/vector.svg" => ("image/svg+xml", b"<svg/>".to_vec()),
+            "/huge.jpg" => ("image/jpeg", vec![0; 4096]),
+            _ => return None,
+        };
+        (data.len() <= limit).then(|| Resource {
+            mime: mime.to_owned(),
+            data,
+        })
+    }
+
+    /// Read back each file's name, whether it was compressed, and contents,
+    /// checking sizes and checksums along the way.
+    fn unzip(mut bytes: &[u8]) -> Vec<(String, bool, Vec<u8>)> {
+        let u16_at = |b: &[u8], i: usize| u16::from_le_bytes([b[i], b[i + 1]]);
+        let u32_at = |b: &[u8], i: usize| u32::from_le_bytes(b[i..i + 4].try_into().unwrap());
+        let mut files = Vec::new();
+        while u32_at(bytes, 0) == 0x0403_4b50 {
+            let method = u16_at(bytes, 8);
+            let crc = u32_at(bytes, 14);
+            let compressed = u32_at(bytes, 18) as usize;
+            let uncompressed = u32_at(bytes, 22) as usize;
+            let name_len = u16_at(bytes, 26) as usize;
+            let name = String::from_utf8(bytes[30..30 + name_len].to_vec()).unwrap();
+            let stored = &bytes[30 + name_len..30 + name_len + compressed];
+            let data = if method == 8 {
+                let mut data = Vec::new();
+                DeflateDecoder::new(stored).read_to_end(&mut data).unwrap();
+                data
+            } else {
+                stored.to_vec()
+            };
+            assert_eq!(data.len(), uncompressed, "{name}");
+            let mut check = Crc::new();
+            check.update(&data);
+            assert_eq!(check.sum(), crc, "{name}");
+            files.push((name, method == 8, data));
+            bytes = &bytes[30 + name_len + compressed..];
+        }
+        assert_eq!(u32_at(bytes, 0), 0x0201_4b50);
+        files
+    }
+
+    fn text<'a>(files: &'a [(String, bool, Vec<u8>)], name: &str) -> &'a str {
+        let (_, _, data) = files.iter().find(|(n, _, _)| n == name).unwrap();
+        std::str::from_utf8(data).unwrap()
+    }
+
+    #[test]
+    fn converts_article_html_to_xhtml() {
+        let base = Url::parse("https://example.com/post/").unwrap();
+        let mut images = Images {
+            fetch: &fetch,
+            budget: 1024,
+            files: Vec::new(),
+            by_url: HashMap::new(),
+        };
+        let html = r#"<p class="x" onclick="go()">Tom&nbsp;&amp; Jerry<br>
+            <img src="/logo.png" alt="Logo"><img src="/logo.png">
+            <img src="/vector.svg" alt="Chart"><img src="/huge.jpg" alt="Big">
+            <a href="../next">Next</a> <a href="javascript:void(0)">JS</a></p>
+            <script>alert(1)</script><iframe src="/ad"></iframe>"#;
+        let out = xhtml(html, &base, &mut images);
+        assert!(out.starts_with("<p>Tom\u{a0}&amp; Jerry<br/>"));
+        assert!(
+            out.contains(r#"<img src="images/1.png" alt="Logo"/><img src="images/1.png" alt=""/>"#)
+        );
+        assert!(out.contains("Chart") && out.contains("Big") && !out.contains("svg"));
+        assert!(out.contains(r#"<a href="https://example.com/next">Next</a> <a>JS</a>"#));
+        assert!(!out.contains("script") && !out.contains("iframe") && !out.contains("onclick"));
+        assert_eq!(images.files.len(), 1);
+        assert_eq!(images.budget, 1021);
+    }
+
+    #[test]
+    fn unwraps_custom_elements_and_keeps_valid_ids() {
+        let base = Url::parse("https://example.com/").unwrap();
+        let mut images = Images {
+            fetch: &fetch,
+            budget: 0,
+            files: Vec::new(),
+            by_url: HashMap::new(),
+        };
+        let html = r#"<h2 id="part-2.1">Part</h2><p id="2nd">Kept <my-note id="n:1">in
+            <em>place</em></my-note></p><script-loader>gone?</script-loader>"#;
+        let out = xhtml(html, &base, &mut images);
+        assert!(out.contains(r#"<h2 id="part-2.1">Part</h2>"#));
+        assert!(out.contains("<p>Kept in\n            <em>place</em></p>"));
+        assert!(out.contains("gone?") && !out.contains("my-note") && !out.contains("n:1"));
+        assert!(is_xml_id("_x") && is_xml_id("été") && !is_xml_id("") && !is_xml_id("a b"));
+    }
+
+    #[test]
+    fn in_page_links_stay_in_the_chapter() {
+        let base = Url::parse("https://example.com/post").unwrap();
+        let mut images = Images {
+            fetch: &fetch,
+            budget: 0,
+            files: Vec::new(),
+            by_url: HashMap::new(),
+        };
+        let html = r##"<p><a href="#notes">Notes</a> <a href="#gone">Gone</a>
+            <a href="#n:1">Bad id</a></p><h2 id="notes">Notes</h2><p id="n:1">1</p>"##;
+        let out = xhtml(html, &base, &mut images);
+        assert!(out.contains(r##"<a href="#notes">Notes</a>"##), "{out}");
+        assert!(out.contains(r#"<a href="https://example.com/post#gone">Gone</a>"#));
+        assert!(out.contains(r#"<a href="https://example.com/post#n:1">Bad id</a>"#));
+    }
+
+    #[test]
+    fn describes_a_single_entry() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let entry = Entry::unfetched(&url, Some("A & B".to_owned()));
+        let book = Book::new(1024, fetch).write(&[entry], Vec::new()).unwrap();
+        let files = unzip(&book);
+        let opf = text(&files, "OEBPS/content.opf");
+        assert!(opf.contains("<dc:title>A &amp; B</dc:title>"));
+        assert!(opf.contains("<dc:source>https://example.com/</dc:source>"));
+        assert!(!opf.contains("cover-image"));
+    }
+
+    proptest! {
+        #[test]
+        fn books_are_valid_containers(entries in prop::collection::vec(entry_strategy(), 1..4)) {
+            let book = Book::new(1024, fetch).write(&entries, Vec::new()).unwrap();
+            prop_assert!(book.starts_with(b"PK\x03\x04"));
+            prop_assert_eq!(&book[30..58], b"mimetypeapplication/epub+zip");
+            let files = unzip(&book);
+            prop_assert_eq!(&files[0].0, "mimetype");
+            prop_assert!(!files[0].1);
+            let opf = text(&files, "OEBPS/content.opf");
+            let nav = text(&files, "OEBPS/nav.xhtml");
+            for (n, entry) in entries.iter().enumerate() {
+                let file = chapter_file(n);
+                let item = format!(r#"href="{file}""#);
+                let link = format!(r#"<a href="{file}">{}</a>"#, xml_escape(entry.title(), false));
+                prop_assert!(opf.contains(&item));
+                prop_assert!(nav.contains(&link));
+                let chapter = text(&files, &format!("OEBPS/{file}"));
+                prop_assert!(chapter.contains(&xml_escape(entry.full_text(), false)));
+            }
+            for author in entries.iter().flat_map(|e| e.authors()) {
+                let creator = format!("<dc:creator>{author}</dc:creator>");
+                prop_assert!(opf.contains(&creator));
+            }
+        }
+    }
+}
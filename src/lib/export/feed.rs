@@ -0,0 +1,406 @@
+//! Syndication feeds of entries: Atom 1.0, RSS 2.0 and JSON Feed 1.1.
+
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde_json::{Map, Value, json};
+use url::Url;
+
+use super::{Error, xml_escape};
+use crate::entry::Entry;
+
+/// Feed document formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum FeedFormat {
+    /// Atom 1.0 (RFC 4287).
+    #[default]
+    Atom,
+    /// RSS 2.0.
+    Rss,
+    /// JSON Feed 1.1.
+    Json,
+}
+
+/// Feed-level metadata.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    pub title: String,
+    /// The site the feed belongs to.
+    pub link: Url,
+    /// Where the feed itself will be published, if known.
+    pub feed_url: Option<Url>,
+    pub description: Option<String>,
+    pub author: Option<String>,
+    /// Include each entry's article text, not just its description.
+    pub full_text: bool,
+}
+
+/// Render entries as a feed, in the order given.
+pub fn render(format: FeedFormat, channel: &Channel, entries: &[Entry]) -> Result<String, Error> {
+    match format {
+        FeedFormat::Atom => Ok(atom(channel, entries)?),
+        FeedFormat::Rss => Ok(rss(channel, entries)?),
+        FeedFormat::Json => Ok(serde_json::to_string_pretty(&json_feed(channel, entries))?),
+    }
+}
+
+/// When an entry last changed, for feed readers deciding what is new.
+fn updated(entry: &Entry) -> DateTime<Utc> {
+    entry
+        .refreshed()
+        .or(entry.added())
+        .or(entry.published())
+        .copied()
+        .unwrap_or_else(Utc::now)
+}
+
+fn last_updated(entries: &[Entry]) -> DateTime<Utc> {
+    entries.iter().map(updated).max().unwrap_or_else(Utc::now)
+}
+
+fn rfc3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// The entry's authors, falling back to the channel's author and then the
+/// site, as Atom requires every entry to have one.
+fn authors<'a>(channel: &'a Channel, entry: &'a Entry) -> Vec<&'a str> {
+    let authors = entry.authors();
+    if !authors.is_empty() {
+        authors
+    } else {
+        vec![channel.author.as_deref().unwrap_or(entry.site())]
+    }
+}
+
+fn atom(channel: &Channel, entries: &[Entry]) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">"#
+    )?;
+    writeln!(
+        out,
+        "  <title>{}</title>",
+        xml_escape(&channel.title, false)
+    )?;
+    if let Some(description) = &channel.description {
+        writeln!(
+            out,
+            "  <subtitle>{}</subtitle>",
+            xml_escape(description, false)
+        )?;
+    }
+    writeln!(
+        out,
+        r#"  <link href="{}"/>"#,
+        xml_escape(channel.link.as_str(), true)
+    )?;
+    if let Some(feed_url) = &channel.feed_url {
+        writeln!(
+            out,
+            r#"  <link rel="self" type="application/atom+xml" href="{}"/>"#,
+            xml_escape(feed_url.as_str(), true)
+        )?;
+    }
+    let id = channel.feed_url.as_ref().unwrap_or(&channel.link);
+    writeln!(out, "  <id>{}</id>", xml_escape(id.as_str(), false))?;
+    writeln!(
+        out,
+        "  <updated>{}</updated>",
+        rfc3339(&last_updated(entries))
+    )?;
+    if let Some(author) = &channel.author {
+        writeln!(
+            out,
+            "  <author><name>{}</name></author>",
+            xml_escape(author, false)
+        )?;
+    }
+    writeln!(out, "  <generator>spy</generator>")?;
+    for entry in entries {
+        writeln!(out, "  <entry>")?;
+        writeln!(
+            out,
+            "    <title>{}</title>",
+            xml_escape(entry.title(), false)
+        )?;
+        writeln!(
+            out,
+            r#"    <link href="{}"/>"#,
+            xml_escape(entry.url().as_str(), true)
+        )?;
+        writeln!(out, "    <id>urn:uuid:{}</id>", entry.id())?;
+        if let Some(published) = entry.published() {
+            writeln!(out, "    <published>{}</published>", rfc3339(published))?;
+        }
+        writeln!(out, "    <updated>{}</updated>", rfc3339(&updated(entry)))?;
+        for author in authors(channel, entry) {
+            writeln!(
+                out,
+                "    <author><name>{}</name></author>",
+                xml_escape(author, false)
+            )?;
+        }
+        if let Some(description) = entry.description() {
+            writeln!(
+                out,
+                "    <summary>{}</summary>",
+                xml_escape(description, false)
+            )?;
+        }
+        if channel.full_text && !entry.full_text().is_empty() {
+            writeln!(
+                out,
+                r#"    <content type="html">{}</content>"#,
+                xml_escape(entry.full_text(), false)
+            )?;
+        }
+        for tag in entry.tags() {
+            writeln!(
+                out,
+                r#"    <category term="{}"/>"#,
+                xml_escape(&tag.to_string(), true)
+            )?;
+        }
+        if let Some(thumbnail) = entry.thumbnail() {
+            writeln!(
+                out,
+                r#"    <media:thumbnail url="{}"/>"#,
+                xml_escape(thumbnail.as_str(), true)
+            )?;
+        }
+        writeln!(out, "  </entry>")?;
+    }
+    write!(out, "</feed>")?;
+    Ok(out)
+}
+
+fn rss(channel: &Channel, entries: &[Entry]) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    writeln!(out, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        out,
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom" xmlns:content="http://purl.org/rss/1.0/modules/content/" xmlns:dc="http://purl.org/dc/elements/1.1/" xmlns:media="http://search.yahoo.com/mrss/">"#
+    )?;
+    writeln!(out, "  <channel>")?;
+    writeln!(
+        out,
+        "    <title>{}</title>",
+        xml_escape(&channel.title, false)
+    )?;
+    writeln!(
+        out,
+        "    <link>{}</link>",
+        xml_escape(channel.link.as_str(), false)
+    )?;
+    // The description is required, so fall back to the title.
+    writeln!(
+        out,
+        "    <description>{}</description>",
+        xml_escape(
+            channel.description.as_deref().unwrap_or(&channel.title),
+            false
+        )
+    )?;
+    if let Some(feed_url) = &channel.feed_url {
+        writeln!(
+            out,
+            r#"    <atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+            xml_escape(feed_url.as_str(), true)
+        )?;
+    }
+    if let Some(author) = &channel.author {
+        writeln!(
+            out,
+            "    <dc:creator>{}</dc:creator>",
+            xml_escape(author, false)
+        )?;
+    }
+    writeln!(
+        out,
+        "    <lastBuildDate>{}</lastBuildDate>",
+        last_updated(entries).to_rfc2822()
+    )?;
+    writeln!(out, "    <generator>spy</generator>")?;
+    for entry in entries {
+        writeln!(out, "    <item>")?;
+        writeln!(
+            out,
+            "      <title>{}</title>",
+            xml_escape(entry.title(), false)
+        )?;
+        writeln!(
+            out,
+            "      <link>{}</link>",
+            xml_escape(entry.url().as_str(), false)
+        )?;
+        writeln!(
+            out,
+            r#"      <guid isPermaLink="false">urn:uuid:{}</guid>"#,
+            entry.id()
+        )?;
+        if let Some(published) = entry.published().or(entry.added()) {
+            writeln!(out, "      <pubDate>{}</pubDate>", published.to_rfc2822())?;
+        }
+        // RSS's own `author` must be an email address.
+        for author in entry.authors() {
+            writeln!(
+                out,
+                "      <dc:creator>{}</dc:creator>",
+                xml_escape(author, false)
+            )?;
+        }
+        if let Some(description) = entry.description() {
+            writeln!(
+                out,
+                "      <description>{}</description>",
+                xml_escape(description, false)
+            )?;
+        }
+        if channel.full_text && !entry.full_text().is_empty() {
+            writeln!(
+                out,
+                "      <content:encoded>{}</content:encoded>",
+                xml_escape(entry.full_text(), false)
+            )?;
+        }
+        for tag in entry.tags() {
+            writeln!(
+                out,
+                "      <category>{}</category>",
+                xml_escape(&tag.to_string(), false)
+            )?;
+        }
+        if let Some(thumbnail) = entry.thumbnail() {
+            writeln!(
+                out,
+                r#"      <media:thumbnail url="{}"/>"#,
+                xml_escape(thumbnail.as_str(), true)
+            )?;
+        }
+        writeln!(out, "    </item>")?;
+    }
+    writeln!(out, "  </channel>")?;
+    write!(out, "</rss>")?;
+    Ok(out)
+}
+
+fn json_feed(channel: &Channel, entries: &[Entry]) -> Value {
+    let mut feed = Map::new();
+    feed.insert("version".into(), "https://jsonfeed.org/version/1.1".into());
+    feed.insert("title".into(), channel.title.as_str().into());
+    feed.insert("home_page_url".into(), channel.link.as_str().into());
+    if let Some(feed_url) = &channel.feed_url {
+        feed.insert("feed_url".into(), feed_url.as_str().into());
+    }
+    if let Some(description) = &channel.description {
+        feed.insert("description".into(), description.as_str().into());
+    }
+    if let Some(author) = &channel.author {
+        feed.insert("authors".into(), json!([{ "name": author }]));
+    }
+    let items = entries
+        .iter()
+        .map(|entry| {
+            let mut item = Map::new();
+            item.insert("id".into(), entry.id().to_string().into());
+            item.insert("url".into(), entry.url().as_str().into());
+            item.insert("title".into(), entry.title().into());
+            // Every item needs content of some kind.
+            if channel.full_text && !entry.full_text().is_empty() {
+                item.insert("content_html".into(), entry.full_text().into());
+            } else {
+                item.insert(
+                    "content_text".into(),
+                    entry.description().unwrap_or_default().into(),
+                );
+            }
+            if let Some(description) = entry.description() {
+                item.insert("summary".into(), description.into());
+            }
+            if let Some(thumbnail) = entry.thumbnail() {
+                item.insert("image".into(), thumbnail.as_str().into());
+            }
+            if let Some(published) = entry.published() {
+                item.insert("date_published".into(), rfc3339(published).into());
+            }
+            item.insert("date_modified".into(), rfc3339(&updated(entry)).into());
+            let authors = entry.authors();
+            if !authors.is_empty() {
+                let authors: Vec<Value> = authors.iter().map(|a| json!({ "name": a })).collect();
+                item.insert("authors".into(), authors.into());
+            }
+            let tags: Vec<String> = entry.tags().iter().map(ToString::to_string).collect();
+            if !tags.is_empty() {
+                item.insert("tags".into(), tags.into());
+            }
+            Value::Object(item)
+        })
+        .collect::<Vec<_>>();
+    feed.insert("items".into(), items.into());
+    Value::Object(feed)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::entry::entry_strategy;
+
+    fn channel() -> Channel {
+        Channel {
+            title: "Links & things".to_owned(),
+            link: Url::parse("https://example.com/").unwrap(),
+            feed_url: Some(Url::parse("https://example.com/feed.xml").unwrap()),
+            description: None,
+            author: None,
+            full_text: true,
+        }
+    }
+
+    #[test]
+    fn atom_entries_have_required_elements() {
+        let url = Url::parse("https://example.org/post?a=1&b=2").unwrap();
+        let entry = Entry::unfetched(&url, Some("<Hello>".to_owned()));
+        let feed = render(FeedFormat::Atom, &channel(), &[entry]).unwrap();
+        assert!(feed.contains("<title>Links &amp; things</title>"));
+        assert!(feed.contains("<id>https://example.com/feed.xml</id>"));
+        assert!(feed.contains("<title>&lt;Hello&gt;</title>"));
+        assert!(feed.contains(r#"<link href="https://example.org/post?a=1&amp;b=2"/>"#));
+        // Falls back to the site for the required author.
+        assert!(feed.contains("<author><name>example.org</name></author>"));
+        assert_eq!(feed.matches("<updated>").count(), 2);
+    }
+
+    #[test]
+    fn rss_requires_a_description() {
+        let feed = render(FeedFormat::Rss, &channel(), &[]).unwrap();
+        assert!(feed.contains("<description>Links &amp; things</description>"));
+        assert!(feed.contains(r#"<atom:link href="https://example.com/feed.xml" rel="self""#));
+    }
+
+    proptest! {
+        #[test]
+        fn every_entry_is_an_item(entries in prop::collection::vec(entry_strategy(), 0..4)) {
+            let atom = render(FeedFormat::Atom, &channel(), &entries).unwrap();
+            let rss = render(FeedFormat::Rss, &channel(), &entries).unwrap();
+            prop_assert_eq!(atom.matches("<entry>").count(), entries.len());
+            prop_assert_eq!(rss.matches("<item>").count(), entries.len());
+            let feed: Value = serde_json::from_str(
+                &render(FeedFormat::Json, &channel(), &entries).unwrap(),
+            )
+            .unwrap();
+            prop_assert_eq!(&feed["version"], "https://jsonfeed.org/version/1.1");
+            let items = feed["items"].as_array().unwrap();
+            prop_assert_eq!(items.len(), entries.len());
+            for (item, entry) in items.iter().zip(&entries) {
+                prop_assert_eq!(&item["id"], &entry.id().to_string());
+                prop_assert!(item.get("content_html").or(item.get("content_text")).is_some());
+            }
+        }
+    }
+}
@@ -0,0 +1,583 @@
+//! Conversion of extracted article HTML to lightweight markup.
+
+use std::fmt::Write;
+
+use ego_tree::NodeRef;
+use scraper::{Html, Node, node::Element};
+
+/// Markup languages article text can be converted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Dialect {
+    Markdown,
+    Org,
+}
+
+/// Elements that start a new block; everything else is treated as inline.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "body",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "html",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+/// Elements dropped with their contents.
+const DROPPED_ELEMENTS: &[&str] = &[
+    "script", "noscript", "template", "style", "head", "iframe", "svg", "math", "form", "button",
+];
+
+/// Convert HTML to Markdown or Org markup. Headings keep their levels, so
+/// callers nesting the result under a heading of their own should pass
+/// `depth` to push them down.
+pub(crate) fn convert(html: &str, dialect: Dialect, depth: usize) -> String {
+    let fragment = Html::parse_fragment(html);
+    let converter = Converter { dialect, depth };
+    converter.block(fragment.root_element().children())
+}
+
+struct Converter {
+    dialect: Dialect,
+    depth: usize,
+}
+
+impl Converter {
+    /// Convert a run of sibling nodes, separating blocks with blank lines.
+    fn block<'a>(&self, nodes: impl Iterator<Item = NodeRef<'a, Node>>) -> String {
+        let mut blocks: Vec<String> = Vec::new();
+        let mut inline = String::new();
+        let flush = |inline: &mut String, blocks: &mut Vec<String>| {
+            let paragraph = inline
+                .lines()
+                .map(|line| self.escape_line_start(line.trim()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !paragraph.trim().is_empty() {
+                blocks.push(paragraph.trim().to_owned());
+            }
+            inline.clear();
+        };
+        for node in nodes {
+            match node.value() {
+                Node::Element(el) if BLOCK_ELEMENTS.contains(&el.name()) => {
+                    flush(&mut inline, &mut blocks);
+                    let block = self.block_element(node, el);
+                    if !block.trim().is_empty() {
+                        blocks.push(block);
+                    }
+                }
+                _ => inline.push_str(&self.inline(node)),
+            }
+        }
+        flush(&mut inline, &mut blocks);
+        blocks.join("\n\n")
+    }
+
+    /// Escape a line of text that would otherwise start a heading, list,
+    /// quote, rule or other block. Org has no escape character, so a zero
+    /// width space goes in front of the line instead.
+    fn escape_line_start(&self, line: &str) -> String {
+        match self.dialect {
+            Dialect::Markdown => match markdown_block_marker(line) {
+                Some(at) => format!("{}\\{}", &line[..at], &line[at..]),
+                None => line.to_owned(),
+            },
+            Dialect::Org if starts_org_block(line) => format!("\u{200B}{line}"),
+            Dialect::Org => line.to_owned(),
+        }
+    }
+
+    fn block_element(&self, node: NodeRef<Node>, el: &Element) -> String {
+        let markdown = self.dialect == Dialect::Markdown;
+        match el.name() {
+            name @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                let level = usize::from(name.as_bytes()[1] - b'0') + self.depth;
+                let marker = if markdown { "#" } else { "*" };
+                let text = self.inline_children(node);
+                format!("{} {}", marker.repeat(level.min(6)), collapse(&text))
+            }
+            "hr" => if markdown { "---" } else { "-----" }.to_owned(),
+            "pre" => {
+                let text = node_text(node);
+                let language = node
+                    .descendants()
+                    .filter_map(|n| n.value().as_element())
+                    .flat_map(|e| e.classes())
+                    .find_map(|c| c.strip_prefix("language-"))
+                    .unwrap_or_default();
+                let text = text.trim_end_matches('\n');
+                if markdown {
+                    let fence = if text.contains("```") { "~~~" } else { "```" };
+                    format!("{fence}{language}\n{text}\n{fence}")
+                } else if language.is_empty() {
+                    format!("#+BEGIN_EXAMPLE\n{text}\n#+END_EXAMPLE")
+                } else {
+                    format!("#+BEGIN_SRC {language}\n{text}\n#+END_SRC")
+                }
+            }
+            "blockquote" => {
+                let inner = self.block(node.children());
+                if markdown {
+                    prefix_lines(&inner, "> ", ">")
+                } else {
+                    format!("#+BEGIN_QUOTE\n{inner}\n#+END_QUOTE")
+                }
+            }
+            "ul" | "ol" => self.list(node, el),
+            "table" => self.table(node),
+            "dt" => {
+                let text = collapse(&self.inline_children(node));
+                if markdown {
+                    format!("**{text}**")
+                } else {
+                    format!("*{text}*")
+                }
+            }
+            _ => self.block(node.children()),
+        }
+    }
+
+    fn list(&self, node: NodeRef<Node>, el: &Element) -> String {
+        let ordered = el.name() == "ol";
+        let mut number: usize = el.attr("start").and_then(|s| s.parse().ok()).unwrap_or(1);
+        let mut items = Vec::new();
+        for item in node.children() {
+            let Some(li) = item.value().as_element() else {
+                continue;
+            };
+            if li.name() != "li" {
+                continue;
+            }
+            let marker = if ordered {
+                let marker = format!("{number}. ");
+                number += 1;
+                marker
+            } else {
+                "- ".to_owned()
+            };
+            let indent = " ".repeat(marker.len());
+            let body = self.block(item.children());
+            // Tight lists read better and keep Org from splitting the list.
+            let body = body.replace("\n\n", "\n");
+            items.push(format!("{marker}{}", prefix_rest(&body, &indent)));
+        }
+        items.join("\n")
+    }
+
+    fn table(&self, node: NodeRef<Node>) -> String {
+        let rows: Vec<Vec<String>> = node
+            .descendants()
+            .filter(|n| n.value().as_element().is_some_and(|e| e.name() == "tr"))
+            .map(|row| {
+                row.children()
+                    .filter(|c| {
+                        c.value()
+                            .as_element()
+                            .is_some_and(|e| matches!(e.name(), "td" | "th"))
+                    })
+                    .map(|cell| collapse(&self.inline_children(cell)).replace('|', "\\|"))
+                    .collect()
+            })
+            .filter(|cells: &Vec<String>| !cells.is_empty())
+            .collect();
+        let Some(width) = rows.iter().map(Vec::len).max() else {
+            return String::new();
+        };
+        let mut out = String::new();
+        for (i, row) in rows.iter().enumerate() {
+            out.push('|');
+            for n in 0..width {
+                let _ = write!(out, " {} |", row.get(n).map_or("", String::as_str));
+            }
+            out.push('\n');
+            if i == 0 {
+                let rule = match self.dialect {
+                    Dialect::Markdown => format!("|{}", " --- |".repeat(width)),
+                    Dialect::Org => format!("|{}|", vec!["---"; width].join("+")),
+                };
+                out.push_str(&rule);
+                out.push('\n');
+            }
+        }
+        out.pop();
+        out
+    }
+
+    fn inline_children(&self, node: NodeRef<Node>) -> String {
+        node.children().map(|c| self.inline(c)).collect()
+    }
+
+    fn inline(&self, node: NodeRef<Node>) -> String {
+        let markdown = self.dialect == Dialect::Markdown;
+        match node.value() {
+            Node::Text(t) => {
+                let text = collapse_keeping_edges(t);
+                if markdown { escape(&text) } else { text }
+            }
+            Node::Element(el) => {
+                let name = el.name();
+                if DROPPED_ELEMENTS.contains(&name) {
+                    return String::new();
+                }
+                if BLOCK_ELEMENTS.contains(&name) {
+                    // Blocks nested in inline elements become their own lines.
+                    return format!("\n{}\n", self.block_element(node, el));
+                }
+                let inner = || self.inline_children(node);
+                match name {
+                    "br" => if markdown { "\\\n" } else { "\\\\\n" }.to_owned(),
+                    "strong" | "b" => wrap(&inner(), if markdown { "**" } else { "*" }),
+                    "em" | "i" | "cite" => wrap(&inner(), if markdown { "*" } else { "/" }),
+                    "del" | "s" | "strike" => wrap(&inner(), if markdown { "~~" } else { "+" }),
+                    "code" | "kbd" | "samp" => {
+                        let text = node_text(node);
+                        if markdown {
+                            let fence = if text.contains('`') { "``" } else { "`" };
+                            wrap(&text, fence)
+                        } else {
+                            wrap(&text, "~")
+                        }
+                    }
+                    "a" => {
+                        let text = inner();
+                        match el.attr("href").map(str::trim) {
+                            Some(href) if !href.is_empty() && !href.starts_with("javascript:") => {
+                                let text = text.trim();
+                                match (markdown, text.is_empty()) {
+                                    (true, true) => format!("<{}>", link_target(href)),
+                                    (true, false) => format!("[{text}]({})", link_target(href)),
+                                    (false, true) => format!("[[{href}]]"),
+                                    (false, false) => format!("[[{href}][{text}]]"),
+                                }
+                            }
+                            _ => text,
+                        }
+                    }
+                    "img" => match el.attr("src").map(str::trim) {
+                        Some(src) if !src.is_empty() && !src.starts_with("data:") => {
+                            let alt = collapse(el.attr("alt").unwrap_or_default());
+                            if markdown {
+                                format!("![{}]({})", escape(&alt), link_target(src))
+                            } else {
+                                format!("[[{src}]]")
+                            }
+                        }
+                        _ => String::new(),
+                    },
+                    _ => inner(),
+                }
+            }
+            _ => String::new(),
+        }
+    }
+}
+
+/// All text below a node, verbatim.
+fn node_text(node: NodeRef<Node>) -> String {
+    node.descendants()
+        .filter_map(|n| n.value().as_text())
+        .map(|t| &**t)
+        .collect()
+}
+
+/// Collapse runs of whitespace into single spaces and trim the ends.
+fn collapse(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Collapse runs of whitespace, keeping a single space at either end so
+/// words either side of inline markup stay apart.
+fn collapse_keeping_edges(s: &str) -> String {
+    let mut out = collapse(s);
+    if out.is_empty() {
+        return if s.is_empty() { out } else { " ".to_owned() };
+    }
+    if s.starts_with(char::is_whitespace) {
+        out.insert(0, ' ');
+    }
+    if s.ends_with(char::is_whitespace) {
+        out.push(' ');
+    }
+    out
+}
+
+/// Wrap inline content in emphasis markers, keeping surrounding spaces
+/// outside them as both dialects require.
+fn wrap(inner: &str, marker: &str) -> String {
+    let trimmed = inner.trim();
+    if trimmed.is_empty() {
+        return inner.to_owned();
+    }
+    let lead = if inner.starts_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    let trail = if inner.ends_with(char::is_whitespace) {
+        " "
+    } else {
+        ""
+    };
+    format!("{lead}{marker}{trimmed}{marker}{trail}")
+}
+
+/// Escape characters Markdown would read as markup.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        // `<` and `&` would otherwise start raw HTML or character references.
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<' | '&') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A URL as a Markdown link destination, with the characters that would end
+/// it early percent-encoded.
+fn link_target(url: &str) -> String {
+    let mut out = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            ' ' => out.push_str("%20"),
+            '(' => out.push_str("%28"),
+            ')' => out.push_str("%29"),
+            '<' => out.push_str("%3C"),
+            '>' => out.push_str("%3E"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Whether `rest`, following a marker, makes it one: a space or nothing.
+fn is_gap(rest: &str) -> bool {
+    rest.is_empty() || rest.starts_with(char::is_whitespace)
+}
+
+/// The length of a list number such as `12.` or `3)` at the start of a
+/// line, up to the punctuation.
+fn list_number(line: &str) -> Option<usize> {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let rest = &line[digits..];
+    ((1..=9).contains(&digits) && rest.starts_with(['.', ')']) && is_gap(&rest[1..]))
+        .then_some(digits)
+}
+
+/// Where a backslash must go to stop a line of Markdown text being read as
+/// a heading, list item, quote or rule, if anywhere.
+fn markdown_block_marker(line: &str) -> Option<usize> {
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if (1..=6).contains(&hashes) && is_gap(&line[hashes..])
+        || line.starts_with(['-', '+']) && is_gap(&line[1..])
+        || line.starts_with('>')
+        || !line.is_empty() && (line.chars().all(|c| c == '-') || line.chars().all(|c| c == '='))
+    {
+        return Some(0);
+    }
+    list_number(line)
+}
+
+/// Whether a line of Org text would be read as a heading, list item,
+/// keyword, comment, table, fixed-width line or rule.
+fn starts_org_block(line: &str) -> bool {
+    let stars = line.len() - line.trim_start_matches('*').len();
+    stars > 0 && is_gap(&line[stars..])
+        || line.starts_with(['-', '+']) && is_gap(&line[1..])
+        || list_number(line).is_some()
+        || line.starts_with("#+")
+        || line.starts_with('#') && is_gap(&line[1..])
+        || line.starts_with('|')
+        || line.starts_with(':') && is_gap(&line[1..])
+        || line.len() >= 5 && line.chars().all(|c| c == '-')
+}
+
+fn prefix_lines(s: &str, prefix: &str, blank: &str) -> String {
+    s.lines()
+        .map(|l| {
+            if l.is_empty() {
+                blank.to_owned()
+            } else {
+                format!("{prefix}{l}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Indent every line but the first.
+fn prefix_rest(s: &str, indent: &str) -> String {
+    let mut lines = s.lines();
+    let mut out = lines.next().unwrap_or_default().to_owned();
+    for line in lines {
+        out.push('\n');
+        if !line.is_empty() {
+            out.push_str(indent);
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = r#"<div><h2>Intro</h2><p>Some <strong>bold</strong> and <em>italic</em>
+        text with <a href="https://example.com/">a link</a> and <code>x*y</code>.<br>Next line.</p>
+        <ul><li>One</li><li>Two<ol start="3"><li>Three</li></ol></li></ul>
+        <blockquote><p>Quoted</p><p>Twice</p></blockquote>
+        <pre><code class="language-rust">fn main() {}
+</code></pre><img src="https://example.com/a.png" alt="A picture">
+        <table><tr><th>Name</th><th>Value</th></tr><tr><td>a</td><td>1</td></tr></table>
+        <script>alert(1)</script></div>"#;
+
+    #[test]
+    fn converts_to_markdown() {
+        assert_eq!(
+            convert(ARTICLE, Dialect::Markdown, 0),
+            r#"## Intro
+
+Some **bold** and *italic* text with [a link](https://example.com/) and `x*y`.\
+Next line.
+
+- One
+- Two
+  3. Three
+
+> Quoted
+>
+> Twice
+
+```rust
+fn main() {}
+```
+
+![A picture](https://example.com/a.png)
+
+| Name | Value |
+| --- | --- |
+| a | 1 |"#
+        );
+    }
+
+    #[test]
+    fn converts_to_org() {
+        assert_eq!(
+            convert(ARTICLE, Dialect::Org, 1),
+            r#"*** Intro
+
+Some *bold* and /italic/ text with [[https://example.com/][a link]] and ~x*y~.\\
+Next line.
+
+- One
+- Two
+  3. Three
+
+#+BEGIN_QUOTE
+Quoted
+
+Twice
+#+END_QUOTE
+
+#+BEGIN_SRC rust
+fn main() {}
+#+END_SRC
+
+[[https://example.com/a.png]]
+
+| Name | Value |
+|---+---|
+| a | 1 |"#
+        );
+    }
+
+    #[test]
+    fn escapes_markdown_in_text() {
+        assert_eq!(
+            convert("<p>snake_case [sic] *not* bold</p>", Dialect::Markdown, 0),
+            r"snake\_case \[sic\] \*not\* bold"
+        );
+        assert_eq!(
+            convert(
+                r#"<p>&lt;script&gt; &amp;amp; <a href="https://e.com/a_(b) c">w</a></p>"#,
+                Dialect::Markdown,
+                0
+            ),
+            r"\<script> \&amp; [w](https://e.com/a_%28b%29%20c)"
+        );
+    }
+
+    const BLOCK_LOOKALIKES: &str = "<p># not a heading</p><p>- not a list<br>+ nor this</p>\
+        <p>1984. Not a list either</p><p>> not quoted</p><p>---</p><p>#hashtag - fine</p>";
+
+    #[test]
+    fn escapes_markdown_block_markers_at_line_start() {
+        assert_eq!(
+            convert(BLOCK_LOOKALIKES, Dialect::Markdown, 0),
+            r"\# not a heading
+
+\- not a list\
+\+ nor this
+
+1984\. Not a list either
+
+\> not quoted
+
+\---
+
+#hashtag - fine"
+        );
+    }
+
+    #[test]
+    fn escapes_org_block_markers_at_line_start() {
+        let org = convert(
+            &format!("{BLOCK_LOOKALIKES}<p>* not a heading</p><p>#+TITLE: x</p><p>*bold*</p>"),
+            Dialect::Org,
+            0,
+        );
+        let escaped: Vec<&str> = org
+            .lines()
+            .filter_map(|l| l.strip_prefix('\u{200B}'))
+            .collect();
+        assert_eq!(
+            escaped,
+            [
+                "# not a heading",
+                r"- not a list\\",
+                "+ nor this",
+                "1984. Not a list either",
+                "* not a heading",
+                "#+TITLE: x"
+            ]
+        );
+        assert!(org.lines().any(|l| l == "*bold*"));
+    }
+}
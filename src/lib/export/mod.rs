@@ -0,0 +1,664 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Datelike, Utc};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+use crate::{
+    entry::{Entry, EntryView},
+    text::{self, slugify},
+};
+
+pub mod bibtex;
+pub mod csl_json;
+pub mod epub;
+pub mod feed;
+pub(crate) mod markup;
+pub mod netscape;
+pub mod notes;
+pub mod ris;
+pub(crate) mod table;
+pub(crate) mod yaml;
+
+/// Built-in output formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// One JSON object per entry.
+    #[default]
+    Json,
+    /// BibTeX/BibLaTeX records.
+    Bibtex,
+    /// RIS records, as read by Zotero, EndNote and Mendeley.
+    Ris,
+    /// A CSL-JSON array, as read by Pandoc and citeproc.
+    CslJson,
+    /// A Netscape bookmark file that browsers can import.
+    Netscape,
+    /// Markdown notes with YAML front matter, e.g. for Obsidian.
+    Markdown,
+    /// Org mode headings with property drawers.
+    Org,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Tab-separated values with a header row.
+    Tsv,
+}
+
+impl Format {
+    /// File extension for documents in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Json | Format::CslJson => "json",
+            Format::Bibtex => "bib",
+            Format::Ris => "ris",
+            Format::Netscape => "html",
+            Format::Markdown => "md",
+            Format::Org => "org",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+        }
+    }
+}
+
+/// How files are named when each entry is written to its own file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Naming {
+    /// A slug of the title, e.g. `rust-in-the-kernel`.
+    #[default]
+    Slug,
+    /// The publication (or bookmarking) date then the slug, e.g.
+    /// `2025-01-31-rust-in-the-kernel`.
+    DateSlug,
+    /// The entry's ID.
+    Id,
+}
+
+/// File name (without extension) for an entry.
+pub fn file_stem(entry: &Entry, naming: Naming) -> String {
+    let slug = || {
+        let slug = slugify(entry.title());
+        if slug.is_empty() {
+            entry.id().to_string()
+        } else {
+            slug
+        }
+    };
+    match naming {
+        Naming::Slug => slug(),
+        Naming::DateSlug => {
+            let date = entry
+                .published()
+                .or(entry.added())
+                .copied()
+                .unwrap_or_else(Utc::now);
+            format!("{}-{}", date.format("%Y-%m-%d"), slug())
+        }
+        Naming::Id => entry.id().to_string(),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialise entries: {0}")]
+    Serialise(#[from] serde_json::Error),
+    #[error("Failed to write output: {0}")]
+    Write(#[from] std::fmt::Error),
+    #[error("Unknown field {0:?}; expected one of {fields}", fields = EntryView::FIELDS.join(", "))]
+    UnknownField(String),
+}
+
+/// Settings for formats built on `EntryView` fields.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Fields to include, in order. Nested values can be picked with a dotted
+    /// path such as `citation.doi`. Empty means every field.
+    pub fields: Vec<String>,
+    /// Fields (or dotted paths) to leave out.
+    pub exclude: Vec<String>,
+    /// Joins list values, such as authors, that share a cell.
+    pub separator: String,
+    /// Start tables with a row of field names.
+    pub header: bool,
+    /// Indent JSON objects rather than writing one per line.
+    pub pretty: bool,
+    /// Write the raw `meta` of entries extracted with it. Naming `meta` in
+    /// `fields` also writes it.
+    pub raw_meta: bool,
+    /// Write the article text. Unlike excluding `full_text`, leaving it out
+    /// this way keeps JSON in the stored form.
+    pub full_text: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            fields: Vec::new(),
+            exclude: Vec::new(),
+            separator: "; ".to_owned(),
+            header: true,
+            pretty: false,
+            raw_meta: false,
+            full_text: true,
+        }
+    }
+}
+
+impl Options {
+    /// Whether fields were selected, so JSON should be written from
+    /// `EntryView` rather than the stored form.
+    fn projects(&self) -> bool {
+        !self.fields.is_empty() || !self.exclude.is_empty()
+    }
+
+    /// The selected fields less excluded ones, checked against `EntryView`'s.
+    fn fields(&self) -> Result<Vec<&str>, Error> {
+        fn check(f: &str) -> Result<&str, Error> {
+            let top = f.split('.').next().unwrap_or_default();
+            if EntryView::FIELDS.contains(&top) {
+                Ok(f)
+            } else {
+                Err(Error::UnknownField(f.to_owned()))
+            }
+        }
+        self.exclude.iter().try_for_each(|f| check(f).map(drop))?;
+        let fields: Vec<&str> = if self.fields.is_empty() {
+            EntryView::FIELDS
+                .iter()
+                .copied()
+                .filter(|&f| f != "meta" || self.raw_meta)
+                .collect()
+        } else {
+            self.fields
+                .iter()
+                .map(|f| check(f))
+                .collect::<Result<_, _>>()?
+        };
+        Ok(fields
+            .into_iter()
+            .filter(|f| !self.exclude.iter().any(|e| e == f))
+            .filter(|&f| f != "full_text" || self.full_text)
+            .collect())
+    }
+
+    /// The entry's view with only the selected fields. Dotted paths keep
+    /// their nesting, so `citation.doi` gives `{"citation": {"doi": ...}}`.
+    fn project(&self, entry: &Entry) -> Result<Value, Error> {
+        let view = serde_json::to_value(EntryView::from(entry))?;
+        let mut out = Value::Object(Map::new());
+        for path in self.fields()? {
+            if let Some(value) = field(&view, path) {
+                insert(&mut out, path, value.clone());
+            }
+        }
+        for path in &self.exclude {
+            remove(&mut out, path);
+        }
+        Ok(out)
+    }
+}
+
+/// Look up a dotted path in a serialised view.
+fn field<'v>(view: &'v Value, path: &str) -> Option<&'v Value> {
+    path.split('.').try_fold(view, |value, key| value.get(key))
+}
+
+fn insert(target: &mut Value, path: &str, value: Value) {
+    match path.split_once('.') {
+        None => {
+            if let Value::Object(map) = target {
+                map.insert(path.to_owned(), value);
+            }
+        }
+        Some((head, rest)) => {
+            if let Value::Object(map) = target {
+                let child = map.entry(head).or_insert_with(|| Value::Object(Map::new()));
+                insert(child, rest, value);
+            }
+        }
+    }
+}
+
+fn remove(target: &mut Value, path: &str) {
+    let Value::Object(map) = target else {
+        return;
+    };
+    match path.split_once('.') {
+        None => {
+            map.shift_remove(path);
+        }
+        Some((head, rest)) => {
+            if let Some(child) = map.get_mut(head) {
+                remove(child, rest);
+            }
+        }
+    }
+}
+
+/// One JSON object per entry: the stored form, which `--input` reads back,
+/// unless fields were selected.
+fn json(entries: &[Entry], options: &Options) -> Result<String, Error> {
+    let mut lines = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let value = if options.projects() {
+            options.project(entry)?
+        } else {
+            let mut value = serde_json::to_value(entry)?;
+            if !options.raw_meta {
+                remove(&mut value, "meta");
+            }
+            if !options.full_text {
+                remove(&mut value, "full_text");
+            }
+            value
+        };
+        lines.push(if options.pretty {
+            serde_json::to_string_pretty(&value)?
+        } else {
+            serde_json::to_string(&value)?
+        });
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Render a set of entries in the given format.
+pub fn render(format: Format, entries: &[Entry], options: &Options) -> Result<String, Error> {
+    match format {
+        Format::Json => json(entries, options),
+        Format::Bibtex => Ok(bibtex::render(entries)?),
+        Format::Ris => Ok(ris::render(entries)?),
+        Format::CslJson => csl_json::render(entries),
+        Format::Netscape => Ok(netscape::render(entries)?),
+        Format::Markdown => notes::markdown(entries),
+        Format::Org => notes::org(entries),
+        Format::Csv => table::render(entries, options, table::Delimiter::Comma),
+        Format::Tsv => table::render(entries, options, table::Delimiter::Tab),
+    }
+}
+
+/// Escape text for XML; unlike HTML, only the predefined entities exist.
+pub(crate) fn xml_escape(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' if !attr => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            // Control characters are not allowed in XML at all.
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// What kind of publication an entry describes, derived from its citation metadata.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Article,
+    Proceedings,
+    Webpage,
+}
+
+impl Kind {
+    pub(crate) fn of(entry: &Entry) -> Self {
+        match entry.citation() {
+            Some(c) if c.journal.is_some() => Kind::Article,
+            Some(c) if c.conference.is_some() => Kind::Proceedings,
+            _ => Kind::Webpage,
+        }
+    }
+}
+
+/// A personal name split into family and given parts.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Name<'a> {
+    pub(crate) family: &'a str,
+    pub(crate) given: Option<&'a str>,
+}
+
+impl<'a> Name<'a> {
+    /// Split `Family, Given` or `Given Family`. Single-word names and
+    /// organisations are returned whole, as a family name only.
+    pub(crate) fn parse(raw: &'a str) -> Self {
+        let raw = raw.trim();
+        if is_organisation(raw) {
+            return Name {
+                family: raw,
+                given: None,
+            };
+        }
+        if let Some((family, given)) = raw.split_once(',') {
+            let given = given.trim();
+            return Name {
+                family: family.trim(),
+                given: (!given.is_empty()).then_some(given),
+            };
+        }
+        match raw.rsplit_once(char::is_whitespace) {
+            Some((given, family)) => Name {
+                family: family.trim(),
+                given: Some(given.trim()),
+            },
+            None => Name {
+                family: raw,
+                given: None,
+            },
+        }
+    }
+}
+
+/// Words that mark an author as an organisation rather than a person.
+const ORGANISATION_WORDS: &[&str] = &[
+    "agency",
+    "association",
+    "committee",
+    "company",
+    "corp",
+    "corporation",
+    "council",
+    "department",
+    "foundation",
+    "gmbh",
+    "group",
+    "inc",
+    "institute",
+    "llc",
+    "ltd",
+    "ministry",
+    "organisation",
+    "organization",
+    "project",
+    "society",
+    "team",
+    "university",
+];
+
+/// Whether a name reads as an organisation: it joins several parties
+/// (`Procter and Gamble`, `AT&T`), contains digits, or uses a word such as
+/// `Inc` or `Foundation`.
+fn is_organisation(name: &str) -> bool {
+    name.contains('&')
+        || name.chars().any(|c| c.is_ascii_digit())
+        || name
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .any(|w| w == "and" || ORGANISATION_WORDS.contains(&w.as_str()))
+}
+
+const KEY_STOPWORDS: &[&str] = &[
+    "a", "an", "and", "at", "for", "from", "in", "of", "on", "the", "to", "with",
+];
+
+/// Deterministic citation keys in the Google Scholar style
+/// (`turing1936computable`), disambiguated with `a`, `b`, ... suffixes when
+/// several entries in the same batch would collide.
+pub(crate) fn citation_keys(entries: &[Entry]) -> Vec<String> {
+    let bases: Vec<String> = entries.iter().map(base_key).collect();
+    let mut totals = HashMap::<&str, usize>::new();
+    for b in &bases {
+        *totals.entry(b).or_default() += 1;
+    }
+    // Unique bases keep their key as-is; reserve them before suffixing the rest.
+    let mut used: HashSet<String> = bases
+        .iter()
+        .filter(|b| totals[b.as_str()] == 1)
+        .cloned()
+        .collect();
+    let mut next = HashMap::<&str, usize>::new();
+    bases
+        .iter()
+        .map(|b| {
+            if totals[b.as_str()] == 1 {
+                return b.clone();
+            }
+            let n = next.entry(b).or_default();
+            loop {
+                let key = format!("{b}{}", key_suffix(*n));
+                *n += 1;
+                if used.insert(key.clone()) {
+                    return key;
+                }
+            }
+        })
+        .collect()
+}
+
+fn key_suffix(mut n: usize) -> String {
+    let mut out = Vec::new();
+    loop {
+        out.push(b'a' + (n % 26) as u8);
+        n /= 26;
+        if n == 0 {
+            break;
+        }
+        n -= 1;
+    }
+    out.reverse();
+    String::from_utf8(out).expect("suffix is ASCII")
+}
+
+fn base_key(entry: &Entry) -> String {
+    let who = entry
+        .authors()
+        .first()
+        .map(|a| key_part(Name::parse(a).family))
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            entry
+                .url()
+                .host_str()
+                .map(|h| key_part(h.trim_start_matches("www.")))
+        })
+        .unwrap_or_default();
+    let year = entry
+        .published()
+        .map(|d| d.year().to_string())
+        .unwrap_or_default();
+    let word = entry
+        .title()
+        .split(|c: char| !c.is_alphanumeric())
+        .map(key_part)
+        .find(|w| !w.is_empty() && !KEY_STOPWORDS.contains(&w.as_str()))
+        .unwrap_or_default();
+    let key = format!("{who}{year}{word}");
+    if key.is_empty() {
+        entry.id().simple().to_string()[..8].to_owned()
+    } else {
+        key
+    }
+}
+
+/// Lowercase letters and digits, with Latin accents folded to ASCII so
+/// `Gödel` keys as `godel`; letters of other scripts are kept.
+fn key_part(s: &str) -> String {
+    text::fold_ascii(s)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        entry::{Extraction, entry_strategy},
+        fetch::Live,
+        store,
+    };
+    use proptest::prelude::*;
+
+    #[test]
+    fn names_are_split() {
+        assert_eq!(
+            Name::parse("Turing, Alan M."),
+            Name {
+                family: "Turing",
+                given: Some("Alan M.")
+            }
+        );
+        assert_eq!(
+            Name::parse("Grace Brewster Hopper"),
+            Name {
+                family: "Hopper",
+                given: Some("Grace Brewster")
+            }
+        );
+        assert_eq!(
+            Name::parse("UNESCO"),
+            Name {
+                family: "UNESCO",
+                given: None
+            }
+        );
+        for organisation in [
+            "Procter and Gamble",
+            "Google, Inc.",
+            "AT&T",
+            "The Rust Project",
+        ] {
+            assert_eq!(
+                Name::parse(organisation),
+                Name {
+                    family: organisation,
+                    given: None
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn key_parts_fold_accents() {
+        assert_eq!(key_part("Gödel"), "godel");
+        assert_eq!(key_part("Erdős-Rényi"), "erdosrenyi");
+        assert_eq!(key_part("湯川"), "湯川");
+    }
+
+    #[test]
+    fn key_suffixes_continue_past_z() {
+        assert_eq!(key_suffix(0), "a");
+        assert_eq!(key_suffix(25), "z");
+        assert_eq!(key_suffix(26), "aa");
+    }
+
+    proptest! {
+        #[test]
+        fn citation_keys_are_unique_and_stable(entries in prop::collection::vec(entry_strategy(), 1..8)) {
+            let keys = citation_keys(&entries);
+            let mut deduped = keys.clone();
+            deduped.sort();
+            deduped.dedup();
+            prop_assert_eq!(deduped.len(), keys.len());
+            prop_assert_eq!(keys, citation_keys(&entries));
+        }
+
+        #[test]
+        fn json_keeps_only_selected_fields(entry in entry_strategy()) {
+            let options = Options {
+                fields: vec!["title".into(), "url".into(), "citation".into()],
+                exclude: vec!["citation.doi".into()],
+                ..Options::default()
+            };
+            let json: Value = serde_json::from_str(&render(Format::Json, std::slice::from_ref(&entry), &options).unwrap()).unwrap();
+            let object = json.as_object().unwrap();
+            prop_assert_eq!(&object["title"], entry.title());
+            prop_assert_eq!(&object["url"], entry.url().as_str());
+            prop_assert!(object.keys().all(|k| ["title", "url", "citation"].contains(&k.as_str())));
+            prop_assert!(field(&json, "citation.doi").is_none());
+        }
+    }
+
+    #[test]
+    fn dotted_fields_keep_their_nesting() {
+        let mut out = Value::Object(Map::new());
+        insert(&mut out, "citation.doi", "10.1/x".into());
+        insert(&mut out, "citation.volume", "3".into());
+        insert(&mut out, "title", "T".into());
+        assert_eq!(
+            out.to_string(),
+            r#"{"citation":{"doi":"10.1/x","volume":"3"},"title":"T"}"#
+        );
+        remove(&mut out, "citation.doi");
+        remove(&mut out, "missing.path");
+        assert_eq!(
+            out.to_string(),
+            r#"{"citation":{"volume":"3"},"title":"T"}"#
+        );
+    }
+
+    #[test]
+    fn raw_meta_is_written_only_when_asked_for() {
+        let url = url::Url::parse("https://example.com/clip").unwrap();
+        let html =
+            r#"<head><meta property="og:video" content="https://example.com/clip.mp4"></head>"#;
+        let extraction = Extraction {
+            raw_meta: true,
+            ..Extraction::METADATA_ONLY
+        };
+        let entries = [Entry::extract(&Live, &url, html, None, extraction)];
+        let json = |options: &Options| -> Value {
+            serde_json::from_str(&render(Format::Json, &entries, options).unwrap()).unwrap()
+        };
+        let fields = |fields: &[&str], raw_meta| Options {
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            raw_meta,
+            ..Options::default()
+        };
+        assert!(json(&Options::default()).get("meta").is_none());
+        assert!(json(&fields(&["title"], true)).get("meta").is_none());
+        let raw = Options {
+            raw_meta: true,
+            ..Options::default()
+        };
+        for options in [raw, fields(&["title", "meta"], false)] {
+            assert_eq!(
+                json(&options)["meta"]["properties"]["og:video"],
+                "https://example.com/clip.mp4"
+            );
+        }
+        let csv = render(Format::Csv, &entries, &Options::default()).unwrap();
+        assert!(!csv.contains("meta"), "{csv}");
+    }
+
+    proptest! {
+        #[test]
+        fn leaving_out_full_text_keeps_the_stored_form(entry in entry_strategy()) {
+            let options = Options {
+                full_text: false,
+                ..Options::default()
+            };
+            let entries = std::slice::from_ref(&entry);
+            let json: Value = serde_json::from_str(&render(Format::Json, entries, &options).unwrap()).unwrap();
+            let mut stored = serde_json::to_value(&entry).unwrap();
+            stored.as_object_mut().unwrap().shift_remove("full_text");
+            prop_assert_eq!(&json, &stored);
+            let written = render(Format::Json, entries, &options).unwrap();
+            let read = store::read_entries(written.as_bytes(), Path::new("-")).unwrap();
+            prop_assert_eq!(read.len(), 1);
+            prop_assert_eq!(read[0].full_text(), "");
+            prop_assert_eq!(read[0].tags(), entry.tags());
+            let mut read = serde_json::to_value(&read[0]).unwrap();
+            for value in [&mut read, &mut stored] {
+                let object = value.as_object_mut().unwrap();
+                object.shift_remove("full_text");
+                object.shift_remove("tags");
+            }
+            prop_assert_eq!(read, stored);
+            let csv = render(Format::Csv, entries, &options).unwrap();
+            prop_assert!(!csv.lines().next().unwrap().contains("full_text"));
+        }
+    }
+
+    #[test]
+    fn excluded_fields_must_exist() {
+        let options = Options {
+            exclude: vec!["fulltext".into()],
+            ..Options::default()
+        };
+        assert!(matches!(options.fields(), Err(Error::UnknownField(f)) if f == "fulltext"));
+    }
+}
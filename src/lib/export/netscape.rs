@@ -0,0 +1,122 @@
+use std::fmt::Write;
+
+use crate::entry::Entry;
+
+const HEADER: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<!-- This is an automatically generated file.
+     It will be read and overwritten.
+     DO NOT EDIT! -->
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+"#;
+
+/// A folder and its contents, in the order they were first seen.
+#[derive(Default)]
+struct Folder<'a> {
+    name: &'a str,
+    entries: Vec<&'a Entry>,
+    children: Vec<Folder<'a>>,
+}
+
+impl<'a> Folder<'a> {
+    fn insert(&mut self, path: &'a [String], entry: &'a Entry) {
+        match path.split_first() {
+            None => self.entries.push(entry),
+            Some((head, rest)) => {
+                let idx = match self.children.iter().position(|c| c.name == head) {
+                    Some(idx) => idx,
+                    None => {
+                        self.children.push(Folder {
+                            name: head,
+                            ..Folder::default()
+                        });
+                        self.children.len() - 1
+                    }
+                };
+                self.children[idx].insert(rest, entry);
+            }
+        }
+    }
+}
+
+/// Render entries as a Netscape bookmark file that browsers can import,
+/// rebuilding folders from each entry's folder path.
+pub fn render(entries: &[Entry]) -> Result<String, std::fmt::Error> {
+    let mut root = Folder::default();
+    for entry in entries {
+        root.insert(entry.folder(), entry);
+    }
+    let mut out = String::from(HEADER);
+    write_folder(&mut out, &root, 0)?;
+    Ok(out)
+}
+
+fn write_folder(out: &mut impl Write, folder: &Folder, depth: usize) -> std::fmt::Result {
+    let indent = "    ".repeat(depth);
+    writeln!(out, "{indent}<DL><p>")?;
+    for entry in &folder.entries {
+        write!(
+            out,
+            r#"{indent}    <DT><A HREF="{}""#,
+            escape(entry.url().as_str())
+        )?;
+        if let Some(added) = entry.added() {
+            write!(out, r#" ADD_DATE="{}""#, added.timestamp())?;
+        }
+        let tags = entry.tags();
+        if !tags.is_empty() {
+            let tags: Vec<&str> = tags.iter().map(|t| t.as_str()).collect();
+            write!(out, r#" TAGS="{}""#, escape(&tags.join(",")))?;
+        }
+        writeln!(out, ">{}</A>", escape(entry.title()))?;
+        if let Some(description) = entry.description() {
+            writeln!(out, "{indent}    <DD>{}", escape(description))?;
+        }
+    }
+    for child in &folder.children {
+        writeln!(out, "{indent}    <DT><H3>{}</H3>", escape(child.name))?;
+        write_folder(out, child, depth + 1)?;
+    }
+    writeln!(out, "{indent}</DL><p>")
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' | '\r' => out.push(' '),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entry::entry_strategy, import::netscape};
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn export_round_trips_through_import(entries in prop::collection::vec(entry_strategy(), 0..6)) {
+            let html = render(&entries).unwrap();
+            let mut parsed = netscape::parse(&html);
+            prop_assert_eq!(parsed.len(), entries.len());
+            for entry in &entries {
+                let idx = parsed
+                    .iter()
+                    .position(|b| &b.url == entry.url() && b.folder == entry.folder()
+                        && b.tags.iter().map(String::as_str).eq(entry.tags().iter().map(|t| t.as_str())))
+                    .expect("every exported entry is imported again");
+                let bookmark = parsed.remove(idx);
+                prop_assert_eq!(bookmark.added.map(|d| d.timestamp()), entry.added().map(|d| d.timestamp()));
+            }
+        }
+    }
+}
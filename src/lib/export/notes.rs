@@ -0,0 +1,135 @@
+//! Note files for Markdown-based tools such as Obsidian, and for Org mode.
+
+use std::fmt::Write;
+
+use serde_json::{Map, Value};
+
+use super::{
+    Error,
+    markup::{self, Dialect},
+    yaml,
+};
+use crate::entry::{Entry, EntryView};
+
+/// Render each entry as a Markdown note: YAML front matter with the entry's
+/// fields, then the article converted from `full_text`.
+pub fn markdown(entries: &[Entry]) -> Result<String, Error> {
+    let mut out = String::new();
+    for entry in entries {
+        let fields = front_matter(entry)?;
+        writeln!(out, "---\n{}---\n", yaml::to_string(&Value::Object(fields)))?;
+        writeln!(out, "# {}", entry.title().replace('\n', " "))?;
+        let body = markup::convert(entry.full_text(), Dialect::Markdown, 1);
+        if !body.is_empty() {
+            write!(out, "\n{body}\n")?;
+        }
+        // Without a blank line, the next `---` would underline a heading.
+        out.push('\n');
+    }
+    Ok(out.trim_end().to_owned())
+}
+
+/// Render each entry as an Org heading with its fields in a property drawer
+/// and tags on the headline, followed by the converted article.
+pub fn org(entries: &[Entry]) -> Result<String, Error> {
+    let mut out = String::new();
+    for entry in entries {
+        let fields = front_matter(entry)?;
+        write!(out, "* {}", entry.title().replace('\n', " "))?;
+        let tags: Vec<String> = entry
+            .tags()
+            .iter()
+            .map(|t| t.to_string().replace('-', "_"))
+            .collect();
+        if !tags.is_empty() {
+            write!(out, " :{}:", tags.join(":"))?;
+        }
+        out.push_str("\n:PROPERTIES:\n");
+        for (key, value) in fields {
+            if key == "title" || key == "tags" {
+                continue;
+            }
+            write_property(&mut out, &key.to_uppercase(), &value)?;
+        }
+        out.push_str(":END:\n");
+        let body = markup::convert(entry.full_text(), Dialect::Org, 1);
+        if !body.is_empty() {
+            write!(out, "\n{body}\n")?;
+        }
+        out.push('\n');
+    }
+    Ok(out.trim_end().to_owned())
+}
+
+/// The entry's `EntryView` fields, less the article body.
+fn front_matter(entry: &Entry) -> Result<Map<String, Value>, Error> {
+    let Value::Object(mut fields) = serde_json::to_value(EntryView::from(entry))? else {
+        unreachable!("entry views serialise to objects");
+    };
+    fields.remove("full_text");
+    Ok(fields)
+}
+
+/// Write a property, flattening objects into `PARENT_CHILD` keys and lists
+/// into one value, as drawers only hold single lines.
+fn write_property(out: &mut String, key: &str, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::Object(map) => {
+            for (child, value) in map {
+                write_property(out, &format!("{key}_{}", child.to_uppercase()), value)?;
+            }
+            Ok(())
+        }
+        Value::Array(items) => {
+            let separator = if key == "FOLDER" { "/" } else { ", " };
+            let items: Vec<String> = items.iter().map(scalar).collect();
+            writeln!(out, ":{key}: {}", items.join(separator))
+        }
+        Value::Null => Ok(()),
+        value => writeln!(out, ":{key}: {}", scalar(value)),
+    }
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.split_whitespace().collect::<Vec<_>>().join(" "),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::entry::entry_strategy;
+
+    proptest! {
+        #[test]
+        fn markdown_notes_start_with_front_matter(entry in entry_strategy()) {
+            let note = markdown(std::slice::from_ref(&entry)).unwrap();
+            let url = format!("url: {}", entry.url());
+            let id = format!("id: {}", entry.id());
+            prop_assert!(note.starts_with("---\n"));
+            prop_assert!(note.contains(&url));
+            prop_assert!(note.contains(&id));
+            let (front, body) = note[4..].split_once("\n---\n").unwrap();
+            prop_assert!(!front.contains("full_text"));
+            let heading = format!("\n# {}", entry.title());
+            prop_assert!(body.starts_with(&heading));
+        }
+
+        #[test]
+        fn org_properties_are_single_lines(entry in entry_strategy()) {
+            let note = org(std::slice::from_ref(&entry)).unwrap();
+            let (drawer, _) = note.split_once(":END:").unwrap();
+            let mut lines = drawer.lines();
+            let headline = format!("* {}", entry.title());
+            prop_assert!(lines.next().unwrap().starts_with(&headline));
+            prop_assert_eq!(lines.next(), Some(":PROPERTIES:"));
+            for line in lines {
+                prop_assert!(line.starts_with(':') && line[1..].contains(": "), "bad property {:?}", line);
+            }
+        }
+    }
+}
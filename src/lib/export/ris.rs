@@ -0,0 +1,86 @@
+use std::fmt::Write;
+
+use super::{Kind, citation_keys};
+use crate::entry::{Entry, collapse_ws};
+
+/// Render entries as RIS records.
+pub fn render(entries: &[Entry]) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+    for (entry, key) in entries.iter().zip(citation_keys(entries)) {
+        write_record(&mut out, entry, &key)?;
+    }
+    Ok(out)
+}
+
+fn write_record(out: &mut impl Write, entry: &Entry, key: &str) -> std::fmt::Result {
+    let kind = Kind::of(entry);
+    let ty = match kind {
+        Kind::Article => "JOUR",
+        Kind::Proceedings => "CPAPER",
+        Kind::Webpage => "ELEC",
+    };
+    tag(out, "TY", Some(ty))?;
+    tag(out, "ID", Some(key))?;
+    tag(out, "TI", Some(entry.title()))?;
+    for author in entry.authors() {
+        tag(out, "AU", Some(author))?;
+    }
+    let citation = entry.citation();
+    match kind {
+        Kind::Article => tag(out, "JO", citation.and_then(|c| c.journal.as_deref()))?,
+        Kind::Proceedings => tag(out, "T2", citation.and_then(|c| c.conference.as_deref()))?,
+        Kind::Webpage => tag(out, "T2", Some(entry.site()))?,
+    }
+    if let Some(date) = entry.published_date() {
+        tag(out, "PY", Some(&format!("{:04}", date.year)))?;
+        // Unknown parts are left empty, keeping the slashes: `1936//`.
+        let part = |p: Option<u32>| p.map_or(String::new(), |p| format!("{p:02}"));
+        let da = format!("{:04}/{}/{}", date.year, part(date.month), part(date.day));
+        tag(out, "DA", Some(&da))?;
+    }
+    if let Some(c) = citation {
+        tag(out, "VL", c.volume.as_deref())?;
+        tag(out, "IS", c.issue.as_deref())?;
+        if let Some(pages) = c.pages.as_deref() {
+            let (start, end) = pages.split_once('-').unwrap_or((pages, ""));
+            tag(out, "SP", Some(start))?;
+            tag(out, "EP", Some(end))?;
+        }
+        tag(out, "PB", c.publisher.as_deref())?;
+        tag(out, "SN", c.issn.as_deref())?;
+        tag(out, "DO", c.doi.as_deref())?;
+        tag(out, "L1", c.pdf_url.as_deref())?;
+    }
+    tag(out, "UR", Some(entry.url().as_str()))?;
+    tag(out, "AB", entry.description())?;
+    writeln!(out, "ER  - ")
+}
+
+/// RIS has no escaping: a value simply runs to the end of the line, so any
+/// embedded line breaks are collapsed.
+fn tag(out: &mut impl Write, name: &str, value: Option<&str>) -> std::fmt::Result {
+    match value.map(collapse_ws).filter(|v| !v.is_empty()) {
+        Some(v) => writeln!(out, "{name}  - {v}"),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::entry_strategy;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn every_line_is_a_tag(entry in entry_strategy()) {
+            let out = render(std::slice::from_ref(&entry)).unwrap();
+            let lines: Vec<&str> = out.lines().collect();
+            prop_assert!(lines.first().unwrap().starts_with("TY  - "));
+            prop_assert_eq!(*lines.last().unwrap(), "ER  - ");
+            for line in lines {
+                prop_assert!(line.len() >= 6 && &line[2..6] == "  - ", "bad line {:?}", line);
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Spreadsheet-friendly CSV and TSV with one row per entry.
+
+use serde_json::Value;
+
+use super::{Error, Options, field};
+use crate::entry::Entry;
+
+/// Column separators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Delimiter {
+    /// RFC 4180 CSV: fields with commas, quotes or line breaks are quoted.
+    Comma,
+    /// Tab-separated values, with tabs and line breaks escaped as `\t`, `\n`.
+    Tab,
+}
+
+/// Render the selected `EntryView` fields (all of them by default) as rows.
+pub(crate) fn render(
+    entries: &[Entry],
+    options: &Options,
+    delimiter: Delimiter,
+) -> Result<String, Error> {
+    let fields = options.fields()?;
+    let separator = match delimiter {
+        Delimiter::Comma => ",",
+        Delimiter::Tab => "\t",
+    };
+    let mut rows: Vec<String> = Vec::with_capacity(entries.len() + 1);
+    if options.header {
+        rows.push(
+            fields
+                .iter()
+                .map(|f| escape(f, delimiter))
+                .collect::<Vec<_>>()
+                .join(separator),
+        );
+    }
+    for entry in entries {
+        let view = options.project(entry)?;
+        rows.push(
+            fields
+                .iter()
+                .map(|f| escape(&cell(field(&view, f), &options.separator), delimiter))
+                .collect::<Vec<_>>()
+                .join(separator),
+        );
+    }
+    Ok(rows.join("\n"))
+}
+
+/// A value as cell text: lists are joined with `separator` and objects
+/// written as JSON.
+fn cell(value: Option<&Value>, separator: &str) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|v| cell(Some(v), separator))
+            .collect::<Vec<_>>()
+            .join(separator),
+        Some(value) => value.to_string(),
+    }
+}
+
+fn escape(s: &str, delimiter: Delimiter) -> String {
+    match delimiter {
+        Delimiter::Comma => {
+            if s.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", s.replace('"', "\"\""))
+            } else {
+                s.to_owned()
+            }
+        }
+        Delimiter::Tab => {
+            let mut out = String::with_capacity(s.len());
+            for c in s.chars() {
+                match c {
+                    '\\' => out.push_str("\\\\"),
+                    '\t' => out.push_str("\\t"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    c => out.push(c),
+                }
+            }
+            out
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::entry::entry_strategy;
+
+    /// Split CSV into records of fields, honouring quotes.
+    fn parse_csv(s: &str) -> Vec<Vec<String>> {
+        let mut records = vec![vec![String::new()]];
+        let mut quoted = false;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let record = records.last_mut().unwrap();
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    record.last_mut().unwrap().push('"');
+                }
+                ('"', _) => quoted = !quoted,
+                (',', false) => record.push(String::new()),
+                ('\n', false) => records.push(vec![String::new()]),
+                (c, _) => record.last_mut().unwrap().push(c),
+            }
+        }
+        records
+    }
+
+    fn options(fields: &[&str]) -> Options {
+        Options {
+            fields: fields.iter().map(|f| f.to_string()).collect(),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn quotes_multi_line_cells() {
+        assert_eq!(escape("a,b", Delimiter::Comma), r#""a,b""#);
+        assert_eq!(
+            escape("say \"hi\"\nbye", Delimiter::Comma),
+            "\"say \"\"hi\"\"\nbye\""
+        );
+        assert_eq!(escape("a\tb\nc\\", Delimiter::Tab), r"a\tb\nc\\");
+        assert_eq!(escape("plain", Delimiter::Comma), "plain");
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let error = render(&[], &options(&["title", "colour"]), Delimiter::Comma).unwrap_err();
+        assert!(matches!(error, Error::UnknownField(f) if f == "colour"));
+        assert!(render(&[], &options(&["citation.doi"]), Delimiter::Comma).is_ok());
+    }
+
+    proptest! {
+        #[test]
+        fn rows_round_trip(entries in prop::collection::vec(entry_strategy(), 1..4)) {
+            let options = options(&["title", "authors", "url", "full_text"]);
+            let csv = render(&entries, &options, Delimiter::Comma).unwrap();
+            let records = parse_csv(&csv);
+            prop_assert_eq!(records.len(), entries.len() + 1);
+            prop_assert_eq!(&records[0], &["title", "authors", "url", "full_text"]);
+            for (record, entry) in records[1..].iter().zip(&entries) {
+                prop_assert_eq!(&record[0], entry.title());
+                prop_assert_eq!(&record[1], &entry.authors().join("; "));
+                prop_assert_eq!(&record[2], entry.url().as_str());
+                prop_assert_eq!(&record[3], entry.full_text());
+            }
+            let tsv = render(&entries, &options, Delimiter::Tab).unwrap();
+            prop_assert_eq!(tsv.lines().count(), entries.len() + 1);
+            prop_assert!(tsv.lines().all(|l| l.split('\t').count() == 4));
+        }
+    }
+}
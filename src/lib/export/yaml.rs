@@ -0,0 +1,173 @@
+//! A small YAML emitter for front matter, covering what JSON can express.
+
+use serde_json::Value;
+
+/// Write a JSON value as a block-style YAML document (without `---` markers).
+pub(crate) fn to_string(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Object(_) | Value::Array(_) => write_block(&mut out, value, 0),
+        scalar => {
+            out.push_str(&write_scalar(scalar));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn write_block(out: &mut String, value: &Value, indent: usize) {
+    let pad = " ".repeat(indent);
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                out.push_str(&pad);
+                out.push_str(&write_string(key));
+                out.push(':');
+                write_value(out, value, indent);
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                out.push_str(&pad);
+                out.push('-');
+                match item {
+                    // Put the first key of a mapping on the dash's line.
+                    Value::Object(map) if !map.is_empty() => {
+                        let mut nested = String::new();
+                        write_block(&mut nested, item, indent + 2);
+                        out.push(' ');
+                        out.push_str(&nested[indent + 2..]);
+                    }
+                    _ => write_value(out, item, indent),
+                }
+            }
+        }
+        scalar => {
+            out.push_str(&pad);
+            out.push_str(&write_scalar(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+/// Write the value after a `key:` or `-`, nesting collections below it.
+fn write_value(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_block(out, value, indent + 2);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_block(out, value, indent + 2);
+        }
+        Value::Object(_) => out.push_str(" {}\n"),
+        Value::Array(_) => out.push_str(" []\n"),
+        scalar => {
+            out.push(' ');
+            out.push_str(&write_scalar(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+fn write_scalar(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => write_string(s),
+        // Collections are handled by the callers.
+        Value::Array(_) | Value::Object(_) => unreachable!("not a scalar"),
+    }
+}
+
+/// Write a string plainly when YAML would read it back unchanged, and
+/// double-quoted (with JSON escapes, which YAML shares) otherwise.
+fn write_string(s: &str) -> String {
+    if needs_quotes(s) {
+        serde_json::to_string(s).expect("strings always serialise")
+    } else {
+        s.to_owned()
+    }
+}
+
+fn needs_quotes(s: &str) -> bool {
+    let Some(first) = s.chars().next() else {
+        return true;
+    };
+    const RESERVED: &[&str] = &[
+        "true", "false", "yes", "no", "on", "off", "y", "n", "null", "~",
+    ];
+    "-?:,[]{}#&*!|>'\"%@`".contains(first)
+        || first.is_whitespace()
+        || s.ends_with(char::is_whitespace)
+        || s.contains(": ")
+        || s.contains(" #")
+        || s.ends_with(':')
+        || s.chars().any(char::is_control)
+        || RESERVED.contains(&s.to_ascii_lowercase().as_str())
+        || s.parse::<f64>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn writes_nested_values() {
+        let value = json!({
+            "title": "Rust: the good parts",
+            "site": "example.com",
+            "authors": ["Ada", "Grace Hopper"],
+            "year": "2024",
+            "empty": [],
+            "citation": {"doi": "10.1/x", "volume": 3},
+            "items": [{"a": 1, "b": "yes"}],
+        });
+        assert_eq!(
+            to_string(&value),
+            r#"title: "Rust: the good parts"
+site: example.com
+authors:
+  - Ada
+  - Grace Hopper
+year: "2024"
+empty: []
+citation:
+  doi: 10.1/x
+  volume: 3
+items:
+  - a: 1
+    b: "yes"
+"#
+        );
+    }
+
+    #[test]
+    fn quotes_ambiguous_strings() {
+        for s in [
+            "",
+            " lead",
+            "#tag",
+            "- item",
+            "true",
+            "No",
+            "1.5",
+            "a: b",
+            "line\nbreak",
+        ] {
+            assert!(needs_quotes(s), "{s:?}");
+        }
+        for s in [
+            "plain",
+            "https://example.com/a",
+            "2024-01-01T00:00:00Z",
+            "C++",
+        ] {
+            assert!(!needs_quotes(s), "{s:?}");
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use ureq::Agent;
+use url::Url;
+
+use crate::{USER_AGENT, entry::Error};
+
+/// Redirects followed before giving up on a request.
+pub const MAX_REDIRECTS: usize = 10;
+/// Largest body read for a page or sub-request, in bytes.
+pub const MAX_BODY: usize = 10 * 1024 * 1024;
+
+/// Agent that hands back every response, redirects and errors included, so
+/// fetchers can follow, record or inspect each one.
+static SEND_AGENT: Lazy<Agent> = Lazy::new(|| {
+    Agent::config_builder()
+        .user_agent(USER_AGENT)
+        .timeout_global(Some(Duration::from_secs(10)))
+        .max_redirects(0)
+        .http_status_as_error(false)
+        .build()
+        .into()
+});
+
+/// The HTTP methods spy sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+}
+
+impl Method {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+        }
+    }
+}
+
+/// The response to a single request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    /// Header names, lowercased, and values in the order they were received.
+    pub headers: Vec<(String, String)>,
+    /// The body, with any `content-encoding` and `transfer-encoding` undone.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// The first value of a header, by its lowercase name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// The media type, without parameters, lowercased.
+    pub fn mime(&self) -> Option<String> {
+        let content_type = self.header("content-type")?;
+        let mime = content_type.split(';').next()?.trim();
+        (!mime.is_empty()).then(|| mime.to_ascii_lowercase())
+    }
+}
+
+/// Fetches pages for extraction, including sub-requests such as web app
+/// manifests, oEmbed endpoints, archive lookups and the resources of
+/// snapshots. Implementations may go to the network, record what they
+/// fetch, or replay earlier responses.
+pub trait Fetcher {
+    /// Send one request with the given extra headers, without following
+    /// redirects, and return the response whatever its status. Bodies over
+    /// `limit` bytes are an error.
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        headers: &[(&str, &str)],
+        limit: usize,
+    ) -> Result<Response, ureq::Error>;
+
+    /// GET a URL, following redirects, and return the URL the successful
+    /// response came from along with the response.
+    fn follow(&self, url: &Url, limit: usize) -> Result<(Url, Response), Box<Error>> {
+        let error = |error, url: &Url| {
+            Box::new(Error::FetchError {
+                error,
+                url: url.clone(),
+            })
+        };
+        let mut current = url.clone();
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self
+                .send(Method::Get, &current, &[], limit)
+                .map_err(|e| error(e, &current))?;
+            let location = response.header("location").map(str::to_owned);
+            match (response.status, location) {
+                (300..=399, Some(location)) => {
+                    current = current
+                        .join(&location)
+                        .map_err(|_| error(ureq::Error::RedirectFailed, &current))?;
+                }
+                (200..=299, _) => return Ok((current, response)),
+                (status, _) => return Err(error(ureq::Error::StatusCode(status), &current)),
+            }
+        }
+        Err(error(ureq::Error::TooManyRedirects, url))
+    }
+
+    /// GET a URL, following redirects, and return the body of a successful response.
+    fn get(&self, url: &Url) -> Result<String, Box<Error>> {
+        let (_, response) = self.follow(url, MAX_BODY)?;
+        Ok(String::from_utf8_lossy(&response.body).into_owned())
+    }
+}
+
+/// Fetches over the network.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Live;
+
+impl Fetcher for Live {
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        headers: &[(&str, &str)],
+        limit: usize,
+    ) -> Result<Response, ureq::Error> {
+        send(method, url, headers, limit)
+    }
+}
+
+/// Send a request over the network.
+pub(crate) fn send(
+    method: Method,
+    url: &Url,
+    headers: &[(&str, &str)],
+    limit: usize,
+) -> Result<Response, ureq::Error> {
+    let mut response = match method {
+        Method::Get => {
+            let mut request = SEND_AGENT.get(url.as_str());
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            request.call()?
+        }
+        Method::Head => {
+            let mut request = SEND_AGENT.head(url.as_str());
+            for (name, value) in headers {
+                request = request.header(*name, *value);
+            }
+            request.call()?
+        }
+    };
+    let headers = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+            (name.to_string(), value)
+        })
+        .collect();
+    let body = match method {
+        Method::Get => response
+            .body_mut()
+            .with_config()
+            .limit(limit as u64)
+            .read_to_vec()?,
+        Method::Head => Vec::new(),
+    };
+    Ok(Response {
+        status: response.status().as_u16(),
+        headers,
+        body,
+    })
+}
@@ -0,0 +1,655 @@
+use std::{cmp::Ordering, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+use crate::entry::{Entry, parse_date};
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Syntax error at offset {pos}: {message}")]
+    Syntax { pos: usize, message: String },
+    #[error("Unknown field {0:?}")]
+    UnknownField(String),
+    #[error("{value:?} is not a date (in comparison with {field})")]
+    InvalidDate { field: &'static str, value: String },
+    #[error("Invalid regular expression {pattern:?}: {error}")]
+    InvalidRegex {
+        pattern: String,
+        error: Box<regex::Error>,
+    },
+    #[error("Operator {op} cannot be used with {field}")]
+    UnsupportedOperator {
+        field: &'static str,
+        op: &'static str,
+    },
+}
+
+/// Entry fields that filters and sorting can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    /// Site title or URL host.
+    Site,
+    Host,
+    Url,
+    Author,
+    Tag,
+    Folder,
+    Description,
+    Notes,
+    Text,
+    Doi,
+    Journal,
+    Id,
+    Published,
+    Added,
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Site => "site",
+            Field::Host => "host",
+            Field::Url => "url",
+            Field::Author => "author",
+            Field::Tag => "tag",
+            Field::Folder => "folder",
+            Field::Description => "description",
+            Field::Notes => "notes",
+            Field::Text => "full_text",
+            Field::Doi => "doi",
+            Field::Journal => "journal",
+            Field::Id => "id",
+            Field::Published => "published",
+            Field::Added => "added",
+        }
+    }
+
+    fn is_date(self) -> bool {
+        matches!(self, Field::Published | Field::Added)
+    }
+
+    fn date(self, entry: &Entry) -> Option<DateTime<Utc>> {
+        match self {
+            Field::Published => entry.published().copied(),
+            Field::Added => entry.added().copied(),
+            _ => None,
+        }
+    }
+
+    /// Text values of the field; multi-valued fields match if any value does.
+    fn texts(self, entry: &Entry) -> Vec<String> {
+        let lower = |s: &str| s.to_lowercase();
+        match self {
+            Field::Title => vec![lower(entry.title())],
+            Field::Site => std::iter::once(lower(entry.site()))
+                .chain(entry.url().host_str().map(lower))
+                .collect(),
+            Field::Host => entry.url().host_str().map(lower).into_iter().collect(),
+            Field::Url => vec![lower(entry.url().as_str())],
+            Field::Author => entry.authors().into_iter().map(lower).collect(),
+            Field::Tag => entry.tags().iter().map(|t| lower(t.as_str())).collect(),
+            Field::Folder => entry.folder().iter().map(|f| lower(f)).collect(),
+            Field::Description => entry.description().map(lower).into_iter().collect(),
+            Field::Notes => entry.notes().map(lower).into_iter().collect(),
+            Field::Text => vec![lower(entry.full_text())],
+            Field::Doi => entry
+                .citation()
+                .and_then(|c| c.doi.as_deref())
+                .map(lower)
+                .into_iter()
+                .collect(),
+            Field::Journal => entry
+                .citation()
+                .and_then(|c| c.journal.as_deref())
+                .map(lower)
+                .into_iter()
+                .collect(),
+            Field::Id => vec![entry.id().to_string()],
+            Field::Published | Field::Added => self
+                .date(entry)
+                .map(|d| d.to_rfc3339())
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_ascii_lowercase().as_str() {
+            "title" => Field::Title,
+            "site" => Field::Site,
+            "host" | "domain" => Field::Host,
+            "url" => Field::Url,
+            "author" | "authors" => Field::Author,
+            "tag" | "tags" => Field::Tag,
+            "folder" => Field::Folder,
+            "description" => Field::Description,
+            "notes" | "note" => Field::Notes,
+            "full_text" | "text" => Field::Text,
+            "doi" => Field::Doi,
+            "journal" => Field::Journal,
+            "id" => Field::Id,
+            "published" | "date" => Field::Published,
+            "added" => Field::Added,
+            _ => return Err(Error::UnknownField(s.to_owned())),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    Matches,
+    NotMatches,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Eq => "=",
+            Op::Ne => "!=",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::Contains => "contains",
+            Op::Matches => "~",
+            Op::NotMatches => "!~",
+        }
+    }
+
+    fn accepts(self, ord: Ordering) -> bool {
+        match self {
+            Op::Eq => ord == Ordering::Equal,
+            Op::Ne => ord != Ordering::Equal,
+            Op::Lt => ord == Ordering::Less,
+            Op::Le => ord != Ordering::Greater,
+            Op::Gt => ord == Ordering::Greater,
+            Op::Ge => ord != Ordering::Less,
+            Op::Contains | Op::Matches | Op::NotMatches => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Text(String),
+    Regex(Regex),
+    /// A date literal; day-only literals compare by calendar day.
+    Date {
+        at: DateTime<Utc>,
+        day: Option<NaiveDate>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare { field: Field, op: Op, value: Value },
+}
+
+impl Expr {
+    fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Expr::And(a, b) => a.matches(entry) && b.matches(entry),
+            Expr::Or(a, b) => a.matches(entry) || b.matches(entry),
+            Expr::Not(e) => !e.matches(entry),
+            Expr::Compare { field, op, value } => compare(*field, *op, value, entry),
+        }
+    }
+}
+
+fn compare(field: Field, op: Op, value: &Value, entry: &Entry) -> bool {
+    match value {
+        Value::Date { at, day } => {
+            let Some(date) = field.date(entry) else {
+                return false;
+            };
+            let ord = match day {
+                Some(day) => date.date_naive().cmp(day),
+                None => date.cmp(at),
+            };
+            op.accepts(ord)
+        }
+        Value::Regex(re) => {
+            let any = field.texts(entry).iter().any(|t| re.is_match(t));
+            if op == Op::NotMatches { !any } else { any }
+        }
+        Value::Text(wanted) => {
+            let texts = field.texts(entry);
+            match op {
+                Op::Contains => texts.iter().any(|t| t.contains(wanted.as_str())),
+                // Negated equality holds only when no value is equal.
+                Op::Ne => !texts.iter().any(|t| t == wanted),
+                _ => texts.iter().any(|t| op.accepts(t.as_str().cmp(wanted))),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    Op(Op),
+    Colon,
+    Str(String),
+    Word(String),
+}
+
+fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut out = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(pos, ch)) = chars.peek() {
+        let token = match ch {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' => {
+                chars.next();
+                Token::LParen
+            }
+            ')' => {
+                chars.next();
+                Token::RParen
+            }
+            ':' => {
+                chars.next();
+                Token::Colon
+            }
+            '=' | '!' | '<' | '>' | '~' => {
+                chars.next();
+                let next = chars.peek().map(|&(_, c)| c);
+                let (op, consumed) = match (ch, next) {
+                    ('=', Some('=')) => (Op::Eq, true),
+                    ('=', _) => (Op::Eq, false),
+                    ('!', Some('=')) => (Op::Ne, true),
+                    ('!', Some('~')) => (Op::NotMatches, true),
+                    ('<', Some('=')) => (Op::Le, true),
+                    ('<', _) => (Op::Lt, false),
+                    ('>', Some('=')) => (Op::Ge, true),
+                    ('>', _) => (Op::Gt, false),
+                    ('~', _) => (Op::Matches, false),
+                    _ => {
+                        return Err(Error::Syntax {
+                            pos,
+                            message: "expected `!=` or `!~`".into(),
+                        });
+                    }
+                };
+                if consumed {
+                    chars.next();
+                }
+                Token::Op(op)
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        // Only quotes and backslashes are escaped, so regex
+                        // escapes such as `\b` pass through unchanged.
+                        Some((_, '\\')) => match chars.next_if(|&(_, c)| c == '"' || c == '\\') {
+                            Some((_, c)) => s.push(c),
+                            None => s.push('\\'),
+                        },
+                        Some((_, c)) => s.push(c),
+                        None => {
+                            return Err(Error::Syntax {
+                                pos,
+                                message: "unterminated string".into(),
+                            });
+                        }
+                    }
+                }
+                Token::Str(s)
+            }
+            _ => {
+                let mut s = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if c.is_whitespace() || "()=!<>~:\"".contains(c) {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                Token::Word(s)
+            }
+        };
+        out.push((pos, token));
+    }
+    Ok(out)
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    idx: usize,
+    len: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.idx).map(|(_, t)| t)
+    }
+
+    fn pos(&self) -> usize {
+        self.tokens.get(self.idx).map_or(self.len, |(p, _)| *p)
+    }
+
+    fn error(&self, message: impl Into<String>) -> Error {
+        Error::Syntax {
+            pos: self.pos(),
+            message: message.into(),
+        }
+    }
+
+    fn keyword(&mut self, kw: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(kw)) {
+            self.idx += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.and()?;
+        while self.keyword("or") {
+            lhs = Expr::Or(Box::new(lhs), Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, Error> {
+        let mut lhs = self.not()?;
+        while self.keyword("and") {
+            lhs = Expr::And(Box::new(lhs), Box::new(self.not()?));
+        }
+        Ok(lhs)
+    }
+
+    fn not(&mut self) -> Result<Expr, Error> {
+        if self.keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, Error> {
+        if self.peek() == Some(&Token::LParen) {
+            self.idx += 1;
+            let expr = self.or()?;
+            if self.peek() != Some(&Token::RParen) {
+                return Err(self.error("expected `)`"));
+            }
+            self.idx += 1;
+            return Ok(expr);
+        }
+        let field: Field = match self.peek() {
+            Some(Token::Word(w)) => w.parse()?,
+            _ => return Err(self.error("expected a field name")),
+        };
+        self.idx += 1;
+        let op = match self.peek() {
+            Some(Token::Op(op)) => *op,
+            // `tag:rust` means the tag is present; `title:rust` means contains.
+            Some(Token::Colon) if field == Field::Tag => Op::Eq,
+            Some(Token::Colon) => Op::Contains,
+            Some(Token::Word(w)) if w.eq_ignore_ascii_case("contains") => Op::Contains,
+            _ => return Err(self.error("expected an operator")),
+        };
+        self.idx += 1;
+        let raw = match self.peek() {
+            Some(Token::Str(s) | Token::Word(s)) => s.clone(),
+            _ => return Err(self.error("expected a value")),
+        };
+        self.idx += 1;
+        let value = value(field, op, raw)?;
+        Ok(Expr::Compare { field, op, value })
+    }
+}
+
+fn value(field: Field, op: Op, raw: String) -> Result<Value, Error> {
+    let unsupported = || Error::UnsupportedOperator {
+        field: field.name(),
+        op: op.symbol(),
+    };
+    match op {
+        Op::Matches | Op::NotMatches => {
+            if field.is_date() {
+                return Err(unsupported());
+            }
+            // Texts are compared lowercased, so the pattern must ignore case too.
+            RegexBuilder::new(&raw)
+                .case_insensitive(true)
+                .build()
+                .map(Value::Regex)
+                .map_err(|error| Error::InvalidRegex {
+                    pattern: raw,
+                    error: Box::new(error),
+                })
+        }
+        _ if field.is_date() => {
+            if op == Op::Contains {
+                return Err(unsupported());
+            }
+            let at = parse_date(&raw).ok_or_else(|| Error::InvalidDate {
+                field: field.name(),
+                value: raw.clone(),
+            })?;
+            let day = NaiveDate::parse_from_str(raw.trim(), "%Y-%m-%d").ok();
+            Ok(Value::Date { at, day })
+        }
+        _ => Ok(Value::Text(raw.to_lowercase())),
+    }
+}
+
+/// A compiled filter expression over entries, e.g.
+/// `site = "lwn.net" and published > 2025-01-01 and tag:rust`.
+///
+/// Comparisons are `=`, `!=`, `<`, `<=`, `>`, `>=`, `contains` (or `field:value`),
+/// `~` and `!~` (regular expressions), combined with `and`, `or`, `not` and
+/// parentheses. Text comparisons ignore case; multi-valued fields such as
+/// `author` and `tag` match if any value does.
+#[derive(Debug, Clone)]
+pub struct Filter(Expr);
+
+impl Filter {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            idx: 0,
+            len: input.len(),
+        };
+        let expr = parser.or()?;
+        if parser.peek().is_some() {
+            return Err(parser.error("unexpected input"));
+        }
+        Ok(Filter(expr))
+    }
+
+    pub fn matches(&self, entry: &Entry) -> bool {
+        self.0.matches(entry)
+    }
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Sort order, written `field` or `field:desc`.
+#[derive(Debug, Clone, Copy)]
+pub struct Sort {
+    field: Field,
+    descending: bool,
+}
+
+impl Sort {
+    /// Sort entries in place. Entries without a value sort last either way.
+    pub fn apply(&self, entries: &mut [Entry]) {
+        entries.sort_by(|a, b| {
+            let ord = if self.field.is_date() {
+                option_last(self.field.date(a), self.field.date(b))
+            } else {
+                option_last(
+                    self.field.texts(a).into_iter().next(),
+                    self.field.texts(b).into_iter().next(),
+                )
+            };
+            match ord {
+                (ord, true) if self.descending => ord.reverse(),
+                (ord, _) => ord,
+            }
+        });
+    }
+}
+
+/// Compare options with `None` last; the flag says whether both were present.
+fn option_last<T: Ord>(a: Option<T>, b: Option<T>) -> (Ordering, bool) {
+    match (a, b) {
+        (Some(a), Some(b)) => (a.cmp(&b), true),
+        (Some(_), None) => (Ordering::Less, false),
+        (None, Some(_)) => (Ordering::Greater, false),
+        (None, None) => (Ordering::Equal, false),
+    }
+}
+
+impl FromStr for Sort {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (field, dir) = s.split_once(':').unwrap_or((s, "asc"));
+        let descending = match dir.to_ascii_lowercase().as_str() {
+            "asc" => false,
+            "desc" => true,
+            _ => {
+                return Err(Error::Syntax {
+                    pos: field.len() + 1,
+                    message: "expected `asc` or `desc`".into(),
+                });
+            }
+        };
+        Ok(Sort {
+            field: field.parse()?,
+            descending,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        entry::{Extraction, entry_strategy},
+        fetch::Live,
+        tag::Tag,
+    };
+    use proptest::prelude::*;
+    use url::Url;
+
+    fn lwn() -> Entry {
+        let html = r#"<html><head><title>Rust in the kernel</title>
+            <meta property="article:published_time" content="2025-02-10T09:00:00Z">
+            </head><body></body></html>"#;
+        Entry::extract(
+            &Live,
+            &Url::parse("https://lwn.net/Articles/1").unwrap(),
+            html,
+            None,
+            Extraction::METADATA_ONLY,
+        )
+        .with_tags([Tag::try_from("rust").unwrap()])
+        .with_added(DateTime::from_timestamp(1_740_000_000, 0))
+    }
+
+    #[test]
+    fn evaluates_the_documented_example() {
+        let filter =
+            Filter::parse(r#"site = "lwn.net" and published > 2025-01-01 and tag:rust"#).unwrap();
+        assert!(filter.matches(&lwn()));
+        let filter = Filter::parse(r#"site = "lwn.net" and published > 2025-03-01"#).unwrap();
+        assert!(!filter.matches(&lwn()));
+    }
+
+    #[test]
+    fn supports_regex_contains_and_grouping() {
+        let entry = lwn();
+        for (src, expected) in [
+            (r#"title ~ "^rust\b""#, true),
+            (r#"title !~ "go""#, true),
+            (r#"title ~ "^Rust in the K""#, true),
+            (r#"title !~ "KERNEL""#, false),
+            ("title contains kernel", true),
+            ("not (tag:go or title:python)", true),
+            ("tag:go or (host = lwn.net and not author:anyone)", true),
+            ("published > 2026-01-01", false),
+            ("title = rust", false),
+        ] {
+            assert_eq!(
+                Filter::parse(src).unwrap().matches(&entry),
+                expected,
+                "{src}"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        assert!(matches!(
+            Filter::parse("colour = red"),
+            Err(Error::UnknownField(_))
+        ));
+        assert!(matches!(
+            Filter::parse("added > yesterday"),
+            Err(Error::InvalidDate { .. })
+        ));
+        assert!(matches!(
+            Filter::parse("title = (x"),
+            Err(Error::Syntax { .. })
+        ));
+        assert!(matches!(
+            Filter::parse("title = x y"),
+            Err(Error::Syntax { pos: 10, .. })
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn negation_is_complement(entry in entry_strategy(), word in "[a-z]{1,3}") {
+            let filter = Filter::parse(&format!("title contains {word} or tag:{word}")).unwrap();
+            let negated = Filter::parse(&format!("not (title contains {word} or tag:{word})")).unwrap();
+            prop_assert_ne!(filter.matches(&entry), negated.matches(&entry));
+        }
+
+        #[test]
+        fn sorting_by_date_is_ordered(mut entries in prop::collection::vec(entry_strategy(), 0..8)) {
+            "added:desc".parse::<Sort>().unwrap().apply(&mut entries);
+            let dates: Vec<_> = entries.iter().map(|e| e.added().copied()).collect();
+            let present: Vec<_> = dates.iter().flatten().collect();
+            prop_assert!(present.windows(2).all(|w| w[0] >= w[1]));
+            let first_none = dates.iter().position(Option::is_none).unwrap_or(dates.len());
+            prop_assert!(dates[first_none..].iter().all(Option::is_none));
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use thiserror::Error;
+use url::Url;
+
+use crate::{
+    entry::{self, Entry},
+    fetch::Fetcher,
+    tag::Tag,
+};
+
+pub mod netscape;
+
+/// Formats bookmarks can be imported from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Source {
+    /// Netscape bookmark HTML (`bookmarks.html`), as exported by every major browser.
+    Netscape,
+}
+
+/// A bookmark read from another application, before it becomes an [`Entry`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub url: Url,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub added: Option<DateTime<Utc>>,
+    pub folder: Vec<String>,
+}
+
+/// Parse bookmarks from the given source. Items without a usable http(s) URL
+/// (bookmarklets, `place:` queries, separators) are skipped.
+pub fn parse(source: Source, input: &str) -> Vec<Bookmark> {
+    match source {
+        Source::Netscape => netscape::parse(input),
+    }
+}
+
+/// Something about a bookmark that could not be imported as it was.
+#[derive(Debug, Error)]
+pub enum Warning {
+    #[error("keeping {url} unenriched: {error}")]
+    Unenriched { url: Url, error: Box<entry::Error> },
+    #[error("tag {tag:?} of {url} imported as {normalised:?}")]
+    TagNormalised {
+        url: Url,
+        tag: String,
+        normalised: Tag,
+    },
+    #[error("tag {tag:?} of {url} has nothing a tag can hold; dropped")]
+    TagDropped { url: Url, tag: String },
+}
+
+impl Bookmark {
+    /// Turn the bookmark into an entry. With a fetcher, the page is fetched
+    /// and run through the normal extraction pipeline, keeping the bookmark's
+    /// own title; if fetching fails the bookmark is kept as-is. Tags that
+    /// aren't valid are normalised, or dropped if nothing is left of them.
+    pub fn into_entry(self, enrich: Option<&dyn Fetcher>) -> (Entry, Vec<Warning>) {
+        let mut warnings = Vec::new();
+        let fetched = enrich.and_then(|fetcher| {
+            Entry::fetch(fetcher, &self.url, self.title.clone())
+                .map_err(|error| {
+                    warnings.push(Warning::Unenriched {
+                        url: self.url.clone(),
+                        error,
+                    })
+                })
+                .ok()
+        });
+        let mut tags = Vec::with_capacity(self.tags.len());
+        for tag in self.tags {
+            if let Ok(valid) = Tag::try_from(tag.as_str()) {
+                tags.push(valid);
+            } else if let Some(normalised) = Tag::normalised(&tag) {
+                tags.push(normalised.clone());
+                warnings.push(Warning::TagNormalised {
+                    url: self.url.clone(),
+                    tag,
+                    normalised,
+                });
+            } else {
+                warnings.push(Warning::TagDropped {
+                    url: self.url.clone(),
+                    tag,
+                });
+            }
+        }
+        let entry = fetched
+            .unwrap_or_else(|| Entry::unfetched(&self.url, self.title))
+            .with_description(self.description)
+            .with_tags(tags)
+            .with_added(self.added)
+            .with_folder(self.folder);
+        (entry, warnings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::{Method, Response};
+
+    struct Offline;
+
+    impl Fetcher for Offline {
+        fn send(
+            &self,
+            _method: Method,
+            _url: &Url,
+            _headers: &[(&str, &str)],
+            _limit: usize,
+        ) -> Result<Response, ureq::Error> {
+            Err(ureq::Error::Other("offline".into()))
+        }
+    }
+
+    #[test]
+    fn reports_what_could_not_be_kept() {
+        let bookmark = Bookmark {
+            url: Url::parse("https://example.com/").unwrap(),
+            title: Some("Example".into()),
+            description: None,
+            tags: vec!["news".into(), "to read".into(), "東京".into()],
+            added: None,
+            folder: Vec::new(),
+        };
+        let (entry, warnings) = bookmark.into_entry(Some(&Offline));
+        let mut tags: Vec<&str> = entry.tags().iter().map(|t| t.as_str()).collect();
+        tags.sort();
+        assert_eq!(tags, ["news", "to-read"]);
+        assert_eq!(entry.title(), "Example");
+        assert!(matches!(
+            warnings.as_slice(),
+            [
+                Warning::Unenriched { .. },
+                Warning::TagNormalised { .. },
+                Warning::TagDropped { .. }
+            ]
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use super::Bookmark;
+use crate::entry::collapse_ws;
+
+/// Parse a Netscape bookmark file. Folders come from the `<H3>` heading of each
+/// enclosing `<DT>`, and descriptions from the `<DD>` following a link.
+pub fn parse(input: &str) -> Vec<Bookmark> {
+    let doc = Html::parse_document(input);
+    let sel = Selector::parse("a[href]").expect("anchor selector is valid");
+    doc.select(&sel).filter_map(bookmark).collect()
+}
+
+fn bookmark(a: ElementRef) -> Option<Bookmark> {
+    let attrs = a.value();
+    let url = Url::parse(attrs.attr("href")?.trim()).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+    let title = Some(collapse_ws(&a.text().collect::<String>())).filter(|s| !s.is_empty());
+    let tags = attrs
+        .attr("tags")
+        .map(|t| {
+            t.split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_owned)
+                .collect()
+        })
+        .unwrap_or_default();
+    let added = attrs.attr("add_date").and_then(parse_timestamp);
+    Some(Bookmark {
+        url,
+        title,
+        description: description(a),
+        tags,
+        added,
+        folder: folder(a),
+    })
+}
+
+/// The `<DD>` directly after the link's `<DT>`, if any.
+fn description(a: ElementRef) -> Option<String> {
+    let dt = a.parent().and_then(ElementRef::wrap)?;
+    let dd = dt
+        .next_siblings()
+        .filter_map(ElementRef::wrap)
+        .next()
+        .filter(|e| e.value().name() == "dd")?;
+    Some(collapse_ws(&dd.text().collect::<String>())).filter(|s| !s.is_empty())
+}
+
+fn folder(a: ElementRef) -> Vec<String> {
+    let mut path: Vec<String> = a
+        .ancestors()
+        .filter_map(ElementRef::wrap)
+        .filter(|e| e.value().name() == "dt")
+        .filter_map(|dt| {
+            dt.children()
+                .filter_map(ElementRef::wrap)
+                .find(|c| c.value().name() == "h3")
+        })
+        .map(|h3| collapse_ws(&h3.text().collect::<String>()))
+        .collect();
+    path.reverse();
+    path
+}
+
+/// `ADD_DATE` is a Unix timestamp in seconds, though some exporters write
+/// milliseconds or microseconds.
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    let n: i64 = raw.trim().parse().ok()?;
+    match n {
+        n if n > 100_000_000_000_000 => DateTime::from_timestamp_micros(n),
+        n if n > 100_000_000_000 => DateTime::from_timestamp_millis(n),
+        n if n > 0 => DateTime::from_timestamp(n, 0),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIREFOX_EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><A HREF="https://lwn.net/" ADD_DATE="1700000000" TAGS="news,linux">LWN.net</A>
+    <DD>Linux news &amp; commentary
+    <DT><H3 ADD_DATE="1690000000">Programming</H3>
+    <DL><p>
+        <DT><H3>Rust</H3>
+        <DL><p>
+            <DT><A HREF="https://doc.rust-lang.org/book/" ADD_DATE="1700000000000">The Book</A>
+            <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+        </DL><p>
+        <DT><A HREF="https://go.dev/">Go</A>
+    </DL><p>
+    <DT><A HREF="place:sort=8&maxResults=10">Recent</A>
+</DL><p>"#;
+
+    #[test]
+    fn parses_folders_tags_and_dates() {
+        let bookmarks = parse(FIREFOX_EXPORT);
+        let urls: Vec<&str> = bookmarks.iter().map(|b| b.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            [
+                "https://lwn.net/",
+                "https://doc.rust-lang.org/book/",
+                "https://go.dev/"
+            ]
+        );
+
+        let lwn = &bookmarks[0];
+        assert_eq!(lwn.title.as_deref(), Some("LWN.net"));
+        assert_eq!(lwn.tags, ["news", "linux"]);
+        assert_eq!(lwn.description.as_deref(), Some("Linux news & commentary"));
+        assert_eq!(lwn.added, DateTime::from_timestamp(1_700_000_000, 0));
+        assert!(lwn.folder.is_empty());
+
+        let book = &bookmarks[1];
+        assert_eq!(book.folder, ["Programming", "Rust"]);
+        assert_eq!(book.added, DateTime::from_timestamp(1_700_000_000, 0));
+        assert_eq!(book.description, None);
+
+        assert_eq!(bookmarks[2].folder, ["Programming"]);
+    }
+}
@@ -0,0 +1,211 @@
+use ego_tree::NodeRef;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::entry::collapse_ws;
+
+/// A page's structured data as written, for fields spy doesn't model, such
+/// as `og:video` or `product:price`.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RawMeta {
+    /// `content` of each `<meta>` by its `name` or `property`. Repeated
+    /// names, like `og:image`, hold a list of values.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub properties: Map<String, Value>,
+    /// Each JSON-LD block, parsed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub json_ld: Vec<Value>,
+    /// Top-level microdata items, as in the HTML standard's JSON form:
+    /// `{"type": [...], "id": ..., "properties": {"name": [values]}}`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub microdata: Vec<Value>,
+}
+
+impl RawMeta {
+    /// Collect a document's structured data, if it has any. Relative URLs
+    /// in microdata are resolved against `url`.
+    pub(crate) fn from_document(url: &Url, doc: &Html) -> Option<Self> {
+        let meta = RawMeta {
+            properties: properties(doc),
+            json_ld: json_ld(doc),
+            microdata: microdata(url, doc),
+        };
+        if meta == RawMeta::default() {
+            None
+        } else {
+            Some(meta)
+        }
+    }
+}
+
+fn properties(doc: &Html) -> Map<String, Value> {
+    let sel = Selector::parse("meta[content]").expect("valid selector");
+    let mut properties = Map::new();
+    for element in doc.select(&sel) {
+        let attrs = element.value();
+        let Some(key) = attrs.attr("property").or(attrs.attr("name")) else {
+            continue;
+        };
+        let key = key.trim();
+        let content = Value::from(attrs.attr("content").unwrap_or_default().trim());
+        if key.is_empty() {
+            continue;
+        }
+        match properties.get_mut(key) {
+            None => {
+                properties.insert(key.to_owned(), content);
+            }
+            Some(Value::Array(values)) => values.push(content),
+            Some(value) => *value = Value::Array(vec![value.take(), content]),
+        }
+    }
+    properties
+}
+
+fn json_ld(doc: &Html) -> Vec<Value> {
+    let sel = Selector::parse(r#"script[type="application/ld+json"]"#).expect("valid selector");
+    doc.select(&sel)
+        .filter_map(|script| serde_json::from_str(&script.text().collect::<String>()).ok())
+        .collect()
+}
+
+fn microdata(url: &Url, doc: &Html) -> Vec<Value> {
+    let sel = Selector::parse("[itemscope]:not([itemprop])").expect("valid selector");
+    doc.select(&sel)
+        .map(|item| microdata_item(url, item))
+        .collect()
+}
+
+fn microdata_item(url: &Url, item: ElementRef) -> Value {
+    let mut object = Map::new();
+    if let Some(types) = item.value().attr("itemtype") {
+        let types: Vec<Value> = types.split_whitespace().map(Value::from).collect();
+        object.insert("type".to_owned(), Value::Array(types));
+    }
+    if let Some(id) = item.value().attr("itemid") {
+        object.insert("id".to_owned(), Value::from(id.trim()));
+    }
+    let mut properties = Map::new();
+    collect_properties(url, *item, &mut properties);
+    object.insert("properties".to_owned(), Value::Object(properties));
+    Value::Object(object)
+}
+
+/// Add the properties below `node` to `properties`, without descending into
+/// nested items, whose properties are their own.
+fn collect_properties(url: &Url, node: NodeRef<Node>, properties: &mut Map<String, Value>) {
+    for child in node.children() {
+        let Some(element) = ElementRef::wrap(child) else {
+            continue;
+        };
+        if let Some(names) = element.value().attr("itemprop") {
+            let value = if element.value().attr("itemscope").is_some() {
+                microdata_item(url, element)
+            } else {
+                Value::from(property_value(url, element))
+            };
+            for name in names.split_whitespace() {
+                match properties.get_mut(name) {
+                    Some(Value::Array(values)) => values.push(value.clone()),
+                    _ => {
+                        properties.insert(name.to_owned(), Value::Array(vec![value.clone()]));
+                    }
+                }
+            }
+        }
+        if element.value().attr("itemscope").is_none() {
+            collect_properties(url, child, properties);
+        }
+    }
+}
+
+/// A property's value, which depends on the element it is on.
+fn property_value(url: &Url, element: ElementRef) -> String {
+    let e = element.value();
+    let absolute = |attr| {
+        e.attr(attr)
+            .and_then(|href| url.join(href.trim()).ok())
+            .map(String::from)
+            .unwrap_or_default()
+    };
+    match e.name() {
+        "meta" => e.attr("content").unwrap_or_default().to_owned(),
+        "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => absolute("src"),
+        "a" | "area" | "link" => absolute("href"),
+        "object" => absolute("data"),
+        "data" | "meter" => e.attr("value").unwrap_or_default().to_owned(),
+        "time" => match e.attr("datetime") {
+            Some(datetime) => datetime.to_owned(),
+            None => collapse_ws(&element.text().collect::<String>()),
+        },
+        _ => collapse_ws(&element.text().collect::<String>()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const PRODUCT_PAGE: &str = r#"<html><head>
+        <meta property="og:title" content="Kettle">
+        <meta property="og:image" content="https://example.com/a.jpg">
+        <meta property="og:image" content="https://example.com/b.jpg">
+        <meta name="product:price" content=" 24.99 ">
+        <meta charset="utf-8">
+        <script type="application/ld+json">{"@type": "Product", "name": "Kettle"}</script>
+        <script type="application/ld+json">{ not json</script>
+        </head><body>
+        <div itemscope itemtype="https://schema.org/Product" itemid="urn:sku:1">
+          <h1 itemprop="name">Kettle</h1>
+          <img itemprop="image" src="/kettle.jpg">
+          <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+            <meta itemprop="price" content="24.99">
+            <time itemprop="validFrom" datetime="2025-01-01">New year</time>
+          </div>
+        </div>
+        </body></html>"#;
+
+    #[test]
+    fn keeps_meta_tags_json_ld_and_microdata() {
+        let url = Url::parse("https://example.com/shop/kettle").unwrap();
+        let meta = RawMeta::from_document(&url, &Html::parse_document(PRODUCT_PAGE)).unwrap();
+        assert_eq!(
+            Value::Object(meta.properties),
+            json!({
+                "og:title": "Kettle",
+                "og:image": ["https://example.com/a.jpg", "https://example.com/b.jpg"],
+                "product:price": "24.99",
+            })
+        );
+        assert_eq!(
+            meta.json_ld,
+            [json!({"@type": "Product", "name": "Kettle"})]
+        );
+        assert_eq!(
+            meta.microdata,
+            [json!({
+                "type": ["https://schema.org/Product"],
+                "id": "urn:sku:1",
+                "properties": {
+                    "name": ["Kettle"],
+                    "image": ["https://example.com/kettle.jpg"],
+                    "offers": [{
+                        "type": ["https://schema.org/Offer"],
+                        "properties": {"price": ["24.99"], "validFrom": ["2025-01-01"]},
+                    }],
+                },
+            })]
+        );
+    }
+
+    #[test]
+    fn plain_pages_have_none() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let doc = Html::parse_document("<html><head><title>Hi</title></head></html>");
+        assert_eq!(RawMeta::from_document(&url, &doc), None);
+    }
+}
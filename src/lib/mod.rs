@@ -1,25 +1,24 @@
-use std::time::Duration;
-
-use once_cell::sync::Lazy;
-use ureq::Agent;
-
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static DHAT_ALLOC: dhat::Alloc = dhat::Alloc;
 
+pub mod archive;
+pub mod check;
+pub mod citation;
 pub mod cli;
+pub mod dedup;
 pub mod entry;
+pub mod export;
+pub mod fetch;
+pub mod filter;
+pub mod import;
+pub mod meta;
+pub mod search;
+pub mod snapshot;
+pub mod store;
 pub mod tag;
 pub mod template;
+pub mod text;
+pub mod warc;
 
 const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.10 Safari/605.1.1";
-
-/// HTTP agent to use to fetch webpages
-pub static AGENT: Lazy<Agent> = Lazy::new(|| {
-    let config = Agent::config_builder()
-        .user_agent(USER_AGENT)
-        .timeout_global(Some(Duration::from_secs(10)))
-        .build();
-    let agent: Agent = config.into();
-    agent
-});
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::{entry::Entry, text::words};
+
+/// BM25 term-frequency saturation.
+const K1: f64 = 1.2;
+/// BM25 document-length normalisation.
+const B: f64 = 0.75;
+/// Position gap inserted between authors (and tags) so phrases cannot span them.
+const VALUE_GAP: usize = 16;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum Error {
+    #[error("Unterminated quote in query")]
+    UnterminatedQuote,
+    #[error("Unknown search field {0:?}; expected author, site or tag")]
+    UnknownField(String),
+    #[error("Empty value for field {0:?}")]
+    EmptyFilter(String),
+}
+
+/// A restriction on which entries can match, written `field:value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Some author's name contains the value.
+    Author(String),
+    /// The site title contains the value, or the URL's host is or is under it.
+    Site(String),
+    /// The entry carries this tag.
+    Tag(String),
+}
+
+impl Filter {
+    fn matches(&self, entry: &Entry) -> bool {
+        match self {
+            Filter::Author(a) => entry
+                .authors()
+                .iter()
+                .any(|name| name.to_lowercase().contains(a)),
+            Filter::Site(s) => {
+                let host = entry.url().host_str().unwrap_or_default();
+                let under = |h: &str| {
+                    h == s || h.strip_suffix(s.as_str()).is_some_and(|h| h.ends_with('.'))
+                };
+                // A site title that is only the host falls back to it, so it
+                // is matched the same way rather than by substring.
+                under(host) || (entry.site() != host && entry.site().to_lowercase().contains(s))
+            }
+            // Stored tags may predate lowercasing.
+            Filter::Tag(t) => entry
+                .tags()
+                .iter()
+                .any(|tag| tag.as_str().eq_ignore_ascii_case(t)),
+        }
+    }
+}
+
+/// A parsed search query: words and `"quoted phrases"` that must all occur,
+/// plus field filters.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Query {
+    /// Each term is a single word or the words of a phrase.
+    terms: Vec<Vec<String>>,
+    filters: Vec<Filter>,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let mut query = Query::default();
+        let mut rest = input.trim_start();
+        while !rest.is_empty() {
+            if let Some((field, tail)) = field_prefix(rest) {
+                let (value, _, tail) = next_token(tail)?;
+                rest = tail.trim_start();
+                let value = value.trim().to_lowercase();
+                if value.is_empty() {
+                    return Err(Error::EmptyFilter(field.to_owned()));
+                }
+                query
+                    .filters
+                    .push(match field.to_ascii_lowercase().as_str() {
+                        "author" => Filter::Author(value),
+                        "site" => Filter::Site(value),
+                        "tag" => Filter::Tag(value),
+                        _ => return Err(Error::UnknownField(field.to_owned())),
+                    });
+                continue;
+            }
+            let (token, quoted, tail) = next_token(rest)?;
+            rest = tail.trim_start();
+            let words = words(&token);
+            if quoted {
+                if !words.is_empty() {
+                    query.terms.push(words);
+                }
+            } else {
+                query.terms.extend(words.into_iter().map(|w| vec![w]));
+            }
+        }
+        Ok(query)
+    }
+}
+
+/// Split a leading `field:` off the input.
+fn field_prefix(input: &str) -> Option<(&str, &str)> {
+    let end = input.find(|c: char| !c.is_ascii_alphanumeric())?;
+    (end > 0 && input[end..].starts_with(':')).then(|| (&input[..end], &input[end + 1..]))
+}
+
+/// Split off the next whitespace-delimited token, or a whole `"quoted phrase"`.
+fn next_token(input: &str) -> Result<(String, bool, &str), Error> {
+    if let Some(quoted) = input.strip_prefix('"') {
+        let end = quoted.find('"').ok_or(Error::UnterminatedQuote)?;
+        return Ok((quoted[..end].to_owned(), true, &quoted[end + 1..]));
+    }
+    let end = input.find(char::is_whitespace).unwrap_or(input.len());
+    Ok((input[..end].to_owned(), false, &input[end..]))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Title,
+    Authors,
+    Tags,
+    Description,
+    FullText,
+}
+
+impl Field {
+    const ALL: [Field; 5] = [
+        Field::Title,
+        Field::Authors,
+        Field::Tags,
+        Field::Description,
+        Field::FullText,
+    ];
+
+    fn boost(self) -> f64 {
+        match self {
+            Field::Title => 3.0,
+            Field::Authors | Field::Tags => 2.0,
+            Field::Description => 1.5,
+            Field::FullText => 1.0,
+        }
+    }
+
+    fn values(self, entry: &Entry) -> Vec<&str> {
+        match self {
+            Field::Title => vec![entry.title()],
+            Field::Authors => entry.authors(),
+            Field::Tags => entry.tags().into_iter().map(|t| t.as_str()).collect(),
+            Field::Description => entry.description().into_iter().collect(),
+            Field::FullText => vec![entry.full_text()],
+        }
+    }
+}
+
+/// Postings for one field: term → document → word positions.
+#[derive(Default)]
+struct FieldIndex {
+    postings: HashMap<String, HashMap<usize, Vec<usize>>>,
+    lengths: Vec<usize>,
+    avg_len: f64,
+}
+
+impl FieldIndex {
+    /// Positions at which the phrase starts in a document.
+    fn occurrences(&self, phrase: &[String], doc: usize) -> usize {
+        let Some(first) = self.postings.get(&phrase[0]).and_then(|d| d.get(&doc)) else {
+            return 0;
+        };
+        first
+            .iter()
+            .filter(|&&start| {
+                phrase.iter().enumerate().skip(1).all(|(i, w)| {
+                    self.postings
+                        .get(w)
+                        .and_then(|d| d.get(&doc))
+                        .is_some_and(|p| p.binary_search(&(start + i)).is_ok())
+                })
+            })
+            .count()
+    }
+
+    fn candidates(&self, phrase: &[String]) -> impl Iterator<Item = usize> + '_ {
+        self.postings
+            .get(&phrase[0])
+            .into_iter()
+            .flat_map(|d| d.keys().copied())
+    }
+}
+
+/// An entry that matched a query, with its relevance score.
+#[derive(Debug)]
+pub struct Hit<'a> {
+    pub entry: &'a Entry,
+    pub score: f64,
+}
+
+/// An in-memory inverted index over title, authors, tags, description and
+/// `full_text`, ranked with BM25 weighted per field.
+pub struct Index<'a> {
+    entries: &'a [Entry],
+    fields: Vec<(Field, FieldIndex)>,
+}
+
+impl<'a> Index<'a> {
+    pub fn new(entries: &'a [Entry]) -> Self {
+        let fields = Field::ALL
+            .iter()
+            .map(|&field| {
+                let mut index = FieldIndex::default();
+                for (doc, entry) in entries.iter().enumerate() {
+                    let mut pos = 0;
+                    for value in field.values(entry) {
+                        for word in words(value) {
+                            index
+                                .postings
+                                .entry(word)
+                                .or_default()
+                                .entry(doc)
+                                .or_default()
+                                .push(pos);
+                            pos += 1;
+                        }
+                        pos += VALUE_GAP;
+                    }
+                    index.lengths.push(pos);
+                }
+                let total: usize = index.lengths.iter().sum();
+                index.avg_len = total as f64 / entries.len().max(1) as f64;
+                (field, index)
+            })
+            .collect();
+        Index { entries, fields }
+    }
+
+    /// Entries matching every filter and every term, best first. A query
+    /// with only filters returns the matching entries in their stored order.
+    pub fn search(&self, query: &Query) -> Vec<Hit<'a>> {
+        let n = self.entries.len() as f64;
+        let mut scores: Vec<Option<f64>> = self
+            .entries
+            .iter()
+            .map(|e| query.filters.iter().all(|f| f.matches(e)).then_some(0.0))
+            .collect();
+        for term in &query.terms {
+            let mut matched = vec![false; self.entries.len()];
+            for (field, index) in &self.fields {
+                let tfs: Vec<(usize, usize)> = index
+                    .candidates(term)
+                    .filter(|&doc| scores[doc].is_some())
+                    .map(|doc| (doc, index.occurrences(term, doc)))
+                    .filter(|&(_, tf)| tf > 0)
+                    .collect();
+                let df = tfs.len() as f64;
+                let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+                for (doc, tf) in tfs {
+                    let tf = tf as f64;
+                    let len_norm = 1.0 - B + B * index.lengths[doc] as f64 / index.avg_len;
+                    let bm25 = idf * tf * (K1 + 1.0) / (tf + K1 * len_norm);
+                    if let Some(score) = scores[doc].as_mut() {
+                        *score += field.boost() * bm25;
+                    }
+                    matched[doc] = true;
+                }
+            }
+            for (score, matched) in scores.iter_mut().zip(matched) {
+                if !matched {
+                    *score = None;
+                }
+            }
+        }
+        let mut hits: Vec<Hit> = scores
+            .into_iter()
+            .zip(self.entries)
+            .filter_map(|(score, entry)| score.map(|score| Hit { entry, score }))
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tag::Tag;
+    use url::Url;
+
+    fn entry(url: &str, title: &str, description: &str, tags: &[&str]) -> Entry {
+        Entry::unfetched(&Url::parse(url).unwrap(), Some(title.to_owned()))
+            .with_description(Some(description.to_owned()))
+            .with_tags(tags.iter().map(|t| Tag::try_from(*t).unwrap()))
+    }
+
+    fn corpus() -> Vec<Entry> {
+        vec![
+            entry(
+                "https://lwn.net/Articles/1",
+                "Rust in the kernel",
+                "Memory safety for drivers, written in Rust",
+                &["rust", "linux"],
+            ),
+            entry(
+                "https://go.dev/blog/gc",
+                "Garbage collection in Go",
+                "How the Go kernel of the runtime manages memory",
+                &["go"],
+            ),
+            entry(
+                "https://example.com/safety",
+                "A note on memory",
+                "Safety of memory in the kernel",
+                &[],
+            ),
+        ]
+    }
+
+    #[test]
+    fn title_matches_rank_first() {
+        let entries = corpus();
+        let hits = Index::new(&entries).search(&Query::parse("kernel").unwrap());
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].entry.url().as_str(), "https://lwn.net/Articles/1");
+    }
+
+    #[test]
+    fn phrases_require_adjacent_words() {
+        let entries = corpus();
+        let index = Index::new(&entries);
+        let hits = index.search(&Query::parse(r#""memory safety""#).unwrap());
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].entry.title(), "Rust in the kernel");
+        let hits = index.search(&Query::parse("memory safety").unwrap());
+        assert_eq!(hits.len(), 2);
+    }
+
+    #[test]
+    fn filters_restrict_matches() {
+        let entries = corpus();
+        let index = Index::new(&entries);
+        let hits = index.search(&Query::parse("memory tag:rust").unwrap());
+        assert_eq!(hits.len(), 1);
+        let hits = index.search(&Query::parse("site:go.dev").unwrap());
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].entry.title(), "Garbage collection in Go");
+        assert!(
+            index
+                .search(&Query::parse("site:o.dev").unwrap())
+                .is_empty()
+        );
+        assert_eq!(index.search(&Query::parse("site:dev").unwrap()).len(), 1);
+    }
+
+    #[test]
+    fn tag_filters_ignore_case() {
+        let mut json = serde_json::to_value(entry("https://a.example/", "A", "", &[])).unwrap();
+        json["tags"] = serde_json::json!(["Rust"]);
+        let entries = [serde_json::from_value(json).unwrap()];
+        let index = Index::new(&entries);
+        for query in ["tag:Rust", "tag:rust", "tag:RUST"] {
+            assert_eq!(
+                index.search(&Query::parse(query).unwrap()).len(),
+                1,
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn parses_quoted_filter_values() {
+        let query = Query::parse(r#"author:"Ada Lovelace" engines"#).unwrap();
+        assert_eq!(query.filters, [Filter::Author("ada lovelace".into())]);
+        assert_eq!(query.terms, [vec!["engines".to_owned()]]);
+        assert_eq!(
+            Query::parse("colour:red"),
+            Err(Error::UnknownField("colour".into()))
+        );
+        assert_eq!(Query::parse(r#""open"#), Err(Error::UnterminatedQuote));
+    }
+}
@@ -0,0 +1,581 @@
+use std::{collections::HashMap, fmt::Write};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use ego_tree::NodeRef;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use scraper::{Html, Node, Selector};
+use url::Url;
+
+use crate::{
+    entry::{Entry, Error},
+    fetch::{Fetcher, MAX_BODY},
+};
+
+/// Default budget for inlined resources, in bytes of encoded data.
+pub const DEFAULT_BUDGET: usize = 10 * 1024 * 1024;
+/// How deeply `@import`ed stylesheets are followed.
+const MAX_IMPORT_DEPTH: usize = 3;
+/// How deeply frames within frames are inlined.
+const MAX_FRAME_DEPTH: usize = 2;
+
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+/// Elements dropped with their contents; scripts cannot run in a snapshot.
+const DROPPED_ELEMENTS: &[&str] = &["script", "noscript", "template"];
+/// `<link rel>` values that only matter to a live page.
+const DROPPED_LINKS: &[&str] = &[
+    "preload",
+    "modulepreload",
+    "prefetch",
+    "preconnect",
+    "dns-prefetch",
+    "prerender",
+    "manifest",
+];
+
+static CSS_URL: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)'"\s]*))\s*\)"#)
+        .expect("CSS url() pattern is valid")
+});
+static CSS_IMPORT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"@import\s+(?:url\(\s*)?["']?([^"')\s;]+)["']?\s*\)?[^;]*;"#)
+        .expect("CSS @import pattern is valid")
+});
+
+/// A fetched sub-resource: its media type and bytes.
+pub struct Resource {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Fetch a sub-resource through `fetcher`, giving up once it exceeds `limit` bytes.
+pub fn fetch_resource(fetcher: &dyn Fetcher, url: &Url, limit: usize) -> Option<Resource> {
+    let (_, response) = fetcher.follow(url, limit).ok()?;
+    Some(Resource {
+        mime: response
+            .mime()
+            .unwrap_or_else(|| "application/octet-stream".to_owned()),
+        data: response.body,
+    })
+}
+
+/// Fetch a page and inline it against the URL it was finally served from,
+/// so redirected pages keep their relative resources and links. With
+/// `reader`, a reading view of the page is inlined too. Each gets its own
+/// `budget`.
+pub fn take(
+    fetcher: &dyn Fetcher,
+    url: &Url,
+    budget: usize,
+    reader: bool,
+) -> Result<(String, Option<String>), Box<Error>> {
+    let (url, response) = fetcher.follow(url, MAX_BODY)?;
+    let body = String::from_utf8_lossy(&response.body);
+    let fetch = |url: &Url, limit| fetch_resource(fetcher, url, limit);
+    let page = Inliner::new(budget, fetch).inline(&body, &url);
+    let reader = reader.then(|| {
+        let entry = Entry::from_html(fetcher, &url, &body, None);
+        Inliner::new(budget, fetch).inline(&reader_html(&entry), &url)
+    });
+    Ok((page, reader))
+}
+
+/// Turns a page into a single standalone HTML file: stylesheets and images
+/// are inlined as data URIs and frames as `srcdoc` while the budget lasts,
+/// scripts and event handlers are stripped, and remaining links are made
+/// absolute. Frames that cannot be inlined are dropped.
+pub struct Inliner<F> {
+    fetch: F,
+    budget: usize,
+    /// Data URIs (or `None` for failures) by resource URL.
+    cache: HashMap<Url, Option<String>>,
+    /// Frames being inlined around the current document.
+    frame_depth: usize,
+}
+
+impl<F: Fn(&Url, usize) -> Option<Resource>> Inliner<F> {
+    /// `fetch` is called with each resource URL and the bytes left in the budget.
+    pub fn new(budget: usize, fetch: F) -> Self {
+        Inliner {
+            fetch,
+            budget,
+            cache: HashMap::new(),
+            frame_depth: 0,
+        }
+    }
+
+    /// Inline the document at `url`. Relative URLs are resolved against its
+    /// first `<base href>`, which is then dropped, or else against `url`.
+    pub fn inline(&mut self, html: &str, url: &Url) -> String {
+        let doc = Html::parse_document(html);
+        let sel = Selector::parse("base[href]").expect("valid selector");
+        let base = doc
+            .select(&sel)
+            .next()
+            .and_then(|b| url.join(b.value().attr("href")?.trim()).ok())
+            .unwrap_or_else(|| url.clone());
+        let base = &base;
+        let mut out = String::new();
+        for child in doc.tree.root().children() {
+            self.write_node(&mut out, child, base);
+        }
+        out
+    }
+
+    fn write_node(&mut self, out: &mut String, node: NodeRef<Node>, base: &Url) {
+        match node.value() {
+            Node::Doctype(d) => {
+                let _ = write!(out, "<!DOCTYPE {}>", d.name());
+            }
+            Node::Text(t) => {
+                let raw = node
+                    .parent()
+                    .and_then(|p| p.value().as_element())
+                    .is_some_and(|e| e.name() == "style");
+                if raw {
+                    out.push_str(&self.css(t, base, 0));
+                } else {
+                    out.push_str(&escape(t, false));
+                }
+            }
+            Node::Element(el) => {
+                let name = el.name();
+                if DROPPED_ELEMENTS.contains(&name) || self.dropped(name, el) {
+                    return;
+                }
+                if name == "link" && rel_has(el, "stylesheet") {
+                    self.write_stylesheet(out, el, base);
+                    return;
+                }
+                if name == "iframe" {
+                    self.write_frame(out, el, base);
+                    return;
+                }
+                let _ = write!(out, "<{name}");
+                if name == "head" {
+                    out.push('>');
+                    out.push_str(r#"<meta charset="utf-8">"#);
+                } else {
+                    self.write_attrs(out, el, base);
+                    out.push('>');
+                }
+                if VOID_ELEMENTS.contains(&name) {
+                    return;
+                }
+                for child in node.children() {
+                    self.write_node(out, child, base);
+                }
+                let _ = write!(out, "</{name}>");
+            }
+            Node::Document | Node::Fragment => {
+                for child in node.children() {
+                    self.write_node(out, child, base);
+                }
+            }
+            Node::Comment(_) | Node::ProcessingInstruction(_) => {}
+        }
+    }
+
+    /// Elements that are pointless or harmful once the page is standalone.
+    fn dropped(&self, name: &str, el: &scraper::node::Element) -> bool {
+        match name {
+            "base" => true,
+            // `<picture>` falls back to its `<img>`, which is inlined.
+            "source" => el.attr("srcset").is_some(),
+            "link" => DROPPED_LINKS.iter().any(|r| rel_has(el, r)),
+            "meta" => {
+                el.attr("http-equiv").is_some_and(|h| {
+                    h.eq_ignore_ascii_case("refresh")
+                        || h.eq_ignore_ascii_case("content-security-policy")
+                        || h.eq_ignore_ascii_case("content-type")
+                }) || el.attr("charset").is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn write_attrs(&mut self, out: &mut String, el: &scraper::node::Element, base: &Url) {
+        let name = el.name();
+        let lazy_src = el.attr("data-src").or_else(|| el.attr("data-lazy-src"));
+        let icon = name == "link" && (rel_has(el, "icon") || rel_has(el, "apple-touch-icon"));
+        for (attr, value) in el.attrs() {
+            let lower = attr.to_ascii_lowercase();
+            if lower.starts_with("on")
+                || matches!(
+                    lower.as_str(),
+                    "srcset" | "sizes" | "integrity" | "nonce" | "loading"
+                )
+            {
+                continue;
+            }
+            if name == "img"
+                && lazy_src.is_some()
+                && matches!(lower.as_str(), "src" | "data-src" | "data-lazy-src")
+            {
+                continue;
+            }
+            let value = match (name, lower.as_str()) {
+                ("img" | "input", "src") | ("video", "poster") | ("link", "href")
+                    if name != "link" || icon =>
+                {
+                    self.data_uri(value, base)
+                        .unwrap_or_else(|| absolute(value, base))
+                }
+                (_, "style") => self.css(value, base, 0),
+                (_, "href" | "action" | "src" | "cite" | "poster") => {
+                    if value
+                        .trim_start()
+                        .to_ascii_lowercase()
+                        .starts_with("javascript:")
+                    {
+                        continue;
+                    }
+                    absolute(value, base)
+                }
+                _ => value.to_owned(),
+            };
+            let _ = write!(out, r#" {attr}="{}""#, escape(&value, true));
+        }
+        if name == "img"
+            && let Some(src) = lazy_src
+        {
+            let src = self
+                .data_uri(src, base)
+                .unwrap_or_else(|| absolute(src, base));
+            let _ = write!(out, r#" src="{}""#, escape(&src, true));
+        }
+    }
+
+    /// Write a frame with its page inlined as `srcdoc`, or nothing if the
+    /// page cannot be fetched or frames are nested too deeply.
+    fn write_frame(&mut self, out: &mut String, el: &scraper::node::Element, base: &Url) {
+        if self.frame_depth >= MAX_FRAME_DEPTH {
+            return;
+        }
+        // `srcdoc` wins over `src`, and its relative URLs resolve as the
+        // parent's do.
+        let (html, url) = match (el.attr("srcdoc"), el.attr("src")) {
+            (Some(html), _) => (html.to_owned(), base.clone()),
+            (None, Some(src)) => {
+                let Some(url) = base
+                    .join(src.trim())
+                    .ok()
+                    .filter(|u| matches!(u.scheme(), "http" | "https"))
+                else {
+                    return;
+                };
+                let Some(resource) = (self.fetch)(&url, self.budget)
+                    .filter(|r| r.mime.contains("html") && r.data.len() <= self.budget)
+                else {
+                    return;
+                };
+                self.budget -= resource.data.len();
+                (String::from_utf8_lossy(&resource.data).into_owned(), url)
+            }
+            (None, None) => return,
+        };
+        self.frame_depth += 1;
+        let page = self.inline(&html, &url);
+        self.frame_depth -= 1;
+        out.push_str("<iframe");
+        for (attr, value) in el.attrs() {
+            let lower = attr.to_ascii_lowercase();
+            if lower.starts_with("on") || matches!(lower.as_str(), "src" | "srcdoc" | "loading") {
+                continue;
+            }
+            let _ = write!(out, r#" {attr}="{}""#, escape(value, true));
+        }
+        let _ = write!(out, r#" srcdoc="{}"></iframe>"#, escape(&page, true));
+    }
+
+    fn write_stylesheet(&mut self, out: &mut String, el: &scraper::node::Element, base: &Url) {
+        let Some(url) = el.attr("href").and_then(|h| base.join(h).ok()) else {
+            return;
+        };
+        let css = self.fetch_css(&url, 0);
+        match el.attr("media") {
+            Some(media) => {
+                let _ = write!(out, r#"<style media="{}">"#, escape(media, true));
+            }
+            None => out.push_str("<style>"),
+        }
+        match css {
+            Some(css) => out.push_str(&css),
+            // Keep the external stylesheet rather than lose it.
+            None => {
+                let _ = write!(
+                    out,
+                    "@import url(\"{}\");",
+                    url.as_str().replace('"', "%22")
+                );
+            }
+        }
+        out.push_str("</style>");
+    }
+
+    fn fetch_css(&mut self, url: &Url, depth: usize) -> Option<String> {
+        let resource = (self.fetch)(url, self.budget)?;
+        self.budget = self.budget.saturating_sub(resource.data.len());
+        let css = String::from_utf8_lossy(&resource.data).into_owned();
+        Some(self.css(&css, url, depth))
+    }
+
+    /// Inline `@import`s and `url()` references in a stylesheet.
+    fn css(&mut self, css: &str, base: &Url, depth: usize) -> String {
+        let css = if depth < MAX_IMPORT_DEPTH {
+            CSS_IMPORT
+                .replace_all(css, |c: &Captures| {
+                    base.join(&c[1])
+                        .ok()
+                        .and_then(|url| self.fetch_css(&url, depth + 1))
+                        .unwrap_or_else(|| c[0].to_owned())
+                })
+                .into_owned()
+        } else {
+            css.to_owned()
+        };
+        CSS_URL
+            .replace_all(&css, |c: &Captures| {
+                let target = c
+                    .get(1)
+                    .or(c.get(2))
+                    .or(c.get(3))
+                    .map_or("", |m| m.as_str());
+                let inlined = self
+                    .data_uri(target, base)
+                    .unwrap_or_else(|| absolute(target, base));
+                format!("url(\"{}\")", inlined.replace('"', "%22"))
+            })
+            .into_owned()
+    }
+
+    /// A data URI for the resource, if it fits in what is left of the budget.
+    fn data_uri(&mut self, reference: &str, base: &Url) -> Option<String> {
+        if reference.starts_with("data:") {
+            return Some(reference.to_owned());
+        }
+        let url = base.join(reference.trim()).ok()?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        if let Some(cached) = self.cache.get(&url) {
+            return cached.clone();
+        }
+        // Base64 grows data by a third.
+        let limit = self.budget / 4 * 3;
+        let uri = (self.fetch)(&url, limit)
+            .filter(|r| r.data.len() <= limit)
+            .map(|r| format!("data:{};base64,{}", r.mime, STANDARD.encode(&r.data)));
+        if let Some(uri) = &uri {
+            self.budget = self.budget.saturating_sub(uri.len());
+        }
+        self.cache.insert(url, uri.clone());
+        uri
+    }
+}
+
+/// A plain reading view of an entry: its title, byline and thumbnail above
+/// the readability text, ready to be inlined.
+pub fn reader_html(entry: &Entry) -> String {
+    let mut byline: Vec<String> = Vec::new();
+    if !entry.authors().is_empty() {
+        byline.push(entry.authors().join(", "));
+    }
+    byline.push(entry.site().to_owned());
+    if let Some(published) = entry.published_date() {
+        byline.push(published.to_string());
+    }
+    let thumbnail = entry.thumbnail().map_or(String::new(), |t| {
+        format!(
+            r#"<img class="thumbnail" src="{}" alt="">"#,
+            escape(t.as_str(), true)
+        )
+    });
+    format!(
+        r#"<!DOCTYPE html><html><head><title>{title}</title><style>{READER_CSS}</style></head><body><article><header><h1>{title}</h1><p class="byline">{byline}</p><p class="source"><a href="{url}">{url}</a></p>{thumbnail}</header>{text}</article></body></html>"#,
+        title = escape(entry.title(), false),
+        byline = escape(&byline.join(" · "), false),
+        url = escape(entry.url().as_str(), true),
+        text = entry.full_text(),
+    )
+}
+
+const READER_CSS: &str = "body{max-width:40em;margin:2em auto;padding:0 1em;font:1.1em/1.6 Georgia,serif;color:#222}\
+img{max-width:100%;height:auto}.byline,.source{color:#666;font-size:.9em}pre{overflow:auto}";
+
+fn rel_has(el: &scraper::node::Element, rel: &str) -> bool {
+    el.attr("rel").is_some_and(|r| {
+        r.split_ascii_whitespace()
+            .any(|t| t.eq_ignore_ascii_case(rel))
+    })
+}
+
+fn absolute(reference: &str, base: &Url) -> String {
+    if reference.starts_with('#') || reference.starts_with("data:") {
+        return reference.to_owned();
+    }
+    base.join(reference.trim())
+        .map_or_else(|_| reference.to_owned(), String::from)
+}
+
+fn escape(s: &str, attr: bool) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' if !attr => out.push_str("&lt;"),
+            '>' if !attr => out.push_str("&gt;"),
+            '"' if attr => out.push_str("&quot;"),
+            '\u{a0}' => out.push_str("&nbsp;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::{Method, Response};
+
+    const PAGE: &str = r#"<!DOCTYPE html><html><head>
+        <meta charset="iso-8859-1"><base href="/other/">
+        <link rel="stylesheet" href="/site.css" media="screen">
+        <link rel="preload" href="/font.woff2">
+        <script src="/app.js"></script><script>alert("hi")</script>
+        </head><body onload="track()">
+        <p style="background: url('bg.png')">Tom &amp; Jerry &lt;3</p>
+        <img src="/logo.png" srcset="/logo@2x.png 2x" alt="Logo">
+        <img src="data:image/gif;base64,R0lGOD" data-src="/lazy.png">
+        <img src="/huge.png">
+        <a href="/next" onclick="go()">Next</a> <a href="javascript:void(0)">JS</a>
+        <a href="more#part">More</a>
+        <iframe src="frame.html" onload="x()" title="Map"></iframe>
+        <iframe src="https://ads.example/slot"></iframe>
+        </body></html>"#;
+
+    fn fetch(url: &Url, limit: usize) -> Option<Resource> {
+        let (mime, data): (&str, Vec<u8>) = match url.path() {
+            "/site.css" => (
+                "text/css",
+                b"@import 'print.css'; h1 { background: url(/h1.png) }".to_vec(),
+            ),
+            "/print.css" => ("text/css", b"p { color: red }".to_vec()),
+            "/huge.png" => ("image/png", vec![0; 4096]),
+            // Links itself, to check that nesting stops.
+            "/other/frame.html" => (
+                "text/html",
+                br#"<p>Framed <img src="pic.png"></p><iframe src="frame.html"></iframe>"#.to_vec(),
+            ),
+            "/logo.png" | "/lazy.png" | "/h1.png" | "/other/bg.png" | "/other/pic.png" => {
+                ("image/png", b"png".to_vec())
+            }
+            _ => return None,
+        };
+        (data.len() <= limit).then(|| Resource {
+            mime: mime.to_owned(),
+            data,
+        })
+    }
+
+    fn snapshot(budget: usize) -> String {
+        let base = Url::parse("https://example.com/a/page").unwrap();
+        Inliner::new(budget, fetch).inline(PAGE, &base)
+    }
+
+    #[test]
+    fn strips_scripts_and_handlers() {
+        let html = snapshot(DEFAULT_BUDGET);
+        assert!(!html.contains("script"));
+        assert!(!html.contains("onload") && !html.contains("onclick"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("<base") && !html.contains("preload"));
+        assert!(html.contains(r#"<a href="https://example.com/next">Next</a>"#));
+        assert!(html.contains(r#"<a href="https://example.com/other/more#part">More</a>"#));
+        assert!(html.contains("Tom &amp; Jerry &lt;3"));
+        assert!(html.contains(r#"<head><meta charset="utf-8">"#));
+        assert!(!html.contains("iso-8859-1"));
+    }
+
+    #[test]
+    fn inlines_stylesheets_and_images() {
+        let html = snapshot(DEFAULT_BUDGET);
+        let png = format!("data:image/png;base64,{}", STANDARD.encode(b"png"));
+        assert!(html.contains(r#"<style media="screen">p { color: red } h1 { background: url(""#));
+        assert!(html.contains(&format!(r#"<img alt="Logo" src="{png}">"#)));
+        assert!(html.contains(&format!(r#"background: url(&quot;{png}&quot;)"#)));
+        assert!(!html.contains("srcset") && !html.contains("data-src"));
+        assert!(html.contains(&format!(r#"src="{png}">"#)));
+    }
+
+    #[test]
+    fn inlines_frames_or_drops_them() {
+        let html = snapshot(DEFAULT_BUDGET);
+        let png = format!("data:image/png;base64,{}", STANDARD.encode(b"png"));
+        assert!(html.contains(r#"<iframe title="Map" srcdoc=""#));
+        assert!(html.contains(&format!("<img src=&quot;{png}&quot;>")));
+        // The frame's own frame is inlined once more, then dropped.
+        assert_eq!(html.matches("srcdoc=").count(), 2);
+        assert!(!html.contains("frame.html") && !html.contains("ads.example"));
+        assert!(!html.contains("onload"));
+    }
+
+    #[test]
+    fn leaves_links_once_budget_is_spent() {
+        let html = snapshot(200);
+        assert!(html.contains(r#"<img src="https://example.com/huge.png">"#));
+        let full = snapshot(DEFAULT_BUDGET);
+        assert!(!full.contains("https://example.com/huge.png"));
+    }
+
+    /// Serves `http://example.com/post` by redirecting to the canonical
+    /// `https://example.com/post/`.
+    struct Redirecting;
+
+    impl Fetcher for Redirecting {
+        fn send(
+            &self,
+            _: Method,
+            url: &Url,
+            _: &[(&str, &str)],
+            _: usize,
+        ) -> Result<Response, ureq::Error> {
+            let (status, headers, body): (u16, &[(&str, &str)], &[u8]) = match url.as_str() {
+                "http://example.com/post" => {
+                    (301, &[("location", "https://example.com/post/")], b"")
+                }
+                "https://example.com/post/" => (
+                    200,
+                    &[("content-type", "text/html")],
+                    br#"<p><img src="pic.png"> <a href="next">Next</a></p>"#,
+                ),
+                "https://example.com/post/pic.png" => {
+                    (200, &[("content-type", "image/png")], b"png")
+                }
+                _ => (404, &[], b""),
+            };
+            Ok(Response {
+                status,
+                headers: headers
+                    .iter()
+                    .map(|(n, v)| (n.to_string(), v.to_string()))
+                    .collect(),
+                body: body.to_vec(),
+            })
+        }
+    }
+
+    #[test]
+    fn redirected_pages_resolve_against_where_they_landed() {
+        let url = Url::parse("http://example.com/post").unwrap();
+        let (page, reader) = take(&Redirecting, &url, DEFAULT_BUDGET, true).unwrap();
+        let png = format!("data:image/png;base64,{}", STANDARD.encode(b"png"));
+        assert!(page.contains(&format!(r#"<img src="{png}">"#)), "{page}");
+        assert!(page.contains(r#"<a href="https://example.com/post/next">"#));
+        let reader = reader.unwrap();
+        assert!(reader.contains("https://example.com/post/") && !reader.contains("http://"));
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::entry::Entry;
+
+/// Query parameters that only track where a visitor came from.
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid"];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to access store {path}: {error}")]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    #[error("Corrupt record in store {path} on line {line}: {error}")]
+    Corrupt {
+        path: PathBuf,
+        line: usize,
+        error: serde_json::Error,
+    },
+    #[error("Failed to encode record: {0}")]
+    Encode(#[from] serde_json::Error),
+    #[error("No entry matches {0}")]
+    NotFound(String),
+    #[error("{key} is ambiguous; it matches {count} entries")]
+    Ambiguous { key: String, count: usize },
+    #[error("Could not determine the data directory; set XDG_DATA_HOME or HOME, or pass --store")]
+    NoDataDir,
+}
+
+/// One line of the store's log. Later records supersede earlier ones.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Record {
+    Put(Box<Entry>),
+    Remove {
+        id: Uuid,
+    },
+    /// A re-extracted entry, with the names of the fields that changed.
+    Refresh {
+        entry: Box<Entry>,
+        changed: Vec<String>,
+    },
+}
+
+/// A local bookmark store backed by an append-only JSONL log. Every change
+/// appends a record; opening the store replays the log.
+pub struct Store {
+    path: PathBuf,
+    entries: Vec<Entry>,
+}
+
+impl Store {
+    /// `$XDG_DATA_HOME/spy/entries.jsonl`, falling back to `~/.local/share`.
+    pub fn default_path() -> Result<PathBuf, Error> {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".local/share")))
+            .map(|d| d.join("spy").join("entries.jsonl"))
+            .ok_or(Error::NoDataDir)
+    }
+
+    /// Open the store at `path`. A missing file is an empty store.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, Error> {
+        let path = path.into();
+        let mut store = Store {
+            path,
+            entries: Vec::new(),
+        };
+        let file = match File::open(&store.path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(store),
+            Err(error) => return Err(store.io(error)),
+        };
+        for (idx, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|e| store.io(e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Record = serde_json::from_str(&line).map_err(|error| Error::Corrupt {
+                path: store.path.clone(),
+                line: idx + 1,
+                error,
+            })?;
+            store.apply(record);
+        }
+        Ok(store)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entries in the order they were first added.
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Look an entry up by URL (compared after normalisation), full ID or a
+    /// unique ID prefix.
+    pub fn get(&self, key: &str) -> Result<&Entry, Error> {
+        if let Ok(url) = Url::parse(key) {
+            let wanted = normalise_url(&url);
+            return self
+                .entries
+                .iter()
+                .find(|e| normalise_url(e.url()) == wanted)
+                .ok_or_else(|| Error::NotFound(key.to_owned()));
+        }
+        let prefix = key.trim().to_ascii_lowercase().replace('-', "");
+        if prefix.is_empty() {
+            return Err(Error::NotFound(key.to_owned()));
+        }
+        let matches: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|e| e.id().simple().to_string().starts_with(&prefix))
+            .collect();
+        match matches.as_slice() {
+            [entry] => Ok(entry),
+            [] => Err(Error::NotFound(key.to_owned())),
+            _ => Err(Error::Ambiguous {
+                key: key.to_owned(),
+                count: matches.len(),
+            }),
+        }
+    }
+
+    /// Insert an entry, or replace the stored entry with the same ID.
+    pub fn put(&mut self, entry: Entry) -> Result<(), Error> {
+        let record = Record::Put(Box::new(entry));
+        self.append(&record)?;
+        self.apply(record);
+        Ok(())
+    }
+
+    /// Replace a stored entry with a re-extracted one, recording which fields
+    /// changed.
+    pub fn refresh(&mut self, entry: Entry, changed: &[&str]) -> Result<(), Error> {
+        let record = Record::Refresh {
+            entry: Box::new(entry),
+            changed: changed.iter().map(|&c| c.to_owned()).collect(),
+        };
+        self.append(&record)?;
+        self.apply(record);
+        Ok(())
+    }
+
+    /// Remove the entry with the given ID, returning it.
+    pub fn remove(&mut self, id: &Uuid) -> Result<Entry, Error> {
+        let idx = self
+            .entries
+            .iter()
+            .position(|e| e.id() == id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        self.append(&Record::Remove { id: *id })?;
+        Ok(self.entries.remove(idx))
+    }
+
+    fn apply(&mut self, record: Record) {
+        match record {
+            Record::Put(entry) | Record::Refresh { entry, .. } => {
+                let entry = *entry;
+                match self.entries.iter_mut().find(|e| e.id() == entry.id()) {
+                    Some(existing) => *existing = entry,
+                    None => self.entries.push(entry),
+                }
+            }
+            Record::Remove { id } => self.entries.retain(|e| e.id() != &id),
+        }
+    }
+
+    fn append(&self, record: &Record) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| self.io(e))?;
+        }
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(line.as_bytes()))
+            .map_err(|e| self.io(e))
+    }
+
+    fn io(&self, error: std::io::Error) -> Error {
+        Error::Io {
+            path: self.path.clone(),
+            error,
+        }
+    }
+}
+
+/// Read entries from JSONL, such as the default output of earlier `spy` runs.
+/// `path` names the source in errors.
+pub fn read_entries(reader: impl BufRead, path: &Path) -> Result<Vec<Entry>, Error> {
+    let mut entries = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.map_err(|error| Error::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        entries.push(serde_json::from_str(&line).map_err(|error| Error::Corrupt {
+            path: path.to_path_buf(),
+            line: idx + 1,
+            error,
+        })?);
+    }
+    Ok(entries)
+}
+
+/// Normalise a URL for identity comparisons: drop the fragment, `utm_*` and
+/// other tracking parameters, sort the remaining query, and drop a trailing
+/// slash from non-root paths. Scheme and host are already lowercased by [`Url`].
+pub fn normalise_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    url.set_fragment(None);
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| {
+            let k = k.to_ascii_lowercase();
+            !k.starts_with("utm_") && !TRACKING_PARAMS.contains(&k.as_str())
+        })
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    pairs.sort();
+    if pairs.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    let path = url.path();
+    if path.len() > 1 && path.ends_with('/') {
+        let trimmed = path.trim_end_matches('/').to_owned();
+        url.set_path(&trimmed);
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::entry_strategy;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn replaying_the_log_restores_entries(
+            entries in prop::collection::vec(entry_strategy(), 1..6),
+            remove in any::<prop::sample::Index>(),
+        ) {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("nested").join("entries.jsonl");
+            let mut store = Store::open(&path).unwrap();
+            for entry in &entries {
+                store.put(entry.clone()).unwrap();
+            }
+            let removed = remove.get(&entries).id();
+            store.remove(removed).unwrap();
+
+            let reopened = Store::open(&path).unwrap();
+            let ids: Vec<&Uuid> = reopened.entries().iter().map(Entry::id).collect();
+            let expected: Vec<&Uuid> = store.entries().iter().map(Entry::id).collect();
+            prop_assert_eq!(ids, expected);
+            prop_assert!(reopened.get(&removed.to_string()).is_err());
+
+            if let Some(kept) = store.entries().first().cloned() {
+                let changed = kept.clone().with_notes(Some("refreshed".into()));
+                store.refresh(changed, &["notes"]).unwrap();
+                let reopened = Store::open(&path).unwrap();
+                let refreshed = reopened.get(&kept.id().to_string()).unwrap();
+                prop_assert_eq!(refreshed.notes(), Some("refreshed"));
+                prop_assert_eq!(reopened.entries().len(), store.entries().len());
+            }
+        }
+    }
+
+    #[test]
+    fn entries_are_found_by_url_or_id_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = Store::open(dir.path().join("entries.jsonl")).unwrap();
+        let url = Url::parse("https://lwn.net/Articles/1/?utm_source=rss#comments").unwrap();
+        let entry = Entry::unfetched(&url, Some("LWN".into()));
+        let id = *entry.id();
+        store.put(entry).unwrap();
+
+        assert_eq!(store.get("https://lwn.net/Articles/1").unwrap().id(), &id);
+        assert_eq!(store.get(&id.to_string()[..8]).unwrap().id(), &id);
+        assert!(matches!(
+            store.get("https://lwn.net/Articles/2"),
+            Err(Error::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn normalisation_ignores_tracking_and_ordering() {
+        let a = Url::parse("https://Example.com/a/?b=2&utm_medium=x&a=1#frag").unwrap();
+        let b = Url::parse("https://example.com/a?a=1&b=2&fbclid=abc").unwrap();
+        assert_eq!(normalise_url(&a), normalise_url(&b));
+        let root = Url::parse("https://example.com/").unwrap();
+        assert_eq!(normalise_url(&root).as_str(), "https://example.com/");
+    }
+}
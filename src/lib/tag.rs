@@ -1,13 +1,45 @@
+use std::fmt::Display;
+
 use once_cell::sync::Lazy;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::text::fold_ascii;
+
 pub static TAG_RE: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"^[a-zA-Z0-9_-]{1,30}$").expect("Tag regex is invalid!"));
 
-#[derive(Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Tag(String);
 
+impl Tag {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Make a tag out of free text from another application, such as
+    /// `to read` or `Café`: accents are dropped and other runs of characters
+    /// a tag can't hold become `-`. `None` if nothing usable is left.
+    pub fn normalised(text: &str) -> Option<Self> {
+        let mut tag = String::new();
+        for c in fold_ascii(text).chars() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                tag.push(c.to_ascii_lowercase());
+            } else if !tag.is_empty() && !tag.ends_with('-') {
+                tag.push('-');
+            }
+        }
+        let tag: String = tag.trim_end_matches('-').chars().take(30).collect();
+        Tag::try_from(tag.trim_end_matches('-')).ok()
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 impl TryFrom<&&str> for Tag {
     type Error = ();
     fn try_from(tag: &&str) -> Result<Self, Self::Error> {
@@ -30,3 +62,18 @@ impl TryFrom<&str> for Tag {
         Self::try_from(&tag)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_text_is_normalised() {
+        let normalised = |s| Tag::normalised(s).map(|t| t.to_string());
+        assert_eq!(normalised("to read"), Some("to-read".into()));
+        assert_eq!(normalised(" Café / Bar! "), Some("cafe-bar".into()));
+        assert_eq!(normalised("Rust"), Some("rust".into()));
+        assert_eq!(normalised("東京"), None);
+        assert!(normalised(&"long ".repeat(20)).unwrap().as_str().len() <= 30);
+    }
+}
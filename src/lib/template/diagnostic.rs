@@ -0,0 +1,240 @@
+//! Template errors located in their source, in the style of compiler errors.
+
+use std::fmt;
+
+use minijinja::ErrorKind;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::{Context, ENVIRONMENT, is_known, known_variables};
+
+/// A variable or attribute path, such as `citation.doi`.
+static PATH: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*(\.[A-Za-z_][A-Za-z0-9_]*)*$").unwrap());
+
+/// What went wrong in a template and where.
+#[derive(Debug)]
+pub struct Diagnostic {
+    message: String,
+    location: Option<Location>,
+    /// The variable or attribute that was undefined, if that was the error.
+    undefined: Option<String>,
+    help: Option<String>,
+}
+
+/// The offending part of a template line.
+#[derive(Debug)]
+struct Location {
+    name: String,
+    line: usize,
+    /// One-based, in characters.
+    column: usize,
+    text: String,
+    /// Characters to underline, at least one.
+    width: usize,
+}
+
+impl Diagnostic {
+    /// Describe `error`, raised by the template `name` with the given source.
+    /// Errors in library and included templates are located in their own
+    /// source.
+    pub(crate) fn new(
+        error: &minijinja::Error,
+        name: &str,
+        source: Option<&str>,
+        context: Context,
+    ) -> Self {
+        let loaded;
+        let (name, source) = match (error.name(), source) {
+            (Some(n), Some(source)) if n == name => (name, Some(source)),
+            (None, source) => (name, source),
+            (Some(n), _) => {
+                loaded = ENVIRONMENT.get_template(n).ok();
+                (n, loaded.as_ref().map(|t| t.source()))
+            }
+        };
+        let location = match (source, error.line(), error.range()) {
+            (Some(source), Some(line), Some(range)) => {
+                Some(Location::new(name, source, line, range))
+            }
+            _ => None,
+        };
+        let expression = source
+            .zip(error.range())
+            .and_then(|(source, range)| source.get(range));
+        let undefined = match (error.kind(), expression) {
+            (ErrorKind::UndefinedError, Some(e)) if PATH.is_match(e) => Some(e.to_owned()),
+            _ => None,
+        };
+        let message = match (&undefined, error.detail()) {
+            (Some(path), _) if path.contains('.') => format!("undefined attribute `{path}`"),
+            (Some(path), _) => format!("undefined variable `{path}`"),
+            (None, Some(detail)) => format!("{}: {detail}", error.kind()),
+            (None, None) => error.kind().to_string(),
+        };
+        let help = undefined.as_deref().and_then(|path| help(path, context));
+        Self {
+            message,
+            location,
+            undefined,
+            help,
+        }
+    }
+
+    /// The undefined variable or attribute path, such as `titel` or
+    /// `citation.doi`.
+    pub fn undefined(&self) -> Option<&str> {
+        self.undefined.as_deref()
+    }
+}
+
+impl Location {
+    fn new(name: &str, source: &str, line: usize, range: std::ops::Range<usize>) -> Self {
+        let text = source.lines().nth(line - 1).unwrap_or_default();
+        // The range is in bytes from the start of the source.
+        let start = source[..range.start.min(source.len())]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        let offset = range.start.saturating_sub(start).min(text.len());
+        let end = range.end.saturating_sub(start).clamp(offset, text.len());
+        let before = text.get(..offset).unwrap_or_default();
+        let underlined = text.get(offset..end).unwrap_or_default();
+        Self {
+            name: name.to_owned(),
+            line,
+            column: before.chars().count() + 1,
+            text: text.to_owned(),
+            width: underlined.chars().count().max(1),
+        }
+    }
+}
+
+/// Advice on an undefined `path`: a field this entry lacks, or the
+/// closest-spelled known variable.
+fn help(path: &str, context: Context) -> Option<String> {
+    let (head, attribute) = match path.split_once('.') {
+        Some((head, attribute)) => (head, Some(attribute)),
+        None => (path, None),
+    };
+    if head == "meta" && context == Context::Entry {
+        // `meta.x` is reported whole whether `meta` or only `x` is missing.
+        return Some(
+            "`meta` holds only what the page had, and only when fetched with `--include-raw-meta`"
+                .to_owned(),
+        );
+    }
+    if is_known(head, context) {
+        return attribute.is_none().then(|| {
+            format!("this entry has no `{head}`; check with `{{% if {head} is defined %}}`")
+        });
+    }
+    let suggestion = known_variables(context)
+        .map(|known| (distance(head, known), known))
+        .filter(|(d, _)| *d <= head.chars().count().div_ceil(3))
+        .min()?
+        .1;
+    Some(match attribute {
+        Some(attribute) => format!("did you mean `{suggestion}.{attribute}`?"),
+        None => format!("did you mean `{suggestion}`?"),
+    })
+}
+
+/// Edit distance in characters, counting swapped neighbours as one edit
+/// so `titel` is closer to `title` than to `site`.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(location) = &self.location {
+            let number = location.line.to_string();
+            let pad = " ".repeat(number.len());
+            write!(
+                f,
+                "\n{pad}--> {}:{}:{}\n{pad} |\n{number} | {}\n{pad} | {}{}",
+                location.name,
+                location.line,
+                location.column,
+                location.text,
+                " ".repeat(location.column - 1),
+                "^".repeat(location.width),
+            )?;
+        }
+        if let Some(help) = &self.help {
+            write!(f, "\n= help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnose(source: &str) -> Diagnostic {
+        let error = ENVIRONMENT
+            .template_from_named_str("digest.md", source)
+            .and_then(|t| t.render(minijinja::context! { title => "x" }))
+            .unwrap_err();
+        Diagnostic::new(&error, "digest.md", Some(source), Context::Entry)
+    }
+
+    #[test]
+    fn points_at_undefined_variables() {
+        let diagnostic = diagnose("# Digest\n  {{ titel }}\n");
+        assert_eq!(diagnostic.undefined(), Some("titel"));
+        assert_eq!(
+            diagnostic.to_string(),
+            "undefined variable `titel`
+ --> digest.md:2:6
+  |
+2 |   {{ titel }}
+  |      ^^^^^
+= help: did you mean `title`?"
+        );
+    }
+
+    #[test]
+    fn explains_missing_fields_and_syntax_errors() {
+        let diagnostic = diagnose("{{ description }}");
+        assert!(diagnostic.to_string().ends_with(
+            "= help: this entry has no `description`; check with `{% if description is defined %}`"
+        ));
+        assert!(diagnose("{{ zzzzzz }}").help.is_none());
+        let diagnostic = diagnose("{% for a in autors %}{% endfor %}");
+        assert!(diagnostic.to_string().ends_with("did you mean `authors`?"));
+        let diagnostic = diagnose("ok\n{% if title %}");
+        assert!(diagnostic.undefined().is_none());
+        assert!(diagnostic.to_string().starts_with("syntax error: "));
+        assert!(diagnostic.to_string().contains("--> digest.md:2:"));
+    }
+
+    #[test]
+    fn measures_edit_distance() {
+        assert_eq!(distance("titel", "title"), 1);
+        assert_eq!(distance("titel", "site"), 2);
+        assert_eq!(distance("autor", "author"), 1);
+        assert_eq!(distance("", "url"), 3);
+        assert_eq!(distance("site", "site"), 0);
+    }
+}
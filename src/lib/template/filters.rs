@@ -0,0 +1,275 @@
+//! Filters and functions for formatting bookmarks in templates.
+
+use chrono::{
+    SecondsFormat, Utc,
+    format::{Item, StrftimeItems},
+};
+use ego_tree::iter::Edge;
+use minijinja::{Environment, Error, ErrorKind, Value};
+use scraper::{Html, Node};
+use url::Url;
+
+use crate::{
+    entry,
+    export::{
+        markup::{self, Dialect},
+        yaml,
+    },
+    text,
+};
+
+/// Add spy's filters and the `now()` function to an environment.
+pub(crate) fn register(env: &mut Environment) {
+    env.add_filter("slugify", slugify);
+    env.add_filter("truncate_words", truncate_words);
+    env.add_filter("strip_html", strip_html);
+    env.add_filter("markdown", markdown);
+    env.add_filter("date", date);
+    env.add_filter("domain", domain);
+    env.add_filter("urlencode", urlencode);
+    // Marked safe so shell escaping doesn't quote them again.
+    env.add_filter("shell_quote", |s: &str| {
+        Value::from_safe_string(shell_quote(s))
+    });
+    env.add_filter("json", json);
+    env.add_filter("yaml", yaml);
+    env.add_filter("wrap", wrap);
+    env.add_function("now", now);
+}
+
+fn slugify(s: &str) -> String {
+    text::slugify(s)
+}
+
+/// The first `count` words, followed by `end` (`…` by default) if any were
+/// cut. Whitespace between the words kept is collapsed.
+fn truncate_words(s: &str, count: usize, end: Option<&str>) -> String {
+    let words: Vec<&str> = s.split_whitespace().collect();
+    if words.len() <= count {
+        return words.join(" ");
+    }
+    format!("{}{}", words[..count].join(" "), end.unwrap_or("…"))
+}
+
+/// Elements that can split a word, like `<b>H</b>ello`.
+const INLINE_ELEMENTS: &[&str] = &[
+    "a", "abbr", "b", "bdi", "bdo", "cite", "code", "data", "dfn", "em", "i", "kbd", "mark", "q",
+    "s", "samp", "small", "span", "strong", "sub", "sup", "time", "u", "var",
+];
+
+/// The text of an HTML fragment on one line, without scripts or styles.
+fn strip_html(s: &str) -> String {
+    let fragment = Html::parse_fragment(s);
+    let mut text = String::new();
+    let mut skipping = 0;
+    for edge in fragment.root_element().traverse() {
+        match edge {
+            Edge::Open(node) => match node.value() {
+                Node::Element(e) if matches!(e.name(), "script" | "style") => skipping += 1,
+                // Keep words in neighbouring blocks and cells apart.
+                Node::Element(e) if !INLINE_ELEMENTS.contains(&e.name()) => text.push(' '),
+                Node::Text(t) if skipping == 0 => text.push_str(t),
+                _ => {}
+            },
+            Edge::Close(node) => match node.value() {
+                Node::Element(e) if matches!(e.name(), "script" | "style") => skipping -= 1,
+                _ => {}
+            },
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Convert HTML, such as `full_text`, to Markdown.
+fn markdown(s: &str) -> String {
+    markup::convert(s, Dialect::Markdown, 0)
+}
+
+/// Format an RFC 3339 timestamp or a `YYYY-MM-DD`, `YYYY-MM` or `YYYY`
+/// date with a `strftime` string, `%Y-%m-%d` by default. Missing dates
+/// format as nothing.
+fn date(value: Value, format: Option<&str>) -> Result<String, Error> {
+    if value.is_none() || value.is_undefined() {
+        return Ok(String::new());
+    }
+    let Some(s) = value.as_str() else {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("date expects a date string, got {}", value.kind()),
+        ));
+    };
+    let datetime = entry::parse_date(s)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidOperation, format!("invalid date {s:?}")))?;
+    let items: Vec<Item> = StrftimeItems::new(format.unwrap_or("%Y-%m-%d")).collect();
+    if items.contains(&Item::Error) {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("invalid date format {:?}", format.unwrap_or_default()),
+        ));
+    }
+    Ok(datetime.format_with_items(items.into_iter()).to_string())
+}
+
+/// The host of a URL without any leading `www.`.
+fn domain(s: &str) -> Result<String, Error> {
+    let url = Url::parse(s).map_err(|e| {
+        Error::new(ErrorKind::InvalidOperation, format!("invalid URL {s:?}")).with_source(e)
+    })?;
+    let host = url.host_str().unwrap_or_default();
+    Ok(host.strip_prefix("www.").unwrap_or(host).to_owned())
+}
+
+/// Percent-encode everything but unreserved characters, so the result can
+/// go anywhere in a URL, such as a query parameter.
+fn urlencode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            byte => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// Quote a string as one word for POSIX shells, leaving safe words bare.
+pub(crate) fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "@%+=:,./_-".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_owned()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// JSON, safe to print whatever the escaping: characters that HTML treats
+/// specially are written as `\u` escapes.
+fn json(value: Value) -> Result<Value, Error> {
+    let json = serde_json::to_string(&value).map_err(|e| {
+        Error::new(ErrorKind::InvalidOperation, "failed to write JSON").with_source(e)
+    })?;
+    Ok(Value::from_safe_string(
+        json.replace('<', "\\u003c")
+            .replace('>', "\\u003e")
+            .replace('&', "\\u0026")
+            .replace('\'', "\\u0027"),
+    ))
+}
+
+/// Block-style YAML, without the final line break.
+fn yaml(value: Value) -> Result<String, Error> {
+    let value = serde_json::to_value(&value).map_err(|e| {
+        Error::new(ErrorKind::InvalidOperation, "failed to write YAML").with_source(e)
+    })?;
+    Ok(yaml::to_string(&value).trim_end().to_owned())
+}
+
+/// Break lines at spaces so they fit in `width` (79 by default) columns.
+/// Existing line breaks are kept and longer words are left whole.
+fn wrap(s: &str, width: Option<usize>) -> String {
+    let width = width.unwrap_or(79);
+    let mut out = String::with_capacity(s.len());
+    for (i, line) in s.lines().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let mut column = 0;
+        for word in line.split_whitespace() {
+            let len = word.chars().count();
+            if column > 0 && column + 1 + len > width {
+                out.push('\n');
+                column = 0;
+            } else if column > 0 {
+                out.push(' ');
+                column += 1;
+            }
+            out.push_str(word);
+            column += len;
+        }
+    }
+    out
+}
+
+/// The current time as RFC 3339, for use with `date`.
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{
+        entry::{Entry, entry_strategy},
+        template::{Settings, Template},
+    };
+
+    fn render(template: &str, entry: &Entry) -> String {
+        Template::new(template.to_owned(), Settings::default())
+            .unwrap()
+            .render(entry)
+            .unwrap()
+    }
+
+    #[test]
+    fn formats_text() {
+        assert_eq!(truncate_words("one two  three four", 2, None), "one two…");
+        assert_eq!(truncate_words("one two", 2, Some("...")), "one two");
+        assert_eq!(
+            strip_html("<h1>Hi</h1><p>H<b>ello</b> there</p><script>x()</script>\n<p>again</p>"),
+            "Hi Hello there again"
+        );
+        assert_eq!(wrap("aa bb cc dd\n\nee", Some(5)), "aa bb\ncc dd\n\nee");
+        assert_eq!(urlencode("a b/c?d=é"), "a%20b%2Fc%3Fd%3D%C3%A9");
+        assert_eq!(shell_quote("notes/a-b.md"), "notes/a-b.md");
+        assert_eq!(shell_quote("it's here"), r"'it'\''s here'");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(domain("https://www.example.com/a").unwrap(), "example.com");
+    }
+
+    #[test]
+    fn formats_dates() {
+        let published = Value::from("2024-03-05T10:20:00+01:00");
+        assert_eq!(date(published.clone(), None).unwrap(), "2024-03-05");
+        assert_eq!(
+            date(published, Some("%d %b %Y %H:%M")).unwrap(),
+            "05 Mar 2024 09:20"
+        );
+        assert_eq!(date(Value::from("2024-03-05"), Some("%Y")).unwrap(), "2024");
+        assert_eq!(date(Value::from(()), None).unwrap(), "");
+        assert!(date(Value::from("yesterday"), None).is_err());
+        assert!(date(Value::from("2024-03-05"), Some("%Q")).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn filters_apply_to_entry_fields(entry in entry_strategy()) {
+            prop_assert_eq!(render("{{ title | slugify }}", &entry), text::slugify(entry.title()));
+            let domain = entry.url().host_str().unwrap().trim_start_matches("www.");
+            prop_assert_eq!(render("{{ url | domain }}", &entry), domain);
+            let markdown = markup::convert(entry.full_text(), Dialect::Markdown, 0);
+            prop_assert_eq!(render("{{ full_text | markdown }}", &entry), markdown);
+            let published = entry.published().map(|d| d.format("%Y-%m-%d").to_string());
+            prop_assert_eq!(render("{{ entry.published | date }}", &entry), published.unwrap_or_default());
+            let wrapped = render("{{ full_text | strip_html | wrap(20) }}", &entry);
+            for line in wrapped.lines() {
+                prop_assert!(line.chars().count() <= 20 || !line.contains(' '));
+            }
+            let year = render("{{ now() | date('%Y') }}", &entry);
+            prop_assert_eq!(year, Utc::now().format("%Y").to_string());
+        }
+
+        #[test]
+        fn serialising_filters_keep_values(entry in entry_strategy()) {
+            let json: serde_json::Value = serde_json::from_str(&render("{{ entry | json }}", &entry)).unwrap();
+            prop_assert_eq!(json, serde_json::to_value(&entry).unwrap());
+            let title = yaml::to_string(&serde_json::Value::from(entry.title()));
+            prop_assert_eq!(render("{{ title | yaml }}", &entry), title.trim_end());
+            let quoted = render("{{ title | shell_quote }}", &entry);
+            prop_assert!(quoted.starts_with('\'') || !quoted.contains(' '));
+        }
+    }
+}
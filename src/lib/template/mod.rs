@@ -0,0 +1,762 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Display,
+    path::{Component, Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+
+use clap::ValueEnum;
+use minijinja::{AutoEscape, Environment, ErrorKind, UndefinedBehavior, Value, escape_formatter};
+use once_cell::sync::{Lazy, OnceCell};
+use self_cell::self_cell;
+use thiserror::Error;
+
+use self::diagnostic::Diagnostic;
+use crate::entry::{CollectionTemplateContext, Entry, EntryTemplateContext, EntryView};
+
+pub mod diagnostic;
+mod filters;
+
+/// How templates treat variables that are not defined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum)]
+pub enum Undefined {
+    /// Undefined variables print as nothing and are false in `if`s, but
+    /// reading their attributes is an error.
+    Lenient,
+    /// Like `lenient`, and attributes of undefined values are undefined too.
+    Chainable,
+    /// Any use of an undefined variable other than `is defined` is an error.
+    #[default]
+    Strict,
+}
+
+/// How values printed by templates are escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, ValueEnum)]
+pub enum Escape {
+    /// Choose from the template file's extension: HTML for `.html`, `.htm`,
+    /// `.xhtml` and `.xml`, JSON for `.json`, shell for `.sh`, else none.
+    #[default]
+    Auto,
+    /// Print values as they are.
+    None,
+    /// Escape `<`, `>`, `&` and quotes.
+    Html,
+    /// Print values as JSON, so strings are quoted.
+    Json,
+    /// Quote values as single words for POSIX shells.
+    Shell,
+}
+
+impl Escape {
+    /// The escaping for a template, given its file name.
+    fn for_template(self, name: &str) -> AutoEscape {
+        let escape = match self {
+            Escape::Auto => {
+                let resolved = templates_dir().and_then(|dir| resolve(&dir, name));
+                let extension = match Path::new(name).extension() {
+                    Some(extension) => Some(extension),
+                    None => resolved.as_deref().and_then(Path::extension),
+                };
+                match extension.and_then(|e| e.to_str()) {
+                    Some("html" | "htm" | "xhtml" | "xml") => Escape::Html,
+                    Some("json") => Escape::Json,
+                    Some("sh") => Escape::Shell,
+                    _ => Escape::None,
+                }
+            }
+            escape => escape,
+        };
+        match escape {
+            Escape::Auto | Escape::None => AutoEscape::None,
+            Escape::Html => AutoEscape::Html,
+            Escape::Json => AutoEscape::Json,
+            Escape::Shell => AutoEscape::Custom("shell"),
+        }
+    }
+}
+
+/// Options that change how templates render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Settings {
+    pub undefined: Undefined,
+    pub escape: Escape,
+}
+
+/// The environment for the default settings.
+pub static ENVIRONMENT: Lazy<&Environment> = Lazy::new(|| environment(Settings::default()));
+
+/// One environment per combination of settings, built on first use and
+/// kept for the life of the process.
+static ENVIRONMENTS: Lazy<Mutex<HashMap<Settings, &'static Environment<'static>>>> =
+    Lazy::new(Default::default);
+
+fn environment(settings: Settings) -> &'static Environment<'static> {
+    let mut environments = ENVIRONMENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    environments.entry(settings).or_insert_with(|| {
+        let mut e = Environment::new();
+        e.set_undefined_behavior(match settings.undefined {
+            Undefined::Lenient => UndefinedBehavior::Lenient,
+            Undefined::Chainable => UndefinedBehavior::Chainable,
+            Undefined::Strict => UndefinedBehavior::Strict,
+        });
+        e.set_auto_escape_callback(move |name| settings.escape.for_template(name));
+        e.set_formatter(|out, state, value| match state.auto_escape() {
+            AutoEscape::Custom("shell") if !value.is_safe() => {
+                let text = if value.is_none() || value.is_undefined() {
+                    String::new()
+                } else {
+                    value.to_string()
+                };
+                out.write_str(&filters::shell_quote(&text))?;
+                Ok(())
+            }
+            _ => escape_formatter(out, state, value),
+        });
+        filters::register(&mut e);
+        e.set_loader(|name| match templates_dir() {
+            Some(dir) => load(&dir, name),
+            None => Ok(None),
+        });
+        Box::leak(Box::new(e))
+    })
+}
+
+/// Directory named templates are loaded from, if set before first use.
+static TEMPLATES_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Load named templates (and the targets of `{% include %}` and
+/// `{% extends %}`) from `dir`. Only the first call has an effect.
+pub fn set_templates_dir(dir: PathBuf) {
+    let _ = TEMPLATES_DIR.set(dir);
+}
+
+/// The templates directory: as set, or `$XDG_CONFIG_HOME/spy/templates`.
+pub fn templates_dir() -> Option<PathBuf> {
+    TEMPLATES_DIR.get().cloned().or_else(|| {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+            .map(|d| d.join("spy").join("templates"))
+    })
+}
+
+/// Find a template by name in `dir`: the file of that name, or failing that
+/// the first with that name plus an extension (so `digest` finds
+/// `digest.md`). Names may use `/` for subdirectories but not leave `dir`.
+fn resolve(dir: &Path, name: &str) -> Option<PathBuf> {
+    let relative = Path::new(name);
+    if !relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+    let path = dir.join(relative);
+    if path.is_file() {
+        return Some(path);
+    }
+    let prefix = format!("{}.", path.file_name()?.to_string_lossy());
+    let mut matches: Vec<PathBuf> = std::fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|f| f.path())
+        .filter(|p| {
+            p.is_file()
+                && p.file_name()
+                    .is_some_and(|f| f.to_string_lossy().starts_with(&prefix))
+        })
+        .collect();
+    matches.sort();
+    matches.into_iter().next()
+}
+
+fn load(dir: &Path, name: &str) -> Result<Option<String>, minijinja::Error> {
+    let Some(path) = resolve(dir, name) else {
+        return Ok(None);
+    };
+    std::fs::read_to_string(&path).map(Some).map_err(|e| {
+        minijinja::Error::new(
+            ErrorKind::InvalidOperation,
+            format!("failed to read template {}", path.display()),
+        )
+        .with_source(e)
+    })
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to compile template {name}: {diagnostic}")]
+    TemplateInitialisationError {
+        name: String,
+        diagnostic: Box<Diagnostic>,
+    },
+    #[error("Failed to render template {name} for {url}: {diagnostic}")]
+    RenderFailure {
+        name: String,
+        url: String,
+        diagnostic: Box<Diagnostic>,
+    },
+    #[error("Failed to render template {name} for {count} entries: {diagnostic}")]
+    CollectionRenderFailure {
+        name: String,
+        count: usize,
+        diagnostic: Box<Diagnostic>,
+    },
+    #[error("Failed to read template file {path}: {error}")]
+    ReadFailure {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+}
+
+/// A template's name and, unless it is loaded from the library by name,
+/// its source.
+struct Source {
+    /// Shown in errors and used to find library templates.
+    name: String,
+    text: Option<String>,
+    settings: Settings,
+}
+
+type Compiled<'a> = minijinja::Template<'a, 'a>;
+
+self_cell!(
+    /// A compiled template, borrowing its source.
+    struct Cell {
+        owner: Source,
+        #[not_covariant]
+        dependent: Compiled,
+    }
+);
+
+/// A template string, or the name of a template in the templates directory,
+/// compiled once and reused for every entry rendered with it.
+pub struct Template(Cell);
+
+impl Template {
+    pub fn new(string: String, settings: Settings) -> Result<Self, Error> {
+        Self::compile(Source {
+            name: "<string>".to_owned(),
+            text: Some(string),
+            settings,
+        })
+    }
+
+    /// Read a template from a file. It can include and extend templates in
+    /// the templates directory by name.
+    pub fn from_file(path: &Path, settings: Settings) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path).map_err(|error| Error::ReadFailure {
+            path: path.to_owned(),
+            error,
+        })?;
+        Self::compile(Source {
+            name: path.display().to_string(),
+            text: Some(text),
+            settings,
+        })
+    }
+
+    /// A template from the templates directory, e.g. `org-capture`.
+    pub fn named(name: String, settings: Settings) -> Result<Self, Error> {
+        Self::compile(Source {
+            name,
+            text: None,
+            settings,
+        })
+    }
+
+    fn compile(source: Source) -> Result<Self, Error> {
+        Cell::try_new(source, |source| {
+            let environment = environment(source.settings);
+            match &source.text {
+                Some(text) => environment.template_from_named_str(&source.name, text),
+                None => environment.get_template(&source.name),
+            }
+            .map_err(|e| Error::TemplateInitialisationError {
+                name: source.name.clone(),
+                diagnostic: Box::new(Diagnostic::new(
+                    &e,
+                    &source.name,
+                    source.text.as_deref(),
+                    Context::Entry,
+                )),
+            })
+        })
+        .map(Self)
+    }
+
+    /// Substitute the keys in a template string with the given values
+    pub fn render(&self, entry: &Entry) -> Result<String, Error> {
+        let data = Value::from_serialize(EntryTemplateContext::new(entry));
+        self.0.with_dependent(|source, template| {
+            template.render(data).map_err(|e| Error::RenderFailure {
+                name: source.name.clone(),
+                url: entry.url().to_string(),
+                diagnostic: Box::new(Diagnostic::new(
+                    &e,
+                    &source.name,
+                    source.text.as_deref(),
+                    Context::Entry,
+                )),
+            })
+        })
+    }
+
+    /// Render all the entries into one document.
+    pub fn render_collection(&self, entries: &[Entry]) -> Result<String, Error> {
+        let data = Value::from_serialize(CollectionTemplateContext::new(entries));
+        self.0.with_dependent(|source, template| {
+            template
+                .render(data)
+                .map_err(|e| Error::CollectionRenderFailure {
+                    name: source.name.clone(),
+                    count: entries.len(),
+                    diagnostic: Box::new(Diagnostic::new(
+                        &e,
+                        &source.name,
+                        source.text.as_deref(),
+                        Context::Collection,
+                    )),
+                })
+        })
+    }
+
+    /// The variables the template reads from its context, with attributes
+    /// (`citation.doi`), found without rendering it.
+    pub fn variables(&self) -> BTreeSet<String> {
+        self.0
+            .with_dependent(|_, template| template.undeclared_variables(true))
+            .into_iter()
+            .collect()
+    }
+
+    /// The filters and tests the template calls that spy does not define,
+    /// such as a misspelt `slugfy`. Only the template's own source is
+    /// checked, not what it includes or extends.
+    pub fn unknown_calls(&self) -> BTreeSet<Call> {
+        self.0.with_dependent(|source, _| {
+            let text = match &source.text {
+                Some(text) => Some(text.clone()),
+                None => templates_dir().and_then(|dir| load(&dir, &source.name).ok().flatten()),
+            };
+            let environment = environment(source.settings);
+            calls(text.as_deref().unwrap_or_default())
+                .into_iter()
+                .filter(|call| !call.is_defined(environment))
+                .collect()
+        })
+    }
+}
+
+/// A filter or test called by a template, by name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Call {
+    Filter(String),
+    Test(String),
+}
+
+impl Display for Call {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Call::Filter(name) => write!(f, "filter {name:?}"),
+            Call::Test(name) => write!(f, "test {name:?}"),
+        }
+    }
+}
+
+impl Call {
+    /// Whether `environment` has the filter or test, found by calling it
+    /// on `none`: only a missing one fails as unknown.
+    fn is_defined(&self, environment: &Environment) -> bool {
+        let (expression, unknown) = match self {
+            Call::Filter(name) => (format!("none | {name}"), ErrorKind::UnknownFilter),
+            Call::Test(name) => (format!("none is {name}"), ErrorKind::UnknownTest),
+        };
+        let result = environment
+            .compile_expression(&expression)
+            .and_then(|e| e.eval(()));
+        !matches!(result, Err(e) if e.kind() == unknown)
+    }
+}
+
+/// The filters (`x | name`, `{% filter name %}`) and tests (`x is name`,
+/// `x is not name`) named in a template's tags, found by scanning its
+/// source outside comments, raw blocks and string literals.
+fn calls(source: &str) -> BTreeSet<Call> {
+    let is_name = |t: &&str| t.starts_with(|c: char| c.is_alphabetic() || c == '_');
+    let mut calls = BTreeSet::new();
+    let mut rest = source;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start..];
+        let close = match rest.get(..2) {
+            Some("{{") => "}}",
+            Some("{%") => "%}",
+            Some("{#") => {
+                rest = rest.find("#}").map_or("", |end| &rest[end + 2..]);
+                continue;
+            }
+            _ => {
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let (tokens, after) = tag_tokens(&rest[2..], close);
+        rest = after;
+        let mut words = tokens.iter().copied().skip_while(|&t| t == "-");
+        let first = words.next();
+        if close == "%}" && first == Some("raw") {
+            rest = rest.find("endraw").map_or("", |end| &rest[end..]);
+            continue;
+        }
+        if close == "%}" && first == Some("filter") {
+            calls.extend(
+                words
+                    .next()
+                    .filter(is_name)
+                    .map(|n| Call::Filter(n.to_owned())),
+            );
+        }
+        for (i, token) in tokens.iter().enumerate() {
+            let next = |k: usize| tokens.get(i + k).copied();
+            match *token {
+                "|" => calls.extend(next(1).filter(is_name).map(|n| Call::Filter(n.to_owned()))),
+                "is" => {
+                    let name = if next(1) == Some("not") {
+                        next(2)
+                    } else {
+                        next(1)
+                    };
+                    calls.extend(name.filter(is_name).map(|n| Call::Test(n.to_owned())));
+                }
+                _ => {}
+            }
+        }
+    }
+    calls
+}
+
+/// The names and punctuation in a tag up to `close`, skipping string
+/// literals, and the source after the tag.
+fn tag_tokens<'s>(tag: &'s str, close: &str) -> (Vec<&'s str>, &'s str) {
+    let mut tokens = Vec::new();
+    let mut chars = tag.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if tag[i..].starts_with(close) {
+            return (tokens, &tag[i + close.len()..]);
+        }
+        if c == '"' || c == '\'' {
+            let mut escaped = false;
+            for (_, d) in chars.by_ref() {
+                match d {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    _ if d == c => break,
+                    _ => {}
+                }
+            }
+        } else if c.is_alphabetic() || c == '_' {
+            let mut end = i + c.len_utf8();
+            while let Some(&(j, d)) = chars.peek() {
+                if !(d.is_alphanumeric() || d == '_') {
+                    break;
+                }
+                end = j + d.len_utf8();
+                chars.next();
+            }
+            tokens.push(&tag[i..end]);
+        } else if !c.is_whitespace() {
+            tokens.push(&tag[i..i + c.len_utf8()]);
+        }
+    }
+    (tokens, "")
+}
+
+/// What a template is rendered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Context {
+    /// One entry's fields, the raw `entry`, and `meta` for entries
+    /// extracted with their raw structured data.
+    Entry,
+    /// `entries`, each with an entry's fields, and aggregates over them:
+    /// `count`, `sites` and `date_range` (with `start` and `end`).
+    Collection,
+}
+
+/// Whether templates rendered with `context` can read `variable` (or an
+/// attribute of it, as in `citation.doi`): one of the context's fields, or
+/// a global such as `now`.
+pub fn is_known(variable: &str, context: Context) -> bool {
+    let name = variable.split('.').next().unwrap_or_default();
+    known_variables(context).any(|known| known == name)
+}
+
+fn known_variables(context: Context) -> impl Iterator<Item = &'static str> {
+    let fields: &[&str] = match context {
+        Context::Entry => EntryView::FIELDS,
+        Context::Collection => CollectionTemplateContext::FIELDS,
+    };
+    let entry: &[&str] = match context {
+        Context::Entry => &["entry"],
+        Context::Collection => &[],
+    };
+    fields
+        .iter()
+        .chain(entry)
+        .copied()
+        .chain(ENVIRONMENT.globals().map(|(name, _)| name))
+}
+
+/// Build the MiniJinja value map used to render an entry.
+pub fn context_value(entry: &Entry) -> Value {
+    Value::from_serialize(EntryTemplateContext::new(entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    use crate::entry::{EntryTemplateContext, entry_strategy};
+
+    fn unknown_field_strategy() -> proptest::strategy::BoxedStrategy<String> {
+        prop::string::string_regex("[a-z_]{5,16}")
+            .unwrap()
+            .prop_filter("field must be undefined", |s| !is_known(s, Context::Entry))
+            .boxed()
+    }
+
+    proptest! {
+        #[test]
+        fn rendering_unknown_field_is_error(entry in entry_strategy(), field in unknown_field_strategy()) {
+            let template = Template::new(format!("{{{{ {field} }}}}"), Settings::default()).unwrap();
+            let result = template.render(&entry);
+            let failed = matches!(result, Err(Error::RenderFailure { .. }));
+            prop_assert!(failed);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn rendering_known_title_matches_context(entry in entry_strategy()) {
+            let output = Template::new("{{ title }}".to_string(), Settings::default()).unwrap().render(&entry).unwrap();
+            let context = EntryTemplateContext::new(&entry);
+            let json = serde_json::to_value(context).unwrap();
+            let expected = json.get("title").and_then(|v| v.as_str()).unwrap();
+            prop_assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn finds_unknown_filters_and_tests() {
+        let source = r#"{# {{ x | commented }} #}{{ title | slugfy }} {{ "a | quoted" | upper }}
+{% if notes is not definde and authors is defined %}{{ url | domain }}{% endif %}
+{%- filter shouty %}{{ site }}{% endfilter %}{% raw %}{{ x | raw_only }}{% endraw %}"#;
+        let template = Template::new(source.to_owned(), Settings::default()).unwrap();
+        assert_eq!(
+            template.unknown_calls(),
+            BTreeSet::from([
+                Call::Filter("shouty".into()),
+                Call::Filter("slugfy".into()),
+                Call::Filter("upper".into()),
+                Call::Test("definde".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn library_templates_are_found_by_stem() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("digest.md"), "md").unwrap();
+        std::fs::write(dir.path().join("digest.txt"), "txt").unwrap();
+        std::fs::create_dir(dir.path().join("org")).unwrap();
+        std::fs::write(dir.path().join("org/capture.org"), "org").unwrap();
+        let load = |name| load(dir.path(), name).unwrap();
+        assert_eq!(load("digest").as_deref(), Some("md"));
+        assert_eq!(load("digest.txt").as_deref(), Some("txt"));
+        assert_eq!(load("org/capture").as_deref(), Some("org"));
+        assert_eq!(load("missing"), None);
+        assert_eq!(load("../digest"), None);
+        assert_eq!(load("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn library_templates_include_and_extend_each_other() {
+        // The templates directory is set once per process, so this is the
+        // only test that sets it.
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("base.txt"),
+            "[{% block body %}{% endblock %}]",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("byline.txt"), "from {{ site }}").unwrap();
+        std::fs::write(
+            dir.path().join("note.txt"),
+            "{% extends 'base' %}{% block body %}{{ title }} {% include 'byline' %}{% endblock %}",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("card.html"), "<h1>{{ title }}</h1>").unwrap();
+        set_templates_dir(dir.path().to_owned());
+
+        let url = url::Url::parse("https://example.com/").unwrap();
+        let entry = Entry::unfetched(&url, Some("Fish & <Chips>".into()));
+        let named = |name: &str| Template::named(name.to_owned(), Settings::default()).unwrap();
+        assert_eq!(
+            named("note").render(&entry).unwrap(),
+            "[Fish & <Chips> from example.com]"
+        );
+        assert_eq!(
+            named("card").render(&entry).unwrap(),
+            "<h1>Fish &amp; &lt;Chips&gt;</h1>"
+        );
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "{% include 'byline' %}").unwrap();
+        let template = Template::from_file(file.path(), Settings::default()).unwrap();
+        assert_eq!(template.render(&entry).unwrap(), "from example.com");
+        assert!(matches!(
+            Template::named("missing".to_owned(), Settings::default()),
+            Err(Error::TemplateInitialisationError { .. })
+        ));
+    }
+
+    #[test]
+    fn lists_variables_without_rendering() {
+        let template = Template::new(
+            "{% for a in authors %}{{ a }}{% endfor %}{{ citation.doi }}{{ now() }}{{ colour }}"
+                .to_owned(),
+            Settings::default(),
+        )
+        .unwrap();
+        let variables = template.variables();
+        assert_eq!(
+            variables.iter().map(String::as_str).collect::<Vec<_>>(),
+            ["authors", "citation.doi", "colour", "now"]
+        );
+        let unknown: Vec<_> = variables
+            .iter()
+            .filter(|v| !is_known(v, Context::Entry))
+            .collect();
+        assert_eq!(unknown, ["colour"]);
+    }
+
+    proptest! {
+        #[test]
+        fn render_failures_name_the_entry_and_variable(entry in entry_strategy()) {
+            let error = Template::new("{{ titel }}".to_owned(), Settings::default()).unwrap().render(&entry).unwrap_err();
+            let Error::RenderFailure { url, diagnostic, .. } = error else {
+                panic!("expected a render failure");
+            };
+            prop_assert_eq!(url, entry.url().as_str());
+            prop_assert_eq!(diagnostic.undefined(), Some("titel"));
+        }
+    }
+
+    #[test]
+    fn escaping_follows_the_file_extension() {
+        assert_eq!(Escape::Auto.for_template("digest.html"), AutoEscape::Html);
+        assert_eq!(
+            Escape::Auto.for_template("/tmp/feed.json"),
+            AutoEscape::Json
+        );
+        assert_eq!(
+            Escape::Auto.for_template("open.sh"),
+            AutoEscape::Custom("shell")
+        );
+        assert_eq!(Escape::Auto.for_template("note.md"), AutoEscape::None);
+        assert_eq!(Escape::Auto.for_template("<string>"), AutoEscape::None);
+        assert_eq!(Escape::Html.for_template("note.md"), AutoEscape::Html);
+        assert_eq!(Escape::None.for_template("page.html"), AutoEscape::None);
+    }
+
+    proptest! {
+        #[test]
+        fn settings_control_undefined_and_escaping(entry in entry_strategy()) {
+            let render = |source: &str, undefined, escape| {
+                Template::new(source.to_owned(), Settings { undefined, escape })
+                    .unwrap()
+                    .render(&entry)
+            };
+            let lenient = render("[{{ colour }}]", Undefined::Lenient, Escape::None);
+            prop_assert_eq!(lenient.unwrap(), "[]");
+            prop_assert!(render("{{ colour.name }}", Undefined::Lenient, Escape::None).is_err());
+            let chainable = render("[{{ colour.name }}]", Undefined::Chainable, Escape::None);
+            prop_assert_eq!(chainable.unwrap(), "[]");
+
+            let html = render("{{ title }}", Undefined::Strict, Escape::Html).unwrap();
+            prop_assert!(!html.contains(['<', '>', '"']));
+            let json = render("{\"title\": {{ title }}}", Undefined::Strict, Escape::Json).unwrap();
+            let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(json["title"].as_str(), Some(entry.title()));
+            let shell = render("open {{ url }} {{ title }}", Undefined::Strict, Escape::Shell).unwrap();
+            let expected = format!(
+                "open {} {}",
+                filters::shell_quote(entry.url().as_str()),
+                filters::shell_quote(entry.title())
+            );
+            prop_assert_eq!(shell, expected);
+            let quoted = render("{{ title | shell_quote }}", Undefined::Strict, Escape::Shell).unwrap();
+            prop_assert_eq!(quoted, filters::shell_quote(entry.title()));
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn collections_render_all_entries(entries in prop::collection::vec(entry_strategy(), 0..5)) {
+            let template = Template::new(
+                "{{ count }}|{% for e in entries %}{{ e.title }}|{% endfor %}{{ sites | json }}|\
+                 {% if date_range %}{{ date_range.start }}..{{ date_range.end }}{% endif %}"
+                    .to_owned(),
+                Settings::default(),
+            )
+            .unwrap();
+            let output = template.render_collection(&entries).unwrap();
+            let mut parts = output.split('|');
+            prop_assert_eq!(parts.next().unwrap(), entries.len().to_string());
+            for entry in &entries {
+                prop_assert_eq!(parts.next(), Some(entry.title()));
+            }
+            let sites: Vec<String> = serde_json::from_str(parts.next().unwrap()).unwrap();
+            let mut expected: Vec<&str> = Vec::new();
+            for site in entries.iter().map(Entry::site) {
+                if !expected.contains(&site) {
+                    expected.push(site);
+                }
+            }
+            prop_assert_eq!(sites, expected);
+            let dates: Vec<_> = entries.iter().filter_map(|e| e.published().or(e.added())).collect();
+            let range = parts.next().unwrap();
+            match dates.iter().min().zip(dates.iter().max()) {
+                Some((start, end)) => {
+                    let expected = format!(
+                        "{}..{}",
+                        start.to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                        end.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+                    );
+                    prop_assert_eq!(range, expected);
+                }
+                None => prop_assert_eq!(range, ""),
+            }
+        }
+    }
+
+    #[test]
+    fn settings_share_one_environment_each() {
+        let strict = Settings::default();
+        let shell = Settings {
+            undefined: Undefined::Lenient,
+            escape: Escape::Shell,
+        };
+        assert!(std::ptr::eq(environment(strict), *ENVIRONMENT));
+        assert!(std::ptr::eq(environment(shell), environment(shell)));
+        assert!(!std::ptr::eq(environment(shell), environment(strict)));
+    }
+
+    #[test]
+    fn contexts_know_their_variables() {
+        assert!(is_known("count", Context::Collection));
+        assert!(!is_known("count", Context::Entry));
+        assert!(!is_known("title", Context::Collection));
+    }
+}
@@ -0,0 +1,142 @@
+/// Lowercased words of the text, with markup (anything inside `<...>`)
+/// skipped. Used to compare and index `full_text`, which holds HTML.
+pub(crate) fn words(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut word = String::new();
+    let mut in_tag = false;
+    for ch in text.chars() {
+        match ch {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag && c.is_alphanumeric() => word.extend(c.to_lowercase()),
+            _ => {}
+        }
+        if (in_tag || !ch.is_alphanumeric()) && !word.is_empty() {
+            out.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        out.push(word);
+    }
+    out
+}
+
+/// Longest slug produced, in characters.
+const MAX_SLUG: usize = 80;
+
+/// A lowercase, hyphen-separated form of the text for file names and URLs.
+/// Letters and digits outside ASCII are kept.
+pub(crate) fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let word = word.to_lowercase();
+        let len = slug.chars().count();
+        if len > 0 && len + 1 + word.chars().count() > MAX_SLUG {
+            break;
+        }
+        if len > 0 {
+            slug.push('-');
+        }
+        slug.extend(word.chars().take(MAX_SLUG));
+    }
+    slug
+}
+
+/// The text with accented Latin letters replaced by their plain ASCII
+/// forms, e.g. `Gödel` becomes `Godel` and `Straße` becomes `Strasse`.
+/// Other characters are kept as they are.
+pub(crate) fn fold_ascii(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match fold_char(c) {
+            Some(plain) => out.push_str(plain),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+fn fold_char(c: char) -> Option<&'static str> {
+    Some(match c {
+        'À'..='Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Ĉ' | 'Ċ' | 'Č' => "C",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'Ð' | 'Ď' | 'Đ' => "D",
+        'ð' | 'ď' | 'đ' => "d",
+        'È'..='Ë' | 'Ē' | 'Ĕ' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'Ĝ' | 'Ğ' | 'Ġ' | 'Ģ' => "G",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'Ĥ' | 'Ħ' => "H",
+        'ĥ' | 'ħ' => "h",
+        'Ì'..='Ï' | 'Ĩ' | 'Ī' | 'Ĭ' | 'Į' | 'İ' => "I",
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'Ĵ' => "J",
+        'ĵ' => "j",
+        'Ķ' => "K",
+        'ķ' => "k",
+        'Ĺ' | 'Ļ' | 'Ľ' | 'Ŀ' | 'Ł' => "L",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'Ñ' | 'Ń' | 'Ņ' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'Ò'..='Ö' | 'Ø' | 'Ō' | 'Ŏ' | 'Ő' => "O",
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ŕ' | 'Ŗ' | 'Ř' => "R",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'Ś' | 'Ŝ' | 'Ş' | 'Š' | 'Ș' => "S",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+        'ß' => "ss",
+        'Ţ' | 'Ť' | 'Ŧ' | 'Ț' => "T",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'Þ' => "TH",
+        'þ' => "th",
+        'Ù'..='Ü' | 'Ũ' | 'Ū' | 'Ŭ' | 'Ů' | 'Ű' | 'Ų' => "U",
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'Ŵ' => "W",
+        'ŵ' => "w",
+        'Ý' | 'Ŷ' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_is_skipped() {
+        assert_eq!(
+            words("<p class=\"lead\">Hello, <em>World</em>!</p>Über"),
+            ["hello", "world", "über"]
+        );
+    }
+
+    #[test]
+    fn slugs_are_lowercase_words() {
+        assert_eq!(
+            slugify("Rust in the Kernel: Part 2!"),
+            "rust-in-the-kernel-part-2"
+        );
+        assert_eq!(slugify("  Über   café — déjà vu "), "über-café-déjà-vu");
+        assert_eq!(slugify("?!"), "");
+        assert!(slugify(&"word ".repeat(50)).chars().count() <= MAX_SLUG);
+    }
+
+    #[test]
+    fn accents_fold_to_ascii() {
+        assert_eq!(fold_ascii("Gödel, Łukasiewicz"), "Godel, Lukasiewicz");
+        assert_eq!(fold_ascii("Straße Æsir"), "Strasse AEsir");
+        assert_eq!(fold_ascii("東京"), "東京");
+    }
+}
@@ -0,0 +1,545 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use chrono::{SecondsFormat, Utc};
+use flate2::{Compression, read::MultiGzDecoder, write::GzEncoder};
+use thiserror::Error;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    USER_AGENT,
+    fetch::{self, Fetcher, Method, Response},
+};
+
+/// Headers every recorded request is sent with. Responses are asked for
+/// without compression, so bodies are recorded as the server sent them.
+const REQUEST_HEADERS: &[(&str, &str)] = &[
+    ("user-agent", USER_AGENT),
+    ("accept", "*/*"),
+    ("accept-encoding", "identity"),
+];
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Malformed WARC record at byte {offset}: {message}")]
+    Malformed {
+        offset: usize,
+        message: &'static str,
+    },
+}
+
+/// Writes WARC 1.1 records.
+pub struct Writer<W: Write> {
+    out: W,
+}
+
+impl Writer<File> {
+    /// Append to the WARC file at `path`, creating it if needed.
+    pub fn append(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Writer::new(file)
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Start a WARC stream with a `warcinfo` record describing this tool.
+    pub fn new(out: W) -> io::Result<Self> {
+        let mut writer = Writer { out };
+        let info = format!(
+            "software: spy/{}\r\nformat: WARC File Format 1.1\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        writer.record(
+            "warcinfo",
+            None,
+            Uuid::new_v4(),
+            None,
+            "application/warc-fields",
+            info.as_bytes(),
+        )?;
+        Ok(writer)
+    }
+
+    /// Record a request, sent with `headers` besides `host`, and its response
+    /// as a `response` record followed by a `request` record that points
+    /// back at it. The response's headers are written as received, and its
+    /// body is encoded again as they say it was sent.
+    pub fn exchange(
+        &mut self,
+        method: Method,
+        url: &Url,
+        headers: &[(&str, &str)],
+        response: &Response,
+    ) -> io::Result<()> {
+        let response_id = Uuid::new_v4();
+        let status = response.status;
+        let reason = ureq::http::StatusCode::from_u16(status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or("");
+        let body = encode_body(response)?;
+        let mut block = format!("HTTP/1.1 {status} {reason}\r\n").into_bytes();
+        for (name, value) in &response.headers {
+            // A body compressed again may differ in length from the original.
+            let value = match name.as_str() {
+                "content-length" if body.len() != response.body.len() => body.len().to_string(),
+                _ => value.clone(),
+            };
+            block.extend_from_slice(format!("{name}: {value}\r\n").as_bytes());
+        }
+        block.extend_from_slice(b"\r\n");
+        block.extend_from_slice(&body);
+        self.record(
+            "response",
+            Some(url),
+            response_id,
+            None,
+            "application/http;msgtype=response",
+            &block,
+        )?;
+
+        let host = match url.port() {
+            Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+            None => url.host_str().unwrap_or_default().to_owned(),
+        };
+        let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+        let mut request = format!("{} {path} HTTP/1.1\r\nhost: {host}\r\n", method.as_str());
+        for (name, value) in headers {
+            request.push_str(&format!("{name}: {value}\r\n"));
+        }
+        request.push_str("\r\n");
+        self.record(
+            "request",
+            Some(url),
+            Uuid::new_v4(),
+            Some(response_id),
+            "application/http;msgtype=request",
+            request.as_bytes(),
+        )
+    }
+
+    fn record(
+        &mut self,
+        kind: &str,
+        target: Option<&Url>,
+        id: Uuid,
+        concurrent_to: Option<Uuid>,
+        content_type: &str,
+        block: &[u8],
+    ) -> io::Result<()> {
+        let mut head = format!(
+            "WARC/1.1\r\nWARC-Type: {kind}\r\nWARC-Record-ID: <urn:uuid:{id}>\r\nWARC-Date: {}\r\n",
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+        );
+        if let Some(target) = target {
+            head.push_str(&format!("WARC-Target-URI: {target}\r\n"));
+        }
+        if let Some(other) = concurrent_to {
+            head.push_str(&format!("WARC-Concurrent-To: <urn:uuid:{other}>\r\n"));
+        }
+        head.push_str(&format!(
+            "Content-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+            block.len()
+        ));
+        self.out.write_all(head.as_bytes())?;
+        self.out.write_all(block)?;
+        self.out.write_all(b"\r\n\r\n")?;
+        self.out.flush()
+    }
+}
+
+/// Whether a comma-separated header, such as `transfer-encoding`, lists `coding`.
+fn lists(value: Option<&str>, coding: &str) -> bool {
+    value.is_some_and(|v| v.split(',').any(|c| c.trim().eq_ignore_ascii_case(coding)))
+}
+
+/// A decoded body, compressed and chunked again as the response's headers say.
+/// Only gzip is decoded when fetching, so other codings are left as they are.
+fn encode_body(response: &Response) -> io::Result<Vec<u8>> {
+    let mut body = if lists(response.header("content-encoding"), "gzip") {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&response.body)?;
+        encoder.finish()?
+    } else {
+        response.body.clone()
+    };
+    if lists(response.header("transfer-encoding"), "chunked") {
+        let mut chunked = Vec::with_capacity(body.len() + 16);
+        if !body.is_empty() {
+            chunked.extend_from_slice(format!("{:x}\r\n", body.len()).as_bytes());
+            chunked.extend_from_slice(&body);
+            chunked.extend_from_slice(b"\r\n");
+        }
+        chunked.extend_from_slice(b"0\r\n\r\n");
+        body = chunked;
+    }
+    Ok(body)
+}
+
+/// Undo the framing and compression of a recorded body, keeping it as it
+/// is if that fails.
+fn decode_body(headers: &[(String, String)], body: &[u8]) -> Vec<u8> {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    };
+    let mut body = body.to_vec();
+    if lists(header("transfer-encoding"), "chunked")
+        && let Some(unchunked) = unchunk(&body)
+    {
+        body = unchunked;
+    }
+    if lists(header("content-encoding"), "gzip") {
+        let mut decoded = Vec::new();
+        if MultiGzDecoder::new(body.as_slice())
+            .read_to_end(&mut decoded)
+            .is_ok()
+        {
+            body = decoded;
+        }
+    }
+    body
+}
+
+/// The data of a chunked body.
+fn unchunk(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = usize::from_str_radix(size.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(data);
+        }
+        data.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+/// Fetches over the network, writing every exchange, including each redirect
+/// and sub-request, to a WARC file.
+pub struct Recorder<W: Write> {
+    writer: RefCell<Writer<W>>,
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(writer: Writer<W>) -> Self {
+        Recorder {
+            writer: RefCell::new(writer),
+        }
+    }
+}
+
+impl<W: Write> Fetcher for Recorder<W> {
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        headers: &[(&str, &str)],
+        limit: usize,
+    ) -> Result<Response, ureq::Error> {
+        // Every header is given explicitly, so what is recorded is what is sent.
+        let mut sent: Vec<(&str, &str)> = REQUEST_HEADERS
+            .iter()
+            .filter(|(name, _)| !headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)))
+            .copied()
+            .collect();
+        sent.extend_from_slice(headers);
+        let response = fetch::send(method, url, &sent, limit)?;
+        self.writer
+            .borrow_mut()
+            .exchange(method, url, &sent, &response)?;
+        Ok(response)
+    }
+}
+
+/// Serves requests from the responses in a WARC file, without the network.
+/// When a URL was captured more than once, the last capture wins.
+pub struct Replay {
+    responses: HashMap<Url, Response>,
+    /// Responses to `HEAD` requests, such as archive lookups.
+    heads: HashMap<Url, Response>,
+    order: Vec<Url>,
+}
+
+impl Replay {
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        // Responses in file order, and the method of the request each answered.
+        let mut responses: Vec<(String, Url, Response)> = Vec::new();
+        let mut methods: HashMap<String, Method> = HashMap::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            if data[pos..].iter().all(u8::is_ascii_whitespace) {
+                break;
+            }
+            let malformed = |message| Error::Malformed {
+                offset: pos,
+                message,
+            };
+            let (head, block_start) =
+                split_head(data, pos).ok_or_else(|| malformed("unterminated header"))?;
+            let mut lines = head.lines();
+            if !lines.next().is_some_and(|l| l.starts_with("WARC/")) {
+                return Err(malformed("missing WARC version line"));
+            }
+            let fields: HashMap<String, &str> = lines
+                .filter_map(|l| l.split_once(':'))
+                .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim()))
+                .collect();
+            let len: usize = fields
+                .get("content-length")
+                .and_then(|l| l.parse().ok())
+                .ok_or_else(|| malformed("missing Content-Length"))?;
+            let block_end = block_start
+                .checked_add(len)
+                .ok_or_else(|| malformed("Content-Length out of range"))?;
+            let block = data
+                .get(block_start..block_end)
+                .ok_or_else(|| malformed("truncated record"))?;
+            pos = block_end;
+            while data.get(pos).is_some_and(|b| *b == b'\r' || *b == b'\n') {
+                pos += 1;
+            }
+
+            let http = |msgtype| {
+                fields
+                    .get("content-type")
+                    .is_some_and(|t| t.contains(msgtype))
+            };
+            let id = fields.get("warc-record-id").map(|id| id.to_string());
+            match fields.get("warc-type") {
+                Some(&"response") if http("msgtype=response") => {
+                    let target = fields
+                        .get("warc-target-uri")
+                        .and_then(|u| Url::parse(u.trim_matches(['<', '>'])).ok());
+                    if let Some(target) = target
+                        && let Some(response) = parse_response(block)
+                    {
+                        responses.push((id.unwrap_or_default(), target, response));
+                    }
+                }
+                Some(&"request") if http("msgtype=request") => {
+                    let method = match block.split(|b| *b == b' ').next() {
+                        Some(b"HEAD") => Method::Head,
+                        _ => Method::Get,
+                    };
+                    if let Some(response_id) = fields.get("warc-concurrent-to") {
+                        methods.insert(response_id.to_string(), method);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut replay = Replay {
+            responses: HashMap::new(),
+            heads: HashMap::new(),
+            order: Vec::new(),
+        };
+        for (id, target, response) in responses {
+            if methods.get(&id) == Some(&Method::Head) {
+                replay.heads.insert(target, response);
+            } else if replay.responses.insert(target.clone(), response).is_none() {
+                replay.order.push(target);
+            }
+        }
+        Ok(replay)
+    }
+
+    /// URLs of the successfully captured HTML pages, in capture order.
+    pub fn pages(&self) -> Vec<&Url> {
+        self.order
+            .iter()
+            .filter(|u| {
+                let r = &self.responses[*u];
+                let html = r.mime().is_some_and(|m| m.contains("html"));
+                html && (200..300).contains(&r.status)
+            })
+            .collect()
+    }
+}
+
+impl Fetcher for Replay {
+    fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        _headers: &[(&str, &str)],
+        limit: usize,
+    ) -> Result<Response, ureq::Error> {
+        let recorded = match method {
+            Method::Get => self.responses.get(url),
+            Method::Head => self.heads.get(url).or_else(|| self.responses.get(url)),
+        };
+        let mut response = recorded.cloned().ok_or_else(|| {
+            ureq::Error::Other(format!("{url} was not captured in the WARC file").into())
+        })?;
+        match method {
+            Method::Head => response.body.clear(),
+            Method::Get if response.body.len() > limit => {
+                return Err(ureq::Error::BodyExceedsLimit(limit as u64));
+            }
+            Method::Get => {}
+        }
+        Ok(response)
+    }
+}
+
+/// Split the text header starting at `pos` from the block after its blank line.
+fn split_head(data: &[u8], pos: usize) -> Option<(&str, usize)> {
+    let end = data[pos..].windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = std::str::from_utf8(&data[pos..pos + end]).ok()?;
+    Some((head, pos + end + 4))
+}
+
+fn parse_response(block: &[u8]) -> Option<Response> {
+    let (head, body_start) = split_head(block, 0)?;
+    let mut lines = head.lines();
+    let status = lines.next()?.split_whitespace().nth(1)?.parse().ok()?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|l| l.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_owned()))
+        .collect();
+    let body = decode_body(&headers, &block[body_start..]);
+    Some(Response {
+        status,
+        headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entry::Entry;
+
+    const PAGE: &str = r#"<html><head><title>Archived</title>
+        <link rel="manifest" href="/app.webmanifest"></head><body><p>Hello</p></body></html>"#;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &[u8]) -> Response {
+        Response {
+            status,
+            headers: headers
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_vec(),
+        }
+    }
+
+    fn sample() -> Vec<u8> {
+        let mut writer = Writer::new(Vec::new()).unwrap();
+        let mut exchange = |method, url: &str, response| {
+            let url = Url::parse(url).unwrap();
+            writer
+                .exchange(method, &url, REQUEST_HEADERS, &response)
+                .unwrap();
+        };
+        exchange(
+            Method::Get,
+            "http://example.com/old",
+            response(301, &[("location", "/new")], b""),
+        );
+        let page = [
+            ("content-type", "text/html; charset=utf-8"),
+            ("content-encoding", "gzip"),
+            ("transfer-encoding", "chunked"),
+        ];
+        exchange(
+            Method::Get,
+            "http://example.com/new",
+            response(200, &page, PAGE.as_bytes()),
+        );
+        exchange(
+            Method::Get,
+            "http://example.com/app.webmanifest",
+            response(
+                200,
+                &[("content-type", "application/manifest+json")],
+                br#"{"name": "Example App"}"#,
+            ),
+        );
+        let memento = [("memento-datetime", "Thu, 02 Jan 2020 03:04:05 GMT")];
+        exchange(
+            Method::Head,
+            "http://example.com/new",
+            response(200, &memento, b""),
+        );
+        writer.out
+    }
+
+    #[test]
+    fn records_carry_ids_and_link_requests_to_responses() {
+        let warc = String::from_utf8_lossy(&sample()).into_owned();
+        assert!(warc.starts_with("WARC/1.1\r\nWARC-Type: warcinfo\r\n"));
+        assert_eq!(warc.matches("WARC-Type: response").count(), 4);
+        assert_eq!(warc.matches("WARC-Type: request").count(), 4);
+        assert_eq!(warc.matches("WARC-Record-ID: <urn:uuid:").count(), 9);
+        assert_eq!(warc.matches("WARC-Concurrent-To: <urn:uuid:").count(), 4);
+        assert!(warc.contains(&format!(
+            "GET /old HTTP/1.1\r\nhost: example.com\r\nuser-agent: {USER_AGENT}\r\naccept: */*\r\naccept-encoding: identity\r\n\r\n"
+        )));
+        assert!(warc.contains("HEAD /new HTTP/1.1\r\n"));
+        assert!(warc.contains("content-encoding: gzip\r\ntransfer-encoding: chunked\r\n\r\n"));
+        assert!(
+            !warc.contains("<title>Archived"),
+            "bodies are compressed as received"
+        );
+    }
+
+    #[test]
+    fn replays_entries_without_network() {
+        let replay = Replay::parse(&sample()).unwrap();
+        let new = Url::parse("http://example.com/new").unwrap();
+        assert_eq!(replay.pages(), [&new]);
+
+        let old = Url::parse("http://example.com/old").unwrap();
+        let entry = Entry::fetch(&replay, &old, None).unwrap();
+        assert_eq!(entry.url(), &old);
+        assert_eq!(entry.title(), "Archived");
+        assert_eq!(entry.site(), "Example App");
+        assert!(Entry::fetch(&replay, &Url::parse("http://example.com/x").unwrap(), None).is_err());
+
+        let head = replay.send(Method::Head, &new, &[], 0).unwrap();
+        assert!(head.header("memento-datetime").is_some());
+        let get = replay.send(Method::Get, &new, &[], usize::MAX).unwrap();
+        assert_eq!(get.body, PAGE.as_bytes());
+        assert!(replay.send(Method::Get, &new, &[], 10).is_err());
+    }
+
+    #[test]
+    fn chunked_bodies_round_trip() {
+        for body in [&b""[..], b"hello\r\nworld"] {
+            let response = response(200, &[("transfer-encoding", "chunked")], body);
+            let encoded = encode_body(&response).unwrap();
+            assert_eq!(decode_body(&response.headers, &encoded), body);
+        }
+        assert_eq!(unchunk(b"5;x=y\r\nhello\r\n0\r\n\r\n").unwrap(), b"hello");
+        assert_eq!(unchunk(b"5\r\nhel"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(Replay::parse(b"").unwrap().pages().is_empty());
+        assert!(matches!(
+            Replay::parse(b"GET / HTTP/1.1\r\n\r\n"),
+            Err(Error::Malformed { .. })
+        ));
+        assert!(matches!(
+            Replay::parse(b"WARC/1.1\r\nContent-Length: 99\r\n\r\nshort"),
+            Err(Error::Malformed { .. })
+        ));
+        assert!(matches!(
+            Replay::parse(b"WARC/1.1\r\nContent-Length: 18446744073709551615\r\n\r\nshort"),
+            Err(Error::Malformed { .. })
+        ));
+    }
+}
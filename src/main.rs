@@ -1,5 +1,32 @@
-use clap::Parser;
-use libspy::{cli::Cli, entry::Entry, template::Template};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use libspy::{
+    archive::Memento,
+    check::{self, Checker},
+    cli::{Archive, Cli, Command, Output, TemplateCommand},
+    dedup,
+    entry::{Entry, Extraction},
+    export::{
+        self,
+        epub::Book,
+        feed::{self, Channel},
+    },
+    fetch::{Fetcher, Live},
+    import,
+    search::{Index, Query},
+    snapshot,
+    store::{self, Store},
+    tag::Tag,
+    template::{self, Template},
+    warc::{self, Recorder, Replay},
+};
+use url::Url;
 
 #[cfg(all(feature = "dhat-heap", feature = "dhat-ad-hoc"))]
 compile_error!("Enable only one of `dhat-heap` or `dhat-ad-hoc` at a time.");
@@ -13,15 +40,428 @@ fn main() -> color_eyre::Result<()> {
 
     color_eyre::install()?;
 
-    let cli = Cli::parse();
+    let cli = Cli::try_parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    if let Some(dir) = &cli.output.templates_dir {
+        template::set_templates_dir(dir.clone());
+    }
+    // Compiled before fetching, so mistakes in it show up straight away.
+    let template = cli.output.template()?;
+    let recorder = match &cli.warc {
+        Some(path) => Some(Recorder::new(warc::Writer::append(path)?)),
+        None => None,
+    };
+    let fetcher: &dyn Fetcher = match &recorder {
+        Some(recorder) => recorder,
+        None => &Live,
+    };
+    // URLs in a batch that could not be fetched.
+    let mut failed = 0;
     // Where we store our data
-    let entry = Entry::new(&cli.url, None)?;
-    // println!("{}", serde_json::to_string(&entry)?);
-    let rendered = cli
-        .template
-        .map(Template::new)
-        .map(|t| t.render(&entry).map_err(color_eyre::Report::from))
-        .unwrap_or_else(|| serde_json::to_string(&entry).map_err(color_eyre::Report::from))?;
-    println!("{}", rendered);
+    let entries = match cli.command {
+        Some(Command::Import {
+            source,
+            file,
+            enrich,
+        }) => {
+            let input = std::fs::read_to_string(&file)?;
+            import::parse(source, &input)
+                .into_iter()
+                .map(|b| {
+                    let (entry, warnings) = b.into_entry(enrich.then_some(fetcher));
+                    for warning in warnings {
+                        eprintln!("spy: {warning}");
+                    }
+                    entry
+                })
+                .collect()
+        }
+        Some(Command::Add {
+            url,
+            title,
+            tags,
+            note,
+            on_duplicate,
+        }) => {
+            let mut store = open_store(cli.store.as_deref())?;
+            let tags = tags
+                .iter()
+                .map(|t| Tag::try_from(t.as_str()).map_err(|()| eyre!("Invalid tag {t:?}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            let entry = fetch_entry(fetcher, &url, title, &cli.archive, cli.output.extraction())?
+                .with_tags(tags)
+                .with_notes(note)
+                .with_added(Some(Utc::now()));
+            let duplicate = dedup::find(store.entries(), &entry).map(|d| {
+                eprintln!(
+                    "spy: {url} duplicates entry {} ({})",
+                    d.existing.id(),
+                    d.reason
+                );
+                d.existing.clone()
+            });
+            let entry = match on_duplicate.resolve(entry, duplicate.as_ref()) {
+                Some(entry) => {
+                    store.put(entry.clone())?;
+                    entry
+                }
+                None => duplicate.expect("only duplicates are skipped"),
+            };
+            vec![entry]
+        }
+        Some(Command::Batch { file }) => {
+            let urls = read_urls(&file)?;
+            let mut entries = Vec::with_capacity(urls.len());
+            for url in urls {
+                match fetch_entry(fetcher, &url, None, &cli.archive, cli.output.extraction()) {
+                    Ok(entry) => entries.push(entry),
+                    Err(e) => {
+                        eprintln!("spy: failed to fetch {url}: {e}");
+                        failed += 1;
+                    }
+                }
+            }
+            entries
+        }
+        Some(Command::List {
+            filter,
+            sort,
+            limit,
+            input,
+        }) => {
+            let mut entries = match input {
+                Some(path) => read_input(&path)?,
+                None => open_store(cli.store.as_deref())?.entries().to_vec(),
+            };
+            if let Some(filter) = filter {
+                entries.retain(|e| filter.matches(e));
+            }
+            if let Some(sort) = sort {
+                sort.apply(&mut entries);
+            }
+            entries.truncate(limit.unwrap_or(usize::MAX));
+            entries
+        }
+        Some(Command::Show { key }) => vec![open_store(cli.store.as_deref())?.get(&key)?.clone()],
+        Some(Command::Search { query, limit }) => {
+            let store = open_store(cli.store.as_deref())?;
+            let query = Query::parse(&query.join(" "))?;
+            Index::new(store.entries())
+                .search(&query)
+                .into_iter()
+                .take(limit.unwrap_or(usize::MAX))
+                .map(|hit| hit.entry.clone())
+                .collect()
+        }
+        Some(Command::Refresh {
+            key,
+            all: _,
+            older_than,
+        }) => {
+            let mut store = open_store(cli.store.as_deref())?;
+            let stale: Vec<Entry> = match (&key, older_than) {
+                (Some(key), _) => vec![store.get(key)?.clone()],
+                (None, Some(age)) => {
+                    let cutoff = Utc::now() - age;
+                    store
+                        .entries()
+                        .iter()
+                        .filter(|e| e.refreshed().or(e.added()).is_none_or(|d| *d < cutoff))
+                        .cloned()
+                        .collect()
+                }
+                (None, None) => store.entries().to_vec(),
+            };
+            let mut refreshed = Vec::new();
+            for entry in stale {
+                let (fresh, changed) = match entry.refresh(fetcher) {
+                    Ok(result) => result,
+                    // A single entry was asked for, so its failure is the command's.
+                    Err(e) if key.is_some() => return Err(e.into()),
+                    Err(e) => {
+                        eprintln!("spy: failed to refresh {}: {e}", entry.id());
+                        continue;
+                    }
+                };
+                if changed.is_empty() {
+                    eprintln!("spy: {} is unchanged", entry.id());
+                } else {
+                    eprintln!("spy: {} changed {}", entry.id(), changed.join(", "));
+                }
+                store.refresh(fresh.clone(), &changed)?;
+                refreshed.push(fresh);
+            }
+            refreshed
+        }
+        Some(Command::Check {
+            input,
+            report,
+            soft_404,
+        }) => {
+            let entries = match input {
+                Some(path) => read_input(&path)?,
+                None => open_store(cli.store.as_deref())?.entries().to_vec(),
+            };
+            let mut checker = Checker::new(fetcher, soft_404);
+            let reports: Vec<_> = entries.iter().map(|e| checker.check(e)).collect();
+            println!("{}", check::render(report, &reports)?);
+            return Ok(());
+        }
+        Some(Command::Snapshot {
+            url,
+            output,
+            reader,
+            max_size,
+        }) => {
+            let (page, reader_page) = snapshot::take(fetcher, &url, max_size, reader.is_some())?;
+            match output {
+                Some(path) => std::fs::write(path, page)?,
+                None => println!("{page}"),
+            }
+            if let (Some(path), Some(reader_page)) = (reader, reader_page) {
+                std::fs::write(path, reader_page)?;
+            }
+            return Ok(());
+        }
+        Some(Command::Replay { file, urls }) => {
+            let replay = Replay::parse(&std::fs::read(&file)?)?;
+            let urls = if urls.is_empty() {
+                replay.pages().into_iter().cloned().collect()
+            } else {
+                urls
+            };
+            urls.iter()
+                .map(|url| Entry::fetch(&replay, url, None))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        Some(Command::Feed {
+            kind,
+            title,
+            link,
+            feed_url,
+            description,
+            author,
+            full_text,
+            filter,
+            sort,
+            limit,
+            input,
+            output,
+        }) => {
+            let mut entries = match input {
+                Some(path) => read_input(&path)?,
+                None => open_store(cli.store.as_deref())?.entries().to_vec(),
+            };
+            if let Some(filter) = filter {
+                entries.retain(|e| filter.matches(e));
+            }
+            sort.apply(&mut entries);
+            entries.truncate(limit.unwrap_or(usize::MAX));
+            let channel = Channel {
+                title,
+                link,
+                feed_url,
+                description,
+                author,
+                full_text,
+            };
+            let document = feed::render(kind, &channel, &entries)?;
+            match output {
+                Some(path) => std::fs::write(&path, document + "\n")
+                    .map_err(|e| eyre!("Failed to write {}: {e}", path.display()))?,
+                None => println!("{document}"),
+            }
+            return Ok(());
+        }
+        Some(Command::Epub {
+            keys,
+            output,
+            filter,
+            input,
+            title,
+            language,
+            max_size,
+        }) => {
+            let mut entries = match input {
+                Some(path) => read_input(&path)?,
+                None if keys.is_empty() => open_store(cli.store.as_deref())?.entries().to_vec(),
+                None => {
+                    let store = open_store(cli.store.as_deref())?;
+                    keys.iter()
+                        .map(|key| store.get(key).cloned())
+                        .collect::<Result<Vec<_>, _>>()?
+                }
+            };
+            if let Some(filter) = filter {
+                entries.retain(|e| filter.matches(e));
+            }
+            if entries.is_empty() {
+                return Err(eyre!("No entries to put in the book"));
+            }
+            let fetch = |url: &Url, limit| snapshot::fetch_resource(fetcher, url, limit);
+            let book = Book::new(max_size, fetch)
+                .with_title(title)
+                .with_language(language);
+            let file = File::create(&output)
+                .map_err(|e| eyre!("Failed to create {}: {e}", output.display()))?;
+            book.write(&entries, BufWriter::new(file))?.flush()?;
+            return Ok(());
+        }
+        Some(Command::Rm { key }) => {
+            let mut store = open_store(cli.store.as_deref())?;
+            let id = *store.get(&key)?.id();
+            vec![store.remove(&id)?]
+        }
+        Some(Command::Template {
+            command: TemplateCommand::Check { file },
+        }) => {
+            let template = Template::from_file(&file, cli.output.settings())?;
+            let (variables, context) = (template.variables(), cli.output.context());
+            for variable in &variables {
+                println!("{variable}");
+            }
+            let mut warnings = 0;
+            for variable in variables.iter().filter(|v| !template::is_known(v, context)) {
+                eprintln!(
+                    "spy: warning: {} uses unknown variable {variable:?}",
+                    file.display()
+                );
+                warnings += 1;
+            }
+            for call in template.unknown_calls() {
+                eprintln!("spy: warning: {} uses unknown {call}", file.display());
+                warnings += 1;
+            }
+            if warnings > 0 {
+                return Err(eyre!("{} has {warnings} warning(s)", file.display()));
+            }
+            return Ok(());
+        }
+        None => {
+            let url = cli.url.expect("clap requires a URL without a subcommand");
+            vec![fetch_entry(
+                fetcher,
+                &url,
+                None,
+                &cli.archive,
+                cli.output.extraction(),
+            )?]
+        }
+    };
+    match &cli.output.output_dir {
+        Some(dir) => write_files(&cli.output, dir, &entries)?,
+        None => println!("{}", render(&cli.output, template.as_ref(), &entries)?),
+    }
+    if failed > 0 {
+        return Err(eyre!("failed to fetch {failed} of the URLs"));
+    }
+    Ok(())
+}
+
+/// Fetch and extract a page, from an archived snapshot with `--archived`, or
+/// with `--archive-fallback` when the live page cannot be fetched.
+fn fetch_entry(
+    fetcher: &dyn Fetcher,
+    url: &Url,
+    title: Option<String>,
+    archive: &Archive,
+    extraction: Extraction,
+) -> color_eyre::Result<Entry> {
+    let provider = Memento::new(archive.timegate.clone());
+    if archive.archived {
+        return Ok(Entry::archived(url, title, &provider, fetcher, extraction)?);
+    }
+    match Entry::fetch_with(fetcher, url, title.clone(), extraction) {
+        Ok(entry) => Ok(entry),
+        Err(e) if archive.archive_fallback => {
+            eprintln!("spy: {e}; trying the archive");
+            let entry = Entry::archived(url, title, &provider, fetcher, extraction)?;
+            if let Some(snapshot) = entry.archive() {
+                eprintln!(
+                    "spy: using the snapshot of {url} captured {}",
+                    snapshot.captured.format("%Y-%m-%d %H:%M:%S UTC")
+                );
+            }
+            Ok(entry)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn open_store(path: Option<&Path>) -> color_eyre::Result<Store> {
+    let path = match path {
+        Some(path) => path.to_path_buf(),
+        None => Store::default_path()?,
+    };
+    Ok(Store::open(path)?)
+}
+
+/// Read JSONL entries from a file, or from stdin for `-`.
+fn read_input(path: &Path) -> color_eyre::Result<Vec<Entry>> {
+    if path == Path::new("-") {
+        return Ok(store::read_entries(std::io::stdin().lock(), path)?);
+    }
+    let file = File::open(path).map_err(|e| eyre!("Failed to open {}: {e}", path.display()))?;
+    Ok(store::read_entries(BufReader::new(file), path)?)
+}
+
+/// Read a list of URLs, one per line, skipping blank lines and `#` comments.
+fn read_urls(path: &Path) -> color_eyre::Result<Vec<Url>> {
+    let text = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin().lock())?
+    } else {
+        std::fs::read_to_string(path)
+            .map_err(|e| eyre!("Failed to open {}: {e}", path.display()))?
+    };
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            Url::parse(line)
+                .map_err(|e| eyre!("{}:{}: invalid URL {line:?}: {e}", path.display(), i + 1))
+        })
+        .collect()
+}
+
+/// Write each entry to its own file in `dir`, printing the paths written.
+/// Names that are already taken get a numeric suffix.
+fn write_files(output: &Output, dir: &Path, entries: &[Entry]) -> color_eyre::Result<()> {
+    std::fs::create_dir_all(dir).map_err(|e| eyre!("Failed to create {}: {e}", dir.display()))?;
+    let extension = output.format.extension();
+    for entry in entries {
+        let stem = export::file_stem(entry, output.naming);
+        let mut path = dir.join(format!("{stem}.{extension}"));
+        let mut n = 2;
+        while path.exists() {
+            path = dir.join(format!("{stem}-{n}.{extension}"));
+            n += 1;
+        }
+        let mut document = export::render(
+            output.format,
+            std::slice::from_ref(entry),
+            &output.options(),
+        )?;
+        document.push('\n');
+        std::fs::write(&path, document)
+            .map_err(|e| eyre!("Failed to write {}: {e}", path.display()))?;
+        println!("{}", path.display());
+    }
     Ok(())
 }
+
+/// Render entries with the template if one was given, otherwise in the chosen format.
+fn render(
+    output: &Output,
+    template: Option<&Template>,
+    entries: &[Entry],
+) -> color_eyre::Result<String> {
+    match template {
+        Some(template) if output.collection => Ok(template.render_collection(entries)?),
+        Some(template) => Ok(entries
+            .iter()
+            .map(|e| template.render(e))
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n")),
+        None => Ok(export::render(output.format, entries, &output.options())?),
+    }
+}